@@ -0,0 +1,72 @@
+/// Length of the echo path the filter can model. Covers the speaker→mic
+/// delay left after the mixer lines the two streams up, plus a short room
+/// tail; longer paths are only partly cancelled.
+const FILTER_MS: usize = 40;
+/// NLMS step size (0–2). Lower adapts slower but is more stable.
+const STEP_SIZE: f32 = 0.2;
+/// Far-end energy below which the filter does not adapt: nothing is playing,
+/// so there is no echo to learn from.
+const MIN_FAR_ENERGY: f32 = 1e-6;
+/// Geigel double-talk threshold: a mic sample louder than this fraction of
+/// the recent far-end peak is taken as local speech and freezes adaptation,
+/// so the filter doesn't learn to cancel the speaker's own voice.
+const DOUBLE_TALK_RATIO: f32 = 0.5;
+
+/// Normalized-LMS acoustic echo canceller.
+///
+/// Models the path from what the speakers played (far end, the loopback
+/// stream) to what the microphone heard (near end) with an adaptive FIR
+/// filter, and subtracts the predicted echo from the mic signal.
+pub struct EchoCanceller {
+    weights: Vec<f32>,
+    /// Far-end history stored twice, so the latest `taps` samples are always
+    /// one contiguous slice (newest first) starting at `pos`.
+    history: Vec<f32>,
+    pos: usize,
+    /// Sum of squares of the far-end samples in the window.
+    energy: f32,
+}
+
+impl EchoCanceller {
+    pub fn new(sample_rate: u32) -> Self {
+        let taps = (sample_rate as usize * FILTER_MS / 1000).max(1);
+        Self {
+            weights: vec![0.0; taps],
+            history: vec![0.0; taps * 2],
+            pos: 0,
+            energy: 0.0,
+        }
+    }
+
+    /// Feed one frame: `far` is the mono loopback sample, `near` the mic
+    /// sample recorded at the same moment. Returns the mic sample with the
+    /// estimated echo removed.
+    #[inline]
+    pub fn process(&mut self, far: f32, near: f32) -> f32 {
+        let taps = self.weights.len();
+        self.pos = self.pos.checked_sub(1).unwrap_or(taps - 1);
+        let leaving = self.history[self.pos];
+        self.history[self.pos] = far;
+        self.history[self.pos + taps] = far;
+        // Clamped: rounding can push the running sum slightly negative
+        self.energy = (self.energy + far * far - leaving * leaving).max(0.0);
+
+        let window = &self.history[self.pos..self.pos + taps];
+        let mut echo = 0.0f32;
+        let mut far_peak = 0.0f32;
+        for (&w, &x) in self.weights.iter().zip(window) {
+            echo += w * x;
+            far_peak = far_peak.max(x.abs());
+        }
+        let error = near - echo;
+
+        let double_talk = near.abs() > DOUBLE_TALK_RATIO * far_peak;
+        if self.energy > MIN_FAR_ENERGY && !double_talk {
+            let step = STEP_SIZE * error / self.energy;
+            for (w, &x) in self.weights.iter_mut().zip(window) {
+                *w += step * x;
+            }
+        }
+        error
+    }
+}
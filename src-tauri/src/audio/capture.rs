@@ -1,49 +1,305 @@
 use crate::error::AppError;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 
-use super::wasapi::{ComGuard, LoopbackSession};
+use super::mix::StreamMixer;
+use super::resample::{FormatConverter, ASR_SAMPLE_RATE};
+use super::flac::FlacWriter;
+use super::memory::MemorySink;
+#[cfg(feature = "mp3")]
+use super::mp3::Mp3Writer;
+use super::ring::{FrameRing, RingSink};
+use super::sink::{append_raw_samples, AudioSink};
+use super::spectrum::{SpectrumAnalyzer, SPECTRUM_BANDS};
+use super::meter::{compute_levels, compute_rms, Level, MAX_METER_CHANNELS};
+use super::wasapi::{BufferWait, ComGuard, DefaultDeviceWatcher, LoopbackSession, MmcssGuard};
 use super::wav::AudioWavWriter;
+use super::{
+    AudioFormat, CaptureConfig, CaptureFinished, CaptureInfo, MicMix, RealtimeDenoiser,
+    RecordingFormat, RNNOISE_SAMPLE_RATE,
+};
 
-/// Payload emitted to the frontend every ~100 ms with the current RMS audio level.
+/// Frequency bars for a visualizer, emitted with each `audio-level` event
+/// when the capture was started with `spectrum` on.
+#[derive(Clone, serde::Serialize)]
+pub struct AudioSpectrumEvent {
+    /// Log-spaced bands from 40 Hz to Nyquist, each 0.0 (−80 dBFS) to 1.0 (0 dBFS).
+    pub bands: [f32; SPECTRUM_BANDS],
+}
+
+/// Payload emitted to the frontend every ~100 ms with the current audio level.
 #[derive(Clone, serde::Serialize)]
 pub struct AudioLevelEvent {
     /// RMS level in 0.0–1.0 range.
     pub level: f32,
+    /// RMS level of each channel of the recording in file order (e.g.
+    /// L, R for stereo), for a multichannel meter. Capped at 8 channels.
+    pub channel_levels: Vec<f32>,
+    /// Largest absolute sample since the last event, 0.0–1.0; at or near
+    /// 1.0 the recording is clipping.
+    pub peak: f32,
+    /// RMS level of the system (loopback) source before mixing; only set
+    /// when the microphone is mixed in.
+    pub system_level: Option<f32>,
+    /// RMS level of the microphone source before mixing, if mixed in.
+    pub mic_level: Option<f32>,
+}
+
+/// Payload of `clipping-detected`, emitted at most once per second while
+/// the recording clips.
+#[derive(Clone, serde::Serialize)]
+pub struct ClippingEvent {
+    /// Samples at or above full scale since the previous event.
+    pub clipped_samples: u32,
+    /// Unix time of the event, in milliseconds.
+    pub timestamp_ms: u64,
+}
+
+/// Payload of `format-changed`, emitted when the device's mix format changed
+/// mid-recording and the stream was reopened. The file keeps its format;
+/// the new audio is converted to it.
+#[derive(Clone, serde::Serialize)]
+pub struct FormatChangedEvent {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    pub is_float: bool,
+}
+
+/// Payload of `capture-timeout`, emitted once per stall when the buffer
+/// wait keeps timing out with nothing to read — usually nothing is playing.
+#[derive(Clone, serde::Serialize)]
+pub struct CaptureTimeoutEvent {
+    /// Consecutive empty timeouts so far.
+    pub consecutive_timeouts: u32,
+}
+
+/// Payload of `capture-stats`, emitted alongside `audio-level`.
+#[derive(Clone, serde::Serialize)]
+pub struct CaptureStatsEvent {
+    /// Frames written to the file so far.
+    pub frames_written: u64,
+    /// Wall-clock time since the capture loop started.
+    pub elapsed_ms: u64,
+    /// Packets WASAPI flagged as discontinuous (audio was dropped).
+    pub glitch_count: u64,
+    /// Current size of the output file, header included.
+    pub current_file_bytes: u64,
+}
+
+/// Counters accumulated while draining packets.
+#[derive(Default)]
+struct CaptureStats {
+    glitch_count: u64,
+    /// A discontinuity was seen since the capture loop last checked the
+    /// mix format.
+    discontinuity: bool,
+}
+
+impl CaptureStats {
+    #[inline]
+    fn record_flags(&mut self, flags: u32) {
+        // AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY = 0x1
+        if (flags & 0x1) != 0 {
+            self.glitch_count += 1;
+            self.discontinuity = true;
+        }
+    }
+}
+
+/// Flags shared between a `SystemAudioHandle` and its capture thread.
+#[derive(Default)]
+struct CaptureControl {
+    stop: AtomicBool,
+    paused: AtomicBool,
+    /// While paused: write silence (keeps the timeline) instead of dropping audio.
+    pause_silence: AtomicBool,
 }
 
-/// Handle to a running system-audio capture session.
+impl CaptureControl {
+    fn packet_mode(&self) -> PacketMode {
+        if !self.paused.load(Ordering::Acquire) {
+            PacketMode::Record
+        } else if self.pause_silence.load(Ordering::Acquire) {
+            PacketMode::Silence
+        } else {
+            PacketMode::Discard
+        }
+    }
+}
+
+/// What to do with the packets drained in one iteration.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PacketMode {
+    Record,
+    /// Paused, writing silence in place of the audio.
+    Silence,
+    /// Paused, dropping the audio.
+    Discard,
+}
+
+/// Handle to a running system-audio capture session. `stop()` returns `T`:
+/// the file path for file captures, the samples for memory captures.
 ///
 /// On drop: signals the capture thread to stop and waits for it to finish.
-pub struct SystemAudioHandle {
-    stop_flag: Arc<AtomicBool>,
-    join_handle: Option<thread::JoinHandle<Result<String, AppError>>>,
+pub struct SystemAudioHandle<T = String> {
+    control: Arc<CaptureControl>,
+    finished: Arc<CaptureFinished>,
+    join_handle: Option<thread::JoinHandle<Result<T, AppError>>>,
+    info: CaptureInfo,
+    started_at: Instant,
+    output_path: Option<String>,
 }
 
 impl SystemAudioHandle {
-    /// Spawn a dedicated capture thread.
+    /// Spawn a dedicated capture thread recording to `output_path`.
+    /// `config.device_id` selects a render endpoint; `None` (or an unknown ID) uses the default.
     /// `app` is used to emit real-time audio level events to the frontend.
-    pub fn start(output_path: String, app: AppHandle) -> Result<Self, AppError> {
-        let stop_flag = Arc::new(AtomicBool::new(false));
-        let flag_clone = stop_flag.clone();
+    ///
+    /// Blocks until the thread has opened the device, so setup errors are
+    /// returned here instead of from `stop()`.
+    pub fn start(
+        output_path: String,
+        config: CaptureConfig,
+        app: AppHandle,
+    ) -> Result<Self, AppError> {
+        let path = output_path.clone();
+        Self::spawn(config, app, Some(output_path), move |config, control, app, ready| {
+            run_capture(&path, config, control, app, ready)
+        })
+    }
+}
+
+impl SystemAudioHandle<Vec<f32>> {
+    /// Like `start`, but keeps the audio in memory; `stop()` returns it as
+    /// mono 16 kHz samples.
+    pub fn start_memory(config: CaptureConfig, app: AppHandle) -> Result<Self, AppError> {
+        Self::spawn(config, app, None, run_memory_capture)
+    }
+}
+
+impl SystemAudioHandle<()> {
+    /// Like `start`, but keeps only the newest audio in `ring`, which stays
+    /// readable while the capture runs.
+    pub(super) fn start_ring(
+        config: CaptureConfig,
+        app: AppHandle,
+        ring: Arc<Mutex<FrameRing>>,
+    ) -> Result<Self, AppError> {
+        Self::spawn(config, app, None, move |config, control, app, ready| {
+            run_ring_capture(ring, config, control, app, ready)
+        })
+    }
+}
+
+impl<T: Send + 'static> SystemAudioHandle<T> {
+    /// Run `run` on a dedicated capture thread and wait for it to report
+    /// the opened device.
+    fn spawn(
+        config: CaptureConfig,
+        app: AppHandle,
+        output_path: Option<String>,
+        run: impl FnOnce(
+                &CaptureConfig,
+                &CaptureControl,
+                &AppHandle,
+                SyncSender<Result<CaptureInfo, AppError>>,
+            ) -> Result<T, AppError>
+            + Send
+            + 'static,
+    ) -> Result<Self, AppError> {
+        let control = Arc::new(CaptureControl::default());
+        let thread_control = control.clone();
+        let finished = Arc::new(CaptureFinished::default());
+        let thread_finished = FinishedOnExit(finished.clone());
+        let (ready_tx, ready_rx) = mpsc::sync_channel(1);
 
         let join_handle = thread::Builder::new()
             .name("audio-capture".into())
             .stack_size(512 * 1024) // 512 KB — capture thread needs very little stack
-            .spawn(move || run_capture(&output_path, &flag_clone, &app))
+            .spawn(move || {
+                let _finished = thread_finished;
+                run(&config, &thread_control, &app, ready_tx)
+            })
             .map_err(|e| AppError::AudioCapture(format!("Spawn capture thread: {e}")))?;
 
-        Ok(Self {
-            stop_flag,
-            join_handle: Some(join_handle),
-        })
+        match ready_rx.recv() {
+            Ok(Ok(info)) => Ok(Self {
+                control,
+                finished,
+                join_handle: Some(join_handle),
+                info: CaptureInfo { output_path: output_path.clone(), ..info },
+                started_at: Instant::now(),
+                output_path,
+            }),
+            Ok(Err(e)) => {
+                let _ = join_handle.join();
+                Err(e)
+            }
+            // Sender dropped without reporting — the thread died during setup
+            Err(_) => match join_handle.join() {
+                Ok(Err(e)) => Err(e),
+                _ => Err(AppError::CaptureThreadPanicked),
+            },
+        }
+    }
+
+    /// Device details reported by the capture thread at startup.
+    pub fn info(&self) -> &CaptureInfo {
+        &self.info
+    }
+
+    /// Time since the device was opened, paused spans included.
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// File being recorded to; `None` for in-memory captures.
+    pub fn output_path(&self) -> Option<&str> {
+        self.output_path.as_deref()
+    }
+
+    /// Signalled once the capture thread exits, e.g. at `max_duration_ms`.
+    /// It can be waited on after the handle's lock is released.
+    pub fn finished(&self) -> Arc<CaptureFinished> {
+        self.finished.clone()
+    }
+
+    /// Pause recording without finalizing the WAV.
+    ///
+    /// The thread keeps draining WASAPI so nothing backs up. With
+    /// `keep_timeline` the paused span is written as silence; otherwise it
+    /// is dropped and the recording simply resumes where it left off.
+    pub fn pause(&self, keep_timeline: bool) -> Result<(), AppError> {
+        if self.join_handle.is_none() {
+            return Err(AppError::CaptureAlreadyStopped);
+        }
+        if self.control.paused.load(Ordering::Acquire) {
+            return Err(AppError::CaptureAlreadyPaused);
+        }
+        self.control.pause_silence.store(keep_timeline, Ordering::Release);
+        self.control.paused.store(true, Ordering::Release);
+        Ok(())
     }
 
-    /// Signal the capture thread to stop and return the WAV file path.
-    pub fn stop(&mut self) -> Result<String, AppError> {
-        self.stop_flag.store(true, Ordering::Release);
+    /// Resume a paused recording.
+    pub fn resume(&self) -> Result<(), AppError> {
+        if self.join_handle.is_none() {
+            return Err(AppError::CaptureAlreadyStopped);
+        }
+        if !self.control.paused.swap(false, Ordering::AcqRel) {
+            return Err(AppError::CaptureNotPaused);
+        }
+        Ok(())
+    }
+
+    /// Signal the capture thread to stop and return its output.
+    pub fn stop(&mut self) -> Result<T, AppError> {
+        self.control.stop.store(true, Ordering::Release);
 
         match self.join_handle.take() {
             Some(handle) => handle
@@ -52,11 +308,46 @@ impl SystemAudioHandle {
             None => Err(AppError::CaptureAlreadyStopped),
         }
     }
+
+    /// Stop the capture and delete its file instead of keeping it. The
+    /// thread is joined first, so the writer and its file handle are gone
+    /// before the file is removed (Windows won't delete an open file).
+    pub fn abort(&mut self) -> Result<(), AppError> {
+        self.control.stop.store(true, Ordering::Release);
+
+        let Some(handle) = self.join_handle.take() else {
+            return Err(AppError::CaptureAlreadyStopped);
+        };
+        // The take is being thrown away, so how the thread ended doesn't matter
+        let _ = handle.join();
+
+        if let Some(path) = &self.output_path {
+            match std::fs::remove_file(path) {
+                Ok(()) => eprintln!("[capture] Discarded {path}"),
+                // e.g. an empty capture with `EmptyCapture::Reject` already removed it
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(AppError::AudioCapture(format!("Delete discarded recording: {e}")))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Signals `CaptureFinished` when dropped, so waiters wake up even if the
+/// capture thread panics.
+struct FinishedOnExit(Arc<CaptureFinished>);
+
+impl Drop for FinishedOnExit {
+    fn drop(&mut self) {
+        self.0.signal();
+    }
 }
 
-impl Drop for SystemAudioHandle {
+impl<T> Drop for SystemAudioHandle<T> {
     fn drop(&mut self) {
-        self.stop_flag.store(true, Ordering::Release);
+        self.control.stop.store(true, Ordering::Release);
         if let Some(handle) = self.join_handle.take() {
             let _ = handle.join();
         }
@@ -65,85 +356,616 @@ impl Drop for SystemAudioHandle {
 
 // ── Capture thread ──────────────────────────────────────────────────
 
+/// How often to check whether the default output device changed.
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Payload of `device-changed`, emitted when capture follows the default
+/// output to a new device.
+#[derive(Clone, serde::Serialize)]
+pub struct DeviceChangedEvent {
+    pub device_id: String,
+    pub device_name: String,
+    /// Native rate of the new device (audio is resampled to the file's rate).
+    pub sample_rate: u32,
+}
+
+/// The loopback source feeding the file.
+struct SystemSource {
+    session: LoopbackSession,
+    /// Set when recording at a forced sample rate or once capture has
+    /// switched devices: converts the device's audio to the format the file
+    /// was created with.
+    converter: Option<FormatConverter>,
+    /// Reused f32 conversion buffer for one drain pass.
+    raw: Vec<f32>,
+}
+
+impl SystemSource {
+    /// Read all pending packets as f32 in the file's format.
+    fn read(&mut self, stats: &mut CaptureStats) -> Result<&[f32], AppError> {
+        self.raw.clear();
+        read_packets(&self.session, &mut self.raw, stats)?;
+        Ok(match self.converter.as_mut() {
+            Some(converter) => converter.process(&self.raw),
+            None => &self.raw,
+        })
+    }
+}
+
+/// Microphone source mixed into the loopback stream.
+struct MicInput {
+    session: LoopbackSession,
+    mixer: StreamMixer,
+    /// Reused f32 conversion buffer for one drain pass.
+    scratch: Vec<f32>,
+}
+
 fn run_capture(
     output_path: &str,
-    stop_flag: &AtomicBool,
+    config: &CaptureConfig,
+    control: &CaptureControl,
     app: &AppHandle,
+    ready: SyncSender<Result<CaptureInfo, AppError>>,
 ) -> Result<String, AppError> {
     let _com = ComGuard::init();
 
-    // LoopbackSession has RAII Drop — no manual stop/free needed
-    let mut session = unsafe { LoopbackSession::open()? };
-    let mut writer = AudioWavWriter::create(output_path, session.format)?;
+    let recorded = match config.format {
+        RecordingFormat::Wav => record(config, control, app, ready, |format| {
+            AudioWavWriter::create(output_path, format, config.bit_depth, config.buffer_bytes)
+        }),
+        RecordingFormat::Flac => record(config, control, app, ready, |format| {
+            FlacWriter::create(output_path, format, config.bit_depth)
+        }),
+        #[cfg(feature = "mp3")]
+        RecordingFormat::Mp3 => record(config, control, app, ready, |format| {
+            Mp3Writer::create(output_path, format, config.bitrate_kbps)
+        }),
+        #[cfg(not(feature = "mp3"))]
+        RecordingFormat::Mp3 => Err(super::mp3_unavailable()),
+    };
+    if matches!(recorded, Err(AppError::RecordingTooShort)) {
+        let _ = std::fs::remove_file(output_path);
+    }
+    let (total_frames, ()) = recorded?;
 
-    unsafe { session.start()? };
+    let file_size = std::fs::metadata(output_path).map_or(0, |m| m.len());
+    eprintln!("[capture] Done: {total_frames} frames, {file_size} bytes");
+
+    Ok(output_path.to_string())
+}
+
+fn run_memory_capture(
+    config: &CaptureConfig,
+    control: &CaptureControl,
+    app: &AppHandle,
+    ready: SyncSender<Result<CaptureInfo, AppError>>,
+) -> Result<Vec<f32>, AppError> {
+    let _com = ComGuard::init();
+
+    let (total_frames, samples) =
+        record(config, control, app, ready, |format| Ok(MemorySink::new(format)))?;
 
-    let total_frames = capture_loop(&session, &mut writer, stop_flag, app)?;
+    eprintln!(
+        "[capture] Done: {total_frames} frames, {} samples at {ASR_SAMPLE_RATE} Hz in memory",
+        samples.len()
+    );
+    Ok(samples)
+}
+
+fn run_ring_capture(
+    ring: Arc<Mutex<FrameRing>>,
+    config: &CaptureConfig,
+    control: &CaptureControl,
+    app: &AppHandle,
+    ready: SyncSender<Result<CaptureInfo, AppError>>,
+) -> Result<(), AppError> {
+    let _com = ComGuard::init();
+
+    let (total_frames, ()) =
+        record(config, control, app, ready, |format| RingSink::new(format, ring))?;
+
+    eprintln!("[capture] Done: {total_frames} frames through the ring buffer");
+    Ok(())
+}
+
+/// Run a capture session into the sink built by `create_sink` (called with
+/// the capture format once the device is open). Returns the frames written
+/// and whatever the sink produced.
+fn record<S: AudioSink>(
+    config: &CaptureConfig,
+    control: &CaptureControl,
+    app: &AppHandle,
+    ready: SyncSender<Result<CaptureInfo, AppError>>,
+    create_sink: impl FnOnce(AudioFormat) -> Result<S, AppError>,
+) -> Result<(u64, S::Output), AppError> {
+    // Reverted when the session ends, before the thread exits
+    let _mmcss = if config.high_priority { MmcssGuard::pro_audio() } else { None };
+
+    let (session, file_format, mut mic, mut writer) = match open_capture(config, create_sink) {
+        Ok(opened) => opened,
+        Err(e) => {
+            let message = e.to_string();
+            let _ = ready.send(Err(e));
+            return Err(AppError::AudioCapture(message));
+        }
+    };
+
+    let _ = ready.send(Ok(CaptureInfo {
+        device_id: session.device_id.clone(),
+        device_name: session.device_name.clone(),
+        mic_device_name: mic.as_ref().map(|m| m.session.device_name.clone()),
+        sample_rate: session.format.sample_rate,
+        channels: session.format.channels,
+        is_float: session.format.is_float,
+        output_path: None,
+    }));
+
+    // Only follow the default device when the caller didn't pick one (a
+    // process capture isn't tied to a device at all).
+    let watcher = if config.device_id.is_none() && config.process.is_none() {
+        unsafe { DefaultDeviceWatcher::new(session.device_id.clone()) }
+            .inspect_err(|e| eprintln!("[capture] Not following default device: {e}"))
+            .ok()
+    } else {
+        None
+    };
+
+    let converter = (file_format.sample_rate != session.format.sample_rate).then(|| {
+        eprintln!(
+            "[capture] Resampling {} Hz -> {} Hz",
+            session.format.sample_rate, file_format.sample_rate
+        );
+        FormatConverter::new(
+            session.format.channels,
+            session.format.sample_rate,
+            file_format.channels,
+            file_format.sample_rate,
+        )
+    });
+    let mut source = SystemSource { session, converter, raw: Vec::new() };
+    let total_frames =
+        capture_loop(&mut source, mic.as_mut(), &mut writer, control, watcher, config, app)?;
+
+    // Returning early drops (and closes) the writer; `run_capture` deletes the file
+    let padding = config.empty_capture.padding_frames(total_frames, file_format.sample_rate)?;
+    let total_frames = if padding > 0 {
+        eprintln!("[capture] No audio captured, writing {padding} frames of silence");
+        writer.write_silence(padding)?;
+        padding as u64
+    } else {
+        total_frames
+    };
 
     // Session drop → audio_client.Stop() + CoTaskMemFree
-    drop(session);
+    drop(mic);
+    drop(source);
 
     // Drain is not possible after session drop — all data was already drained
     // in capture_loop's final iteration.
 
-    writer.finalize()?;
+    let output = writer.finalize()?;
 
-    let file_size = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
-    eprintln!("[capture] Done: {total_frames} frames, {file_size} bytes");
+    Ok((total_frames, output))
+}
 
-    Ok(output_path.to_string())
+/// Format the sink is created with: the device's, or f32 at
+/// `config.target_sample_rate` when a different rate is forced.
+///
+/// A forced rate goes through `FormatConverter`'s linear interpolation,
+/// which adds no latency but isn't band-limited: downsampling lets a little
+/// content above the new Nyquist alias back. Inaudible for speech and most
+/// music; use the device rate (or resample offline) for critical work.
+fn file_format(device: AudioFormat, config: &CaptureConfig) -> AudioFormat {
+    match config.target_sample_rate {
+        Some(rate) if rate != device.sample_rate => AudioFormat {
+            sample_rate: rate,
+            bits_per_sample: 32,
+            is_float: true,
+            ..device
+        },
+        _ => device,
+    }
+}
+
+/// Open the loopback session (plus the microphone, if mixing), create the
+/// sink, and start the streams. Also returns the sink's format.
+fn open_capture<S: AudioSink>(
+    config: &CaptureConfig,
+    create_sink: impl FnOnce(AudioFormat) -> Result<S, AppError>,
+) -> Result<(LoopbackSession, AudioFormat, Option<MicInput>, S), AppError> {
+    // LoopbackSession has RAII Drop — no manual stop/free needed
+    let mut session = unsafe {
+        match (config.process, config.device_id.as_deref()) {
+            (Some(target), _) => LoopbackSession::open_for_process(target.pid, target.include_tree)?,
+            (None, Some(id)) => LoopbackSession::open_with_device(id)?,
+            (None, None) => LoopbackSession::open()?,
+        }
+    };
+
+    let format = file_format(session.format, config);
+    let mut mic = match config.mic_mix {
+        Some(mix) => Some(open_mic(&format, mix)?),
+        None => None,
+    };
+
+    let mut writer = create_sink(format)?;
+    if config.denoise_intensity > 0.0 {
+        // RNNoise only runs at 48 kHz; later device switches are converted
+        // to this format, so the check holds for the whole recording.
+        if format.sample_rate == RNNOISE_SAMPLE_RATE {
+            writer.set_denoiser(RealtimeDenoiser::new(config.denoise_intensity, format.channels));
+        } else {
+            eprintln!(
+                "[capture] Live denoise needs {RNNOISE_SAMPLE_RATE} Hz, recording at {} Hz; recording without it",
+                format.sample_rate
+            );
+        }
+    }
+
+    unsafe { session.start()? };
+    if let Some(mic) = mic.as_mut() {
+        unsafe { mic.session.start()? };
+    }
+
+    Ok((session, format, mic, writer))
+}
+
+/// Open the default microphone and a mixer targeting the file format (the
+/// system stream as it reaches the mixer).
+fn open_mic(system: &AudioFormat, mix: MicMix) -> Result<MicInput, AppError> {
+    let session = unsafe { LoopbackSession::open_microphone()? };
+    if session.format.sample_rate != system.sample_rate {
+        eprintln!(
+            "[capture] Resampling mic {} Hz -> {} Hz",
+            session.format.sample_rate, system.sample_rate
+        );
+    }
+
+    let mixer = StreamMixer::new(
+        system.channels,
+        system.sample_rate,
+        session.format.sample_rate,
+        mix,
+    );
+
+    Ok(MicInput {
+        session,
+        mixer,
+        scratch: Vec::with_capacity(system.sample_rate as usize * system.channels as usize),
+    })
+}
+
+/// Reopen the loopback on the new default device and convert its audio to
+/// the file's format. The old session is dropped (stopped) on success.
+fn switch_device(
+    source: &mut SystemSource,
+    device_id: &str,
+    file_format: &AudioFormat,
+) -> Result<(), AppError> {
+    let mut session = unsafe { LoopbackSession::open_with_device(device_id)? };
+    unsafe { session.start()? };
+
+    eprintln!(
+        "[capture] Now capturing {} ({} Hz, {} ch)",
+        session.device_name, session.format.sample_rate, session.format.channels
+    );
+
+    source.converter = Some(FormatConverter::new(
+        session.format.channels,
+        session.format.sample_rate,
+        file_format.channels,
+        file_format.sample_rate,
+    ));
+    source.session = session;
+    Ok(())
+}
+
+/// Check whether a discontinuity came from the mix format changing under the
+/// stream; if so, reopen the device so packets are read in the new format
+/// and converted to the file's. Failures are logged and capture goes on.
+fn follow_format_change(source: &mut SystemSource, file_format: &AudioFormat, app: &AppHandle) {
+    let current = match unsafe { source.session.current_mix_format() } {
+        Ok(format) => format,
+        Err(e) => {
+            eprintln!("[capture] Checking mix format failed: {e}");
+            return;
+        }
+    };
+    if current == source.session.format {
+        return;
+    }
+
+    eprintln!("[capture] Mix format changed: {:?} -> {current:?}", source.session.format);
+    let device_id = source.session.device_id.clone();
+    match switch_device(source, &device_id, file_format) {
+        Ok(()) => {
+            let format = source.session.format;
+            let _ = app.emit("format-changed", FormatChangedEvent {
+                sample_rate: format.sample_rate,
+                channels: format.channels,
+                bits_per_sample: format.bits_per_sample,
+                is_float: format.is_float,
+            });
+        }
+        Err(e) => eprintln!("[capture] Reopening after format change failed: {e}"),
+    }
 }
 
 // ── Event-driven capture loop ───────────────────────────────────────
 
-/// Interval (in drain iterations) between emitting audio level events.
-/// At ~10 ms per WASAPI buffer, 10 iterations ≈ 100 ms.
-const LEVEL_EMIT_INTERVAL: u32 = 10;
+/// Typical time between buffer-ready wakeups in shared mode.
+const BUFFER_PERIOD_MS: u32 = 10;
+/// Default period of the level events.
+const DEFAULT_EMIT_INTERVAL_MS: u32 = 100;
+/// Shortest gap between `clipping-detected` events.
+const CLIPPING_EMIT_INTERVAL: Duration = Duration::from_secs(1);
 
-fn capture_loop(
-    session: &LoopbackSession,
-    writer: &mut AudioWavWriter,
-    stop_flag: &AtomicBool,
+/// Empty buffer-wait timeouts in a row (~100 ms each) before
+/// `capture-timeout` is emitted.
+const TIMEOUT_WARNING_THRESHOLD: u32 = 30;
+/// Shortest allowed period of the level events.
+const MIN_EMIT_INTERVAL_MS: u32 = 20;
+
+/// Drain iterations between level events for a requested period.
+///
+/// Shorter periods make meters smoother but cost an IPC round of
+/// `audio-level` + `capture-stats` (+ `audio-spectrum`) each time; below
+/// ~20 ms the webview can't paint faster anyway. The period is approximate:
+/// iterations are counted, not timed, so it follows the device's buffer
+/// period (and runs faster while the mic is mixed in).
+fn emit_interval_iterations(interval_ms: Option<u32>) -> u32 {
+    let ms = interval_ms.unwrap_or(DEFAULT_EMIT_INTERVAL_MS).max(MIN_EMIT_INTERVAL_MS);
+    ms / BUFFER_PERIOD_MS
+}
+
+/// Peak levels over a drain pass or emit window. The sources only track RMS.
+#[derive(Clone, Copy, Default)]
+struct Levels {
+    /// Level of what was written to the file.
+    written: Level,
+    system: f32,
+    mic: f32,
+}
+
+impl Levels {
+    fn max(self, other: Self) -> Self {
+        Self {
+            written: self.written.merge(other.written),
+            system: self.system.max(other.system),
+            mic: self.mic.max(other.mic),
+        }
+    }
+}
+
+/// Returns the number of frames written to the file (paused spans count
+/// only when they were written as silence).
+fn capture_loop<S: AudioSink>(
+    source: &mut SystemSource,
+    mut mic: Option<&mut MicInput>,
+    writer: &mut S,
+    control: &CaptureControl,
+    mut watcher: Option<DefaultDeviceWatcher>,
+    config: &CaptureConfig,
     app: &AppHandle,
 ) -> Result<u64, AppError> {
     let mut total_frames: u64 = 0;
     let mut iter_count: u32 = 0;
-    let mut peak_level: f32 = 0.0;
+    let emit_interval = emit_interval_iterations(config.emit_interval_ms);
+    let mut loudest = Levels::default();
+    let mut clipped: u32 = 0;
+    let mut last_clipping_emit: Option<Instant> = None;
+    let mut stats = CaptureStats::default();
+    let started = Instant::now();
+    let file_format = file_format(source.session.format, config);
+    let mut spectrum = config.spectrum.then(|| SpectrumAnalyzer::new(file_format.sample_rate));
+    let mut last_device_poll = started;
+    let mut consecutive_timeouts: u32 = 0;
+
+    let max_duration = config.max_duration_ms.map(Duration::from_millis);
+
+    while !control.stop.load(Ordering::Acquire) {
+        if max_duration.is_some_and(|max| started.elapsed() >= max) {
+            break;
+        }
+        if let Some(watcher) = watcher.as_mut() {
+            if last_device_poll.elapsed() >= DEVICE_POLL_INTERVAL {
+                last_device_poll = Instant::now();
+                if let Some(id) = watcher.poll() {
+                    match switch_device(source, &id, &file_format) {
+                        Ok(()) => {
+                            watcher.set_current(source.session.device_id.clone());
+                            let _ = app.emit("device-changed", DeviceChangedEvent {
+                                device_id: source.session.device_id.clone(),
+                                device_name: source.session.device_name.clone(),
+                                sample_rate: source.session.format.sample_rate,
+                            });
+                        }
+                        // Retried on the next poll
+                        Err(e) => eprintln!("[capture] Switching to {id} failed: {e}"),
+                    }
+                }
+            }
+        }
+
+        let mode = control.packet_mode();
 
-    while !stop_flag.load(Ordering::Acquire) {
         // Sleep on kernel event instead of busy-polling with thread::sleep
-        session.wait_for_buffer();
+        let (wait, frames, levels) = match mic.as_deref_mut() {
+            Some(mic) => {
+                let wait = source.session.wait_for_either(&mic.session)?;
+                let (frames, levels) =
+                    drain_mixed(source, mic, writer, mode, &mut stats, spectrum.as_mut())?;
+                (wait, frames, levels)
+            }
+            None => {
+                let wait = source.session.wait_for_buffer()?;
+                let (frames, level) =
+                    drain_system(source, writer, mode, &mut stats, spectrum.as_mut())?;
+                (wait, frames, Levels { written: level, ..Levels::default() })
+            }
+        };
 
-        let (frames, level) = drain_packets(session, writer)?;
+        // A timeout that still found packets is just the polling fallback
+        if wait == BufferWait::TimedOut && frames == 0 {
+            consecutive_timeouts += 1;
+            if consecutive_timeouts == TIMEOUT_WARNING_THRESHOLD {
+                let _ = app.emit("capture-timeout", CaptureTimeoutEvent { consecutive_timeouts });
+            }
+        } else {
+            consecutive_timeouts = 0;
+        }
+        // A process capture has no mix format; WASAPI converts to the one it was opened with
+        if std::mem::take(&mut stats.discontinuity) && config.process.is_none() {
+            follow_format_change(source, &file_format, app);
+        }
         total_frames += frames;
 
         // Track peak level across iterations, emit periodically
-        if level > peak_level {
-            peak_level = level;
-        }
+        loudest = loudest.max(levels);
         iter_count += 1;
 
-        if iter_count >= LEVEL_EMIT_INTERVAL {
-            let _ = app.emit("audio-level", AudioLevelEvent { level: peak_level });
-            peak_level = 0.0;
+        clipped = clipped.saturating_add(levels.written.clipped);
+        if clipped > 0 && last_clipping_emit.is_none_or(|t| t.elapsed() >= CLIPPING_EMIT_INTERVAL) {
+            let timestamp_ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64);
+            let _ = app.emit("clipping-detected", ClippingEvent {
+                clipped_samples: clipped,
+                timestamp_ms,
+            });
+            clipped = 0;
+            last_clipping_emit = Some(Instant::now());
+        }
+
+        if iter_count >= emit_interval {
+            let mixing = mic.is_some();
+            let meter_channels = (file_format.channels as usize).min(MAX_METER_CHANNELS);
+            let _ = app.emit("audio-level", AudioLevelEvent {
+                level: loudest.written.rms,
+                channel_levels: loudest.written.channel_rms[..meter_channels].to_vec(),
+                peak: loudest.written.peak,
+                system_level: mixing.then_some(loudest.system),
+                mic_level: mixing.then_some(loudest.mic),
+            });
+            if let Some(spectrum) = spectrum.as_mut() {
+                let _ = app.emit("audio-spectrum", AudioSpectrumEvent { bands: *spectrum.bands() });
+            }
+            let _ = app.emit("capture-stats", CaptureStatsEvent {
+                frames_written: total_frames,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+                glitch_count: stats.glitch_count,
+                current_file_bytes: writer.file_bytes(),
+            });
+            loudest = Levels::default();
             iter_count = 0;
         }
     }
 
     // Final drain after stop flag — get any remaining buffered data
-    let (frames, _) = drain_packets(session, writer)?;
-    total_frames += frames;
+    let mode = control.packet_mode();
+    match mic {
+        Some(mic) => {
+            let (frames, _) = drain_mixed(source, mic, writer, mode, &mut stats, None)?;
+            let rest = mic.mixer.flush();
+            let (rest_frames, _) =
+                write_block(writer, rest, file_format.channels as usize, mode, None)?;
+            total_frames += frames + rest_frames;
+        }
+        None => {
+            let (frames, _) = drain_system(source, writer, mode, &mut stats, None)?;
+            total_frames += frames;
+        }
+    }
+
+    if stats.glitch_count > 0 {
+        eprintln!("[capture] {} discontinuities during capture", stats.glitch_count);
+    }
 
     Ok(total_frames)
 }
 
-/// Read all available WASAPI packets. Returns (frames_read, max_rms_level).
-fn drain_packets(
+/// Write one block of interleaved f32 samples according to `mode`, feeding
+/// the spectrum what was written. Returns (frames_written, level).
+fn write_block<S: AudioSink>(
+    writer: &mut S,
+    samples: &[f32],
+    channels: usize,
+    mode: PacketMode,
+    spectrum: Option<&mut SpectrumAnalyzer>,
+) -> Result<(u64, Level), AppError> {
+    let frames = samples.len() / channels;
+    match mode {
+        PacketMode::Record => {
+            if let Some(spectrum) = spectrum {
+                spectrum.push(samples, channels);
+            }
+            writer.write_samples(samples)?;
+            Ok((frames as u64, compute_levels(samples, channels)))
+        }
+        PacketMode::Silence => {
+            if let Some(spectrum) = spectrum {
+                spectrum.push_silence(frames);
+            }
+            writer.write_silence(frames)?;
+            Ok((frames as u64, Level::default()))
+        }
+        PacketMode::Discard => Ok((0, Level::default())),
+    }
+}
+
+/// Drain the loopback source into the file, converting first when the
+/// source has switched to a device that needs it.
+fn drain_system<S: AudioSink>(
+    source: &mut SystemSource,
+    writer: &mut S,
+    mode: PacketMode,
+    stats: &mut CaptureStats,
+    spectrum: Option<&mut SpectrumAnalyzer>,
+) -> Result<(u64, Level), AppError> {
+    let channels = writer.channels();
+    let samples = source.read(stats)?;
+    write_block(writer, samples, channels, mode, spectrum)
+}
+
+/// Drain both the loopback and mic sessions into the mixer and write
+/// every frame that can be mixed. Returns (frames_written, levels).
+fn drain_mixed<S: AudioSink>(
+    source: &mut SystemSource,
+    mic: &mut MicInput,
+    writer: &mut S,
+    mode: PacketMode,
+    stats: &mut CaptureStats,
+    spectrum: Option<&mut SpectrumAnalyzer>,
+) -> Result<(u64, Levels), AppError> {
+    let system_samples = source.read(stats)?;
+    let system = compute_rms(system_samples);
+    mic.mixer.push_system(system_samples);
+
+    mic.scratch.clear();
+    read_packets(&mic.session, &mut mic.scratch, stats)?;
+    let mic_level = compute_rms(&mic.scratch);
+    mic.mixer.push_mic(&mic.scratch, mic.session.format.channels);
+
+    let mixed = mic.mixer.mix();
+    let (frames, written) = write_block(writer, mixed, writer.channels(), mode, spectrum)?;
+
+    let levels = if mode == PacketMode::Record {
+        Levels { written, system, mic: mic_level }
+    } else {
+        Levels::default()
+    };
+    Ok((frames, levels))
+}
+
+/// Hand every available packet of `session` to `f` as (data, frames, flags).
+/// Each packet is released before `f`'s error is returned, so a failed write
+/// never leaves the capture client holding a buffer. Returns the frames read.
+fn for_each_packet(
     session: &LoopbackSession,
-    writer: &mut AudioWavWriter,
-) -> Result<(u64, f32), AppError> {
+    stats: &mut CaptureStats,
+    mut f: impl FnMut(*const u8, usize, u32) -> Result<(), AppError>,
+) -> Result<u64, AppError> {
     let mut frames_read: u64 = 0;
-    let mut max_level: f32 = 0.0;
 
     loop {
         let packet_length = unsafe {
@@ -164,25 +986,36 @@ fn drain_packets(
                 .map_err(|e| AppError::AudioCapture(format!("GetBuffer: {e}")))?;
         }
 
-        let frame_count = num_frames as usize;
-
-        // AUDCLNT_BUFFERFLAGS_SILENT = 0x2
-        let level = if (flags & 0x2) != 0 {
-            writer.write_silence(frame_count)?;
-            0.0
-        } else {
-            unsafe { writer.write_raw(buffer_ptr, frame_count)? }
-        };
-
-        if level > max_level {
-            max_level = level;
-        }
-        frames_read += frame_count as u64;
+        stats.record_flags(flags);
+        let result = f(buffer_ptr, num_frames as usize, flags);
 
         unsafe {
             let _ = session.capture_client.ReleaseBuffer(num_frames);
         }
+        result?;
+        frames_read += num_frames as u64;
     }
 
-    Ok((frames_read, max_level))
+    Ok(frames_read)
+}
+
+/// Read all available packets from `session`, appending them to `out` as
+/// interleaved f32. Returns the number of frames read.
+fn read_packets(
+    session: &LoopbackSession,
+    out: &mut Vec<f32>,
+    stats: &mut CaptureStats,
+) -> Result<u64, AppError> {
+    let channels = session.format.channels as usize;
+
+    for_each_packet(session, stats, |buffer_ptr, frame_count, flags| {
+        // AUDCLNT_BUFFERFLAGS_SILENT = 0x2
+        if (flags & 0x2) != 0 {
+            out.resize(out.len() + frame_count * channels, 0.0);
+            Ok(())
+        } else {
+            // SAFETY: WASAPI guarantees the buffer holds frame_count frames
+            unsafe { append_raw_samples(buffer_ptr, frame_count, &session.format, out) }
+        }
+    })
 }
@@ -1,154 +1,27 @@
 use crate::error::AppError;
+use crate::progress::ProgressReporter;
+use super::resample::resample;
+use super::wav_format::{write_header, WavInfo};
+use super::wav_reader::{read_wav_f32, read_wav_f32_prefix};
 use nnnoiseless::DenoiseState;
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Size of one RNNoise frame (fixed by the algorithm).
 const FRAME_SIZE: usize = DenoiseState::FRAME_SIZE;
 
-/// WAV header constants
-const RIFF: &[u8; 4] = b"RIFF";
-const WAVE: &[u8; 4] = b"WAVE";
-const FMT_: &[u8; 4] = b"fmt ";
-const DATA: &[u8; 4] = b"data";
-const WAVE_FORMAT_FLOAT: u16 = 3;
-
-// ── WAV reading ─────────────────────────────────────────────────────
-
-/// Minimal WAV format info extracted from header.
-#[derive(Debug, Clone)]
-pub struct WavInfo {
-    pub channels: u16,
-    pub sample_rate: u32,
-    pub bits_per_sample: u16,
-    pub is_float: bool,
-    pub data_offset: u64,
-    pub data_size: u32,
-}
-
-/// Read and parse a WAV header, returning format info.
-fn read_wav_header(reader: &mut (impl Read + Seek)) -> Result<WavInfo, AppError> {
-    reader.seek(SeekFrom::Start(0))
-        .map_err(|e| AppError::AudioEnhance(format!("Seek: {e}")))?;
-
-    let mut header = [0u8; 44];
-    reader.read_exact(&mut header)
-        .map_err(|e| AppError::AudioEnhance(format!("Read WAV header: {e}")))?;
-
-    if &header[0..4] != RIFF || &header[8..12] != WAVE {
-        return Err(AppError::AudioEnhance("Not a valid WAV file".into()));
-    }
-
-    let format_tag = u16::from_le_bytes([header[20], header[21]]);
-    let channels = u16::from_le_bytes([header[22], header[23]]);
-    let sample_rate = u32::from_le_bytes([header[24], header[25], header[26], header[27]]);
-    let bits_per_sample = u16::from_le_bytes([header[34], header[35]]);
-
-    // Find data chunk — it's usually at offset 36, but scan for it
-    let mut data_offset: u64 = 12; // after RIFF + size + WAVE
-    reader.seek(SeekFrom::Start(data_offset))
-        .map_err(|e| AppError::AudioEnhance(format!("Seek to chunks: {e}")))?;
-
-    loop {
-        let mut chunk_header = [0u8; 8];
-        reader.read_exact(&mut chunk_header)
-            .map_err(|e| AppError::AudioEnhance(format!("Read chunk header: {e}")))?;
-        data_offset += 8;
-
-        if &chunk_header[0..4] == DATA {
-            let data_size = u32::from_le_bytes([
-                chunk_header[4], chunk_header[5], chunk_header[6], chunk_header[7],
-            ]);
-            return Ok(WavInfo {
-                channels,
-                sample_rate,
-                bits_per_sample,
-                is_float: format_tag == WAVE_FORMAT_FLOAT,
-                data_offset,
-                data_size,
-            });
-        }
-
-        // Skip this chunk
-        let chunk_size = u32::from_le_bytes([
-            chunk_header[4], chunk_header[5], chunk_header[6], chunk_header[7],
-        ]);
-        reader.seek(SeekFrom::Current(chunk_size as i64))
-            .map_err(|e| AppError::AudioEnhance(format!("Skip chunk: {e}")))?;
-        data_offset += chunk_size as u64;
-    }
-}
-
-/// Read all f32 samples from a WAV file. Returns (samples, info).
-fn read_wav_f32(path: &str) -> Result<(Vec<f32>, WavInfo), AppError> {
-    let file = File::open(path)
-        .map_err(|e| AppError::AudioEnhance(format!("Open WAV: {e}")))?;
-    let mut reader = BufReader::new(file);
-
-    let info = read_wav_header(&mut reader)?;
-
-    reader.seek(SeekFrom::Start(info.data_offset))
-        .map_err(|e| AppError::AudioEnhance(format!("Seek to data: {e}")))?;
-
-    let _sample_count = info.data_size as usize / (info.bits_per_sample as usize / 8);
-
-    if info.is_float && info.bits_per_sample == 32 {
-        let mut bytes = vec![0u8; info.data_size as usize];
-        reader.read_exact(&mut bytes)
-            .map_err(|e| AppError::AudioEnhance(format!("Read audio data: {e}")))?;
-        // SAFETY: f32 is 4 bytes, alignment is handled by Vec reallocation
-        let samples: Vec<f32> = bytes
-            .chunks_exact(4)
-            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
-            .collect();
-        Ok((samples, info))
-    } else if !info.is_float && info.bits_per_sample == 16 {
-        let mut bytes = vec![0u8; info.data_size as usize];
-        reader.read_exact(&mut bytes)
-            .map_err(|e| AppError::AudioEnhance(format!("Read audio data: {e}")))?;
-        let samples: Vec<f32> = bytes
-            .chunks_exact(2)
-            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
-            .collect();
-        Ok((samples, info))
-    } else {
-        Err(AppError::AudioEnhance(format!(
-            "Unsupported WAV format: float={}, bits={}",
-            info.is_float, info.bits_per_sample
-        )))
-    }
-}
+/// The only sample rate RNNoise works at; other rates are resampled.
+pub const RNNOISE_SAMPLE_RATE: u32 = 48_000;
 
 /// Write f32 samples to a WAV file.
-fn write_wav_f32(path: &str, samples: &[f32], info: &WavInfo) -> Result<(), AppError> {
+pub(super) fn write_wav_f32(path: &str, samples: &[f32], info: &WavInfo) -> Result<(), AppError> {
     let file = File::create(path)
         .map_err(|e| AppError::AudioEnhance(format!("Create output WAV: {e}")))?;
     let mut writer = BufWriter::with_capacity(256 * 1024, file);
 
-    let channels = info.channels;
-    let sample_rate = info.sample_rate;
-    let bits_per_sample: u16 = 32;
-    let block_align = channels * (bits_per_sample / 8);
-    let byte_rate = sample_rate * block_align as u32;
     let data_size = (samples.len() * 4) as u32;
-    let chunk_size = 36 + data_size;
-
-    let mut header = [0u8; 44];
-    header[0..4].copy_from_slice(RIFF);
-    header[4..8].copy_from_slice(&chunk_size.to_le_bytes());
-    header[8..12].copy_from_slice(WAVE);
-    header[12..16].copy_from_slice(FMT_);
-    header[16..20].copy_from_slice(&16u32.to_le_bytes());
-    header[20..22].copy_from_slice(&WAVE_FORMAT_FLOAT.to_le_bytes());
-    header[22..24].copy_from_slice(&channels.to_le_bytes());
-    header[24..28].copy_from_slice(&sample_rate.to_le_bytes());
-    header[28..32].copy_from_slice(&byte_rate.to_le_bytes());
-    header[32..34].copy_from_slice(&block_align.to_le_bytes());
-    header[34..36].copy_from_slice(&bits_per_sample.to_le_bytes());
-    header[36..40].copy_from_slice(DATA);
-    header[40..44].copy_from_slice(&data_size.to_le_bytes());
-
-    writer.write_all(&header)
+    write_header(&mut writer, info.channels, info.sample_rate, 32, true, data_size)
         .map_err(|e| AppError::AudioEnhance(format!("Write header: {e}")))?;
 
     // Bulk write: reinterpret &[f32] as &[u8] — f32 is already little-endian on x86.
@@ -193,12 +66,22 @@ fn mono_to_multichannel(mono: &[f32], channels: u16) -> Vec<f32> {
         .collect()
 }
 
+/// Report progress every this many RNNoise frames (~5 s of 48 kHz audio).
+const PROGRESS_INTERVAL_FRAMES: usize = 500;
+
 /// Apply RNNoise denoising to mono f32 samples in [-1.0, 1.0] range.
 /// `intensity` controls the wet/dry mix: 0.0 = original, 1.0 = fully denoised.
-fn denoise_mono(mono: &[f32], intensity: f32) -> Vec<f32> {
+/// `progress` periodically hears stage `"denoise"`, counting RNNoise frames.
+/// Returns `EnhanceCancelled` as soon as `cancel` is set.
+fn denoise_mono(
+    mono: &[f32],
+    intensity: f32,
+    progress: &dyn ProgressReporter,
+    cancel: Option<&AtomicBool>,
+) -> Result<Vec<f32>, AppError> {
     let intensity = intensity.clamp(0.0, 1.0);
     if intensity == 0.0 {
-        return mono.to_vec();
+        return Ok(mono.to_vec());
     }
 
     let mut state = DenoiseState::new();
@@ -211,6 +94,14 @@ fn denoise_mono(mono: &[f32], intensity: f32) -> Vec<f32> {
     let total_frames = mono.len().div_ceil(FRAME_SIZE);
 
     for frame_idx in 0..total_frames {
+        if cancel.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+            return Err(AppError::EnhanceCancelled);
+        }
+
+        if frame_idx % PROGRESS_INTERVAL_FRAMES == 0 {
+            progress.report("denoise", frame_idx as u64, total_frames as u64);
+        }
+
         let start = frame_idx * FRAME_SIZE;
         let end = (start + FRAME_SIZE).min(mono.len());
         let len = end - start;
@@ -232,7 +123,276 @@ fn denoise_mono(mono: &[f32], intensity: f32) -> Vec<f32> {
         }
     }
 
-    output
+    Ok(output)
+}
+
+/// FFT frame of the spectral gate: ~21 ms at 48 kHz, fine enough frequency
+/// resolution for hum and hiss.
+const GATE_FFT_SIZE: usize = 1024;
+/// 50% overlap: a periodic Hann window then sums to exactly 1.
+const GATE_HOP: usize = GATE_FFT_SIZE / 2;
+/// How many times the noise estimate is subtracted, to push residual noise
+/// down at the cost of slightly thinner audio.
+const OVER_SUBTRACTION: f32 = 2.0;
+/// Fraction of a bin's magnitude always kept, which masks the "musical
+/// noise" plain subtraction leaves behind.
+const SPECTRAL_FLOOR: f32 = 0.05;
+/// Length of the quietest audio used when no noise region is given.
+const QUIETEST_PROFILE_MS: u32 = 500;
+
+/// Spectral subtraction on mono samples: learn the average noise spectrum
+/// from `profile`, then subtract it (times `OVER_SUBTRACTION`) from every
+/// frame. Unlike RNNoise it makes no assumptions about speech, so music and
+/// ambient recordings keep their character. Returns as many samples as it
+/// was given.
+pub fn spectral_gate(
+    samples: &[f32],
+    sample_rate: u32,
+    profile: NoiseProfile,
+) -> Result<Vec<f32>, AppError> {
+    use rustfft::num_complex::Complex;
+
+    // Leading pad so the first real sample sits under two overlapping windows
+    let mut padded = vec![0.0f32; GATE_HOP];
+    padded.extend_from_slice(samples);
+    let frames = padded.len().div_ceil(GATE_HOP);
+    padded.resize((frames + 1) * GATE_HOP, 0.0);
+
+    let window: Vec<f32> = (0..GATE_FFT_SIZE)
+        .map(|i| 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / GATE_FFT_SIZE as f32).cos()))
+        .collect();
+    let mut planner = rustfft::FftPlanner::new();
+    let forward = planner.plan_fft_forward(GATE_FFT_SIZE);
+    let inverse = planner.plan_fft_inverse(GATE_FFT_SIZE);
+
+    // Spectrum of every frame, kept for the second pass
+    let spectra: Vec<Vec<Complex<f32>>> = (0..frames)
+        .map(|f| {
+            let start = f * GATE_HOP;
+            let mut buf: Vec<Complex<f32>> = padded[start..start + GATE_FFT_SIZE]
+                .iter()
+                .zip(&window)
+                .map(|(&s, &w)| Complex::new(s * w, 0.0))
+                .collect();
+            forward.process(&mut buf);
+            buf
+        })
+        .collect();
+
+    let noise_frames = noise_profile_frames(samples, sample_rate, profile, &spectra)?;
+    let mut noise = vec![0.0f32; GATE_FFT_SIZE];
+    for &f in &noise_frames {
+        for (n, bin) in noise.iter_mut().zip(&spectra[f]) {
+            *n += bin.norm();
+        }
+    }
+    noise.iter_mut().for_each(|n| *n /= noise_frames.len() as f32);
+
+    let mut output = vec![0.0f32; padded.len()];
+    for (f, mut buf) in spectra.into_iter().enumerate() {
+        for (bin, &n) in buf.iter_mut().zip(&noise) {
+            let mag = bin.norm();
+            if mag > 0.0 {
+                let kept = (mag - OVER_SUBTRACTION * n).max(SPECTRAL_FLOOR * mag);
+                *bin *= kept / mag;
+            }
+        }
+        inverse.process(&mut buf);
+        let start = f * GATE_HOP;
+        for (out, bin) in output[start..start + GATE_FFT_SIZE].iter_mut().zip(&buf) {
+            *out += bin.re / GATE_FFT_SIZE as f32;
+        }
+    }
+
+    Ok(output[GATE_HOP..GATE_HOP + samples.len()].to_vec())
+}
+
+/// Indices of the frames (of `spectra`, offset by one hop of padding) that
+/// make up the noise profile.
+fn noise_profile_frames(
+    samples: &[f32],
+    sample_rate: u32,
+    profile: NoiseProfile,
+    spectra: &[Vec<rustfft::num_complex::Complex<f32>>],
+) -> Result<Vec<usize>, AppError> {
+    let ms_to_sample = |ms: u32| (ms as u64 * sample_rate as u64 / 1000) as usize;
+    match profile {
+        NoiseProfile::Region { start_ms, end_ms } => {
+            let start = ms_to_sample(start_ms);
+            let end = ms_to_sample(end_ms).min(samples.len());
+            if end <= start {
+                return Err(AppError::AudioEnhance(format!(
+                    "Noise region {start_ms}–{end_ms} ms is empty or past the end of the audio"
+                )));
+            }
+            // Frame f is centred on sample f * hop (after the leading pad);
+            // a region shorter than a hop still gets its nearest frame
+            let last = ((end - 1) / GATE_HOP).min(spectra.len() - 1);
+            let first = start.div_ceil(GATE_HOP).min(last);
+            Ok((first..=last).collect())
+        }
+        NoiseProfile::Quietest => {
+            let count = (ms_to_sample(QUIETEST_PROFILE_MS) / GATE_HOP).clamp(1, spectra.len());
+            let energy = |f: usize| spectra[f].iter().map(|b| b.norm_sqr()).sum::<f32>();
+            let mut by_energy: Vec<(usize, f32)> = (0..spectra.len()).map(|f| (f, energy(f))).collect();
+            by_energy.sort_by(|a, b| a.1.total_cmp(&b.1));
+            Ok(by_energy.into_iter().take(count).map(|(f, _)| f).collect())
+        }
+    }
+}
+
+/// Direct-form-I biquad filter section.
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    /// Coefficients normalized so that `a0 == 1`.
+    fn new(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    /// Butterworth high-pass (RBJ audio EQ cookbook, Q = 1/√2).
+    fn high_pass(sample_rate: f32, cutoff_hz: f32) -> Self {
+        let w0 = 2.0 * std::f32::consts::PI * cutoff_hz / sample_rate;
+        let alpha = w0.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let cos_w0 = w0.cos();
+        Self::new(
+            [(1.0 + cos_w0) / 2.0, -(1.0 + cos_w0), (1.0 + cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    /// ITU-R BS.1770 K-weighting stage 1: high-shelf "head" pre-filter.
+    fn k_shelf(sample_rate: f32) -> Self {
+        const F0: f32 = 1_681.974_5;
+        const GAIN_DB: f32 = 3.999_843_9;
+        const Q: f32 = 0.707_175_24;
+
+        let k = (std::f32::consts::PI * F0 / sample_rate).tan();
+        let vh = 10f32.powf(GAIN_DB / 20.0);
+        let vb = vh.powf(0.499_666_78);
+        Self::new(
+            [vh + vb * k / Q + k * k, 2.0 * (k * k - vh), vh - vb * k / Q + k * k],
+            [1.0 + k / Q + k * k, 2.0 * (k * k - 1.0), 1.0 - k / Q + k * k],
+        )
+    }
+
+    /// ITU-R BS.1770 K-weighting stage 2: RLB high-pass.
+    fn k_high_pass(sample_rate: f32) -> Self {
+        const F0: f32 = 38.135_47;
+        const Q: f32 = 0.500_327_04;
+
+        let k = (std::f32::consts::PI * F0 / sample_rate).tan();
+        Self::new(
+            [1.0, -2.0, 1.0],
+            [1.0 + k / Q + k * k, 2.0 * (k * k - 1.0), 1.0 - k / Q + k * k],
+        )
+    }
+
+    #[inline]
+    fn process(&mut self, x0: f32) -> f32 {
+        let y0 = self.b0 * x0 + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x0;
+        self.y2 = self.y1;
+        self.y1 = y0;
+        y0
+    }
+}
+
+/// Subtract each channel's mean from interleaved audio, removing DC bias
+/// (common on some USB devices) that wastes headroom.
+fn remove_dc_offset(samples: &mut [f32], channels: u16) {
+    let ch = channels.max(1) as usize;
+    let frames = samples.len() / ch;
+    if frames == 0 {
+        return;
+    }
+
+    let mut sums = vec![0.0f64; ch];
+    for frame in samples.chunks_exact(ch) {
+        for (sum, &s) in sums.iter_mut().zip(frame) {
+            *sum += s as f64;
+        }
+    }
+    let means: Vec<f32> = sums.iter().map(|sum| (sum / frames as f64) as f32).collect();
+
+    for frame in samples.chunks_exact_mut(ch) {
+        for (s, mean) in frame.iter_mut().zip(&means) {
+            *s -= mean;
+        }
+    }
+}
+
+/// Second-order (biquad) Butterworth high-pass filter, applied in place to
+/// each channel of interleaved audio.
+/// Removes rumble below `cutoff_hz` (e.g. 80 Hz) while leaving voice intact.
+fn high_pass(samples: &mut [f32], channels: u16, sample_rate: u32, cutoff_hz: f32) {
+    let sample_rate = sample_rate as f32;
+    // Keep the cutoff well inside (0, Nyquist) so the coefficients stay stable
+    let cutoff = cutoff_hz.clamp(1.0, sample_rate * 0.45);
+
+    let mut filters: Vec<Biquad> = (0..channels.max(1))
+        .map(|_| Biquad::high_pass(sample_rate, cutoff))
+        .collect();
+    for frame in samples.chunks_exact_mut(filters.len()) {
+        for (s, filter) in frame.iter_mut().zip(filters.iter_mut()) {
+            *s = filter.process(*s);
+        }
+    }
+}
+
+/// Apply the noise reduction RNNoise achieved on the mono downmix to every
+/// original channel, preserving the stereo image.
+///
+/// Per RNNoise-sized frame the gain is `rms(denoised) / rms(mono)` (capped
+/// at 1.0), ramped linearly from the previous frame's gain to avoid zipper
+/// noise.
+fn apply_denoise_mask(samples: &mut [f32], channels: u16, mono: &[f32], denoised: &[f32]) {
+    let ch = channels.max(1) as usize;
+    let mut prev_gain = 1.0f32;
+
+    for ((frames, original), clean) in samples
+        .chunks_mut(FRAME_SIZE * ch)
+        .zip(mono.chunks(FRAME_SIZE))
+        .zip(denoised.chunks(FRAME_SIZE))
+    {
+        let energy_in: f32 = original.iter().map(|s| s * s).sum();
+        let energy_out: f32 = clean.iter().map(|s| s * s).sum();
+        let gain = if energy_in > 1e-12 {
+            (energy_out / energy_in).sqrt().min(1.0)
+        } else {
+            1.0
+        };
+
+        let step = (gain - prev_gain) / original.len() as f32;
+        for (i, frame) in frames.chunks_exact_mut(ch).enumerate() {
+            let g = prev_gain + step * (i + 1) as f32;
+            frame.iter_mut().for_each(|s| *s *= g);
+        }
+        prev_gain = gain;
+    }
 }
 
 /// Peak normalize audio samples so the loudest sample reaches `target_peak`.
@@ -251,133 +411,543 @@ fn peak_normalize(samples: &mut [f32], target_peak: f32) {
     }
 }
 
-/// Apply cosine fade-in and fade-out to avoid clicks.
-fn apply_fade(samples: &mut [f32], sample_rate: u32, fade_ms: u32) {
-    let fade_samples = (sample_rate as usize * fade_ms as usize) / 1000;
-    let fade_samples = fade_samples.min(samples.len() / 2);
-    let inv_fade = 1.0 / fade_samples as f32;
+/// Integrated loudness (LUFS) of interleaved audio per EBU R128 / BS.1770:
+/// K-weighting, 400 ms blocks with 75% overlap, absolute gate at -70 LUFS
+/// and relative gate 10 LU below the ungated level.
+///
+/// Returns `None` for clips shorter than one block or entirely below the gate.
+fn integrated_loudness(samples: &[f32], channels: u16, sample_rate: u32) -> Option<f32> {
+    const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+    const RELATIVE_GATE_LU: f64 = 10.0;
+
+    let ch = channels.max(1) as usize;
+    let rate = sample_rate as f32;
+    let mut filters: Vec<(Biquad, Biquad)> = (0..ch)
+        .map(|_| (Biquad::k_shelf(rate), Biquad::k_high_pass(rate)))
+        .collect();
+
+    // Sum K-weighted power over 100 ms steps; a block is 4 consecutive steps
+    let step_frames = (sample_rate as usize / 10).max(1);
+    let mut step_power: Vec<f64> = Vec::new();
+    let mut acc = 0.0f64;
+    for (i, frame) in samples.chunks_exact(ch).enumerate() {
+        for (x, (shelf, hp)) in frame.iter().zip(filters.iter_mut()) {
+            let y = hp.process(shelf.process(*x)) as f64;
+            acc += y * y;
+        }
+        if (i + 1) % step_frames == 0 {
+            step_power.push(acc);
+            acc = 0.0;
+        }
+    }
+
+    let block_frames = (step_frames * 4) as f64;
+    let blocks: Vec<f64> = step_power
+        .windows(4)
+        .map(|w| w.iter().sum::<f64>() / block_frames)
+        .collect();
+
+    let loudness = |power: f64| -0.691 + 10.0 * power.log10();
+    let gated_mean = |threshold: f64| {
+        let kept: Vec<f64> = blocks
+            .iter()
+            .copied()
+            .filter(|&p| p > 0.0 && loudness(p) > threshold)
+            .collect();
+        (!kept.is_empty()).then(|| kept.iter().sum::<f64>() / kept.len() as f64)
+    };
+
+    let ungated = gated_mean(ABSOLUTE_GATE_LUFS)?;
+    let relative_gate = loudness(ungated) - RELATIVE_GATE_LU;
+    let integrated = gated_mean(relative_gate.max(ABSOLUTE_GATE_LUFS))?;
+    Some(loudness(integrated) as f32)
+}
+
+/// Apply the gain that brings integrated loudness to `target_lufs`
+/// (e.g. -16), reduced if needed so no sample exceeds 0 dBFS.
+fn loudness_normalize(samples: &mut [f32], channels: u16, sample_rate: u32, target_lufs: f32) {
+    let Some(measured) = integrated_loudness(samples, channels, sample_rate) else {
+        return;
+    };
+
+    let peak = samples.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+    let mut gain = 10f32.powf((target_lufs - measured) / 20.0);
+    if peak > 0.0 && peak * gain > 1.0 {
+        gain = 1.0 / peak;
+    }
+
+    for s in samples.iter_mut() {
+        *s *= gain;
+    }
+}
+
+/// Soft limiter: samples above `threshold` are compressed with a tanh knee
+/// that approaches but never reaches ±1.0; samples below pass unchanged.
+fn soft_limit(samples: &mut [f32], threshold: f32) {
+    let t = threshold.clamp(0.0, 0.999);
+    let headroom = 1.0 - t;
+
+    for s in samples.iter_mut() {
+        let magnitude = s.abs();
+        if magnitude > t {
+            let limited = t + headroom * ((magnitude - t) / headroom).tanh();
+            *s = limited.copysign(*s);
+        }
+    }
+}
+
+/// Fade in/out over `fade_ms` of interleaved audio to avoid clicks.
+/// The fade is capped at half the clip so fade-in and fade-out never overlap.
+fn apply_fade(samples: &mut [f32], channels: u16, sample_rate: u32, fade_ms: u32, shape: FadeShape) {
+    let ch = channels.max(1) as usize;
+    let frames = samples.len() / ch;
+    let fade_frames = (sample_rate as usize * fade_ms as usize) / 1000;
+    let fade_frames = fade_frames.min(frames / 2);
+    if fade_frames == 0 {
+        return;
+    }
+    let inv_fade = 1.0 / fade_frames as f32;
 
     // Fade in
-    for (i, sample) in samples.iter_mut().take(fade_samples).enumerate() {
-        let t = i as f32 * inv_fade;
-        let gain = 0.5 * (1.0 - (std::f32::consts::PI * t).cos());
-        *sample *= gain;
+    for (i, frame) in samples.chunks_exact_mut(ch).take(fade_frames).enumerate() {
+        let gain = shape.gain(i as f32 * inv_fade);
+        frame.iter_mut().for_each(|s| *s *= gain);
     }
 
     // Fade out
-    let len = samples.len();
-    for (i, sample) in samples[len - fade_samples..].iter_mut().enumerate() {
-        let t = (fade_samples - 1 - i) as f32 * inv_fade;
-        let gain = 0.5 * (1.0 - (std::f32::consts::PI * t).cos());
-        *sample *= gain;
+    let tail = (frames - fade_frames) * ch;
+    for (i, frame) in samples[tail..frames * ch].chunks_exact_mut(ch).enumerate() {
+        let gain = shape.gain((fade_frames - 1 - i) as f32 * inv_fade);
+        frame.iter_mut().for_each(|s| *s *= gain);
     }
 }
 
 // ── Public API ──────────────────────────────────────────────────────
 
+/// Curve used by the fade-in/out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FadeShape {
+    Linear,
+    /// Raised cosine — smoother start and end than linear.
+    #[default]
+    Cosine,
+}
+
+impl FadeShape {
+    /// Gain at position `t` in 0.0–1.0 through the fade-in.
+    #[inline]
+    fn gain(self, t: f32) -> f32 {
+        match self {
+            Self::Linear => t,
+            Self::Cosine => 0.5 * (1.0 - (std::f32::consts::PI * t).cos()),
+        }
+    }
+}
+
+/// Settings for `denoise_wav`. Deserialized from the frontend (camelCase);
+/// missing fields take their defaults.
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct EnhanceOptions {
+    /// Remove per-channel DC offset before anything else.
+    pub remove_dc: bool,
+    /// Noise suppressor to run.
+    pub method: DenoiseMethod,
+    /// A preset name, or 0.0 (no suppression) to 1.0 (full suppression).
+    pub intensity: DenoiseIntensity,
+    /// Peak or LUFS normalization after denoising.
+    pub normalize_mode: NormalizeMode,
+    /// If set, high-pass filter at this cutoff (~80 Hz is a good default)
+    /// before denoising to remove low-frequency rumble.
+    pub high_pass_hz: Option<f32>,
+    /// Fade in/out length in ms; 0 disables the fade.
+    pub fade_ms: u32,
+    pub fade_shape: FadeShape,
+    /// Soft-limit the final output so nothing clips.
+    pub limiter: bool,
+    /// Apply the denoise gain to each original channel instead of
+    /// duplicating the denoised mono downmix.
+    pub preserve_stereo: bool,
+}
+
+impl Default for EnhanceOptions {
+    fn default() -> Self {
+        Self {
+            remove_dc: true,
+            method: DenoiseMethod::default(),
+            intensity: DenoiseIntensity::default(),
+            normalize_mode: NormalizeMode::Off,
+            high_pass_hz: None,
+            fade_ms: DEFAULT_FADE_MS,
+            fade_shape: FadeShape::default(),
+            limiter: false,
+            preserve_stereo: false,
+        }
+    }
+}
+
+/// Fade applied when the caller doesn't pick one.
+const DEFAULT_FADE_MS: u32 = 50;
+
+/// Level above which the limiter starts compressing (≈ -1 dBFS).
+const LIMITER_THRESHOLD: f32 = 0.891;
+
+/// Named denoise strengths, for users who don't think in raw intensities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DenoisePreset {
+    Light,
+    Medium,
+    Strong,
+}
+
+impl DenoisePreset {
+    pub fn intensity(self) -> f32 {
+        match self {
+            Self::Light => 0.3,
+            Self::Medium => 0.6,
+            Self::Strong => 0.9,
+        }
+    }
+}
+
+/// Denoise strength as sent by the frontend: `"light"`/`"medium"`/`"strong"`
+/// or a raw number.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(untagged)]
+pub enum DenoiseIntensity {
+    Preset(DenoisePreset),
+    Level(f32),
+}
+
+impl Default for DenoiseIntensity {
+    fn default() -> Self {
+        Self::Level(1.0)
+    }
+}
+
+impl DenoiseIntensity {
+    /// The 0.0–1.0 intensity this stands for; raw levels are clamped.
+    pub fn value(self) -> f32 {
+        match self {
+            Self::Preset(preset) => preset.intensity(),
+            Self::Level(level) => level.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Noise suppressor used by `denoise_wav`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DenoiseMethod {
+    /// RNNoise — best on speech.
+    #[default]
+    RnNoise,
+    /// Spectral subtraction of a learned noise profile; better for music
+    /// and ambient recordings, which RNNoise tends to mangle.
+    SpectralGate {
+        #[serde(default)]
+        profile: NoiseProfile,
+    },
+}
+
+/// Where `spectral_gate` learns what the noise sounds like.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NoiseProfile {
+    /// The quietest `QUIETEST_PROFILE_MS` of the recording.
+    #[default]
+    Quietest,
+    /// A stretch the user marked as noise only, in ms from the start.
+    #[serde(rename_all = "camelCase")]
+    Region { start_ms: u32, end_ms: u32 },
+}
+
+/// Loudness normalization applied after denoising.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum NormalizeMode {
+    Off,
+    /// Scale so the loudest sample reaches -1 dBFS.
+    Peak,
+    /// Scale to a target integrated loudness in LUFS (e.g. -16), limited
+    /// so no sample exceeds 0 dBFS.
+    Lufs { target: f32 },
+}
+
 /// Denoise a WAV file and write the result to `output_path`.
 ///
-/// - `intensity`: 0.0 (no suppression) to 1.0 (full suppression)
-/// - `normalize`: if true, peak-normalize to -1dB after denoising
+/// `progress` periodically hears stage `"denoise"` (RNNoise frames), ending
+/// with stage `"write"` at 1 of 1 once the output is written. Setting
+/// `cancel` aborts denoising with `AppError::EnhanceCancelled`.
+///
+/// Fails with `AppError::MisalignedAudioData` if the input's data chunk
+/// isn't a whole number of frames.
 ///
 /// Returns the output path on success.
 pub fn denoise_wav(
     input_path: &str,
     output_path: &str,
-    intensity: f32,
-    normalize: bool,
+    options: &EnhanceOptions,
+    progress: &dyn ProgressReporter,
+    cancel: Option<&AtomicBool>,
 ) -> Result<String, AppError> {
     let (samples, info) = read_wav_f32(input_path)?;
+    let output_samples = enhance_samples(samples, &info, options, progress, cancel)?;
+
+    // Write output WAV
+    write_wav_f32(output_path, &output_samples, &info)?;
+
+    progress.report("write", 1, 1);
+
+    Ok(output_path.to_string())
+}
+
+/// Run [`denoise_wav`]'s pipeline on just the first `seconds` of
+/// `input_path`, so settings can be auditioned on a long recording without
+/// processing all of it. Only that much of the input is read.
+///
+/// Returns the output path on success.
+pub fn denoise_preview(
+    input_path: &str,
+    output_path: &str,
+    options: &EnhanceOptions,
+    seconds: f32,
+    progress: &dyn ProgressReporter,
+) -> Result<String, AppError> {
+    let (samples, info) = read_wav_f32_prefix(input_path, seconds)?;
+    let output_samples = enhance_samples(samples, &info, options, progress, None)?;
+
+    write_wav_f32(output_path, &output_samples, &info)?;
+
+    progress.report("write", 1, 1);
 
-    if info.sample_rate != 48000 {
-        return Err(AppError::AudioEnhance(format!(
-            "Expected 48kHz audio, got {}Hz. RNNoise requires 48kHz.",
-            info.sample_rate
-        )));
+    Ok(output_path.to_string())
+}
+
+/// The enhancement pipeline shared by [`denoise_wav`] and
+/// [`denoise_preview`]: returns exactly as many frames as `samples` holds.
+fn enhance_samples(
+    mut samples: Vec<f32>,
+    info: &WavInfo,
+    options: &EnhanceOptions,
+    progress: &dyn ProgressReporter,
+    cancel: Option<&AtomicBool>,
+) -> Result<Vec<f32>, AppError> {
+    let block_align = info.channels * (info.bits_per_sample / 8);
+    if info.data_size.checked_rem(u32::from(block_align)) != Some(0) {
+        return Err(AppError::MisalignedAudioData { data_size: info.data_size, block_align });
+    }
+    let frames = samples.len() / info.channels as usize;
+
+    if options.remove_dc {
+        remove_dc_offset(&mut samples, info.channels);
+    }
+
+    if let Some(cutoff_hz) = options.high_pass_hz {
+        high_pass(&mut samples, info.channels, info.sample_rate, cutoff_hz);
     }
 
-    // Convert to mono for RNNoise processing
+    // Convert to mono for noise suppression
     let mono = stereo_to_mono(&samples, info.channels);
 
-    // Apply noise suppression
-    let denoised_mono = denoise_mono(&mono, intensity);
+    let intensity = options.intensity.value();
+    let denoised_mono = match options.method {
+        // RNNoise only runs at 48 kHz
+        DenoiseMethod::RnNoise if info.sample_rate == RNNOISE_SAMPLE_RATE => {
+            denoise_mono(&mono, intensity, progress, cancel)?
+        }
+        DenoiseMethod::RnNoise => {
+            let resampled = resample(&mono, info.sample_rate, RNNOISE_SAMPLE_RATE);
+            let denoised = denoise_mono(&resampled, intensity, progress, cancel)?;
+            let mut restored = resample(&denoised, RNNOISE_SAMPLE_RATE, info.sample_rate);
+            // Keep exactly the input length so the timeline is unchanged
+            restored.resize(mono.len(), 0.0);
+            restored
+        }
+        DenoiseMethod::SpectralGate { .. } if intensity == 0.0 => mono.clone(),
+        DenoiseMethod::SpectralGate { profile } => {
+            if cancel.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+                return Err(AppError::EnhanceCancelled);
+            }
+            let gated = spectral_gate(&mono, info.sample_rate, profile)?;
+            mono.iter()
+                .zip(gated)
+                .map(|(&original, clean)| clean * intensity + original * (1.0 - intensity))
+                .collect()
+        }
+    };
 
-    // Convert back to original channel count
-    let mut output_samples = mono_to_multichannel(&denoised_mono, info.channels);
+    let mut output_samples = if options.preserve_stereo && info.channels > 1 {
+        apply_denoise_mask(&mut samples, info.channels, &mono, &denoised_mono);
+        samples
+    } else {
+        // Convert back to original channel count
+        mono_to_multichannel(&denoised_mono, info.channels)
+    };
 
-    // Optional peak normalization to -1dB (0.891)
-    if normalize {
-        peak_normalize(&mut output_samples, 0.891);
+    match options.normalize_mode {
+        NormalizeMode::Off => {}
+        // Peak normalization to -1dB (0.891)
+        NormalizeMode::Peak => peak_normalize(&mut output_samples, 0.891),
+        NormalizeMode::Lufs { target } => {
+            loudness_normalize(&mut output_samples, info.channels, info.sample_rate, target);
+        }
     }
 
-    // Apply fade in/out (50ms) to avoid clicks
-    apply_fade(&mut output_samples, info.sample_rate, 50);
+    // Fade in/out to avoid clicks
+    apply_fade(
+        &mut output_samples,
+        info.channels,
+        info.sample_rate,
+        options.fade_ms,
+        options.fade_shape,
+    );
+
+    // Last stage: tame anything normalization pushed past full scale
+    if options.limiter {
+        soft_limit(&mut output_samples, LIMITER_THRESHOLD);
+    }
 
-    // Write output WAV
-    write_wav_f32(output_path, &output_samples, &info)?;
+    // The header promises whole frames; never end on a torn one
+    let produced = output_samples.len();
+    if fit_to_frames(&mut output_samples, info.channels, frames) {
+        eprintln!(
+            "[enhance] Output had {produced} samples, expected {frames} frames of {} channels; adjusted",
+            info.channels
+        );
+    }
+
+    Ok(output_samples)
+}
 
+/// Pad with silence or trim `samples` to exactly `frames` frames of
+/// `channels`. Returns whether anything changed.
+fn fit_to_frames(samples: &mut Vec<f32>, channels: u16, frames: usize) -> bool {
+    let expected = frames * channels as usize;
+    if samples.len() == expected {
+        return false;
+    }
+    samples.resize(expected, 0.0);
+    true
+}
+
+/// Scale a WAV by `gain_db` and write the result to `output_path` as 32-bit
+/// float. Anything pushed past full scale is soft-clipped rather than
+/// hard-clipped. Returns the output path.
+pub fn apply_gain(input_path: &str, output_path: &str, gain_db: f32) -> Result<String, AppError> {
+    let (mut samples, info) = read_wav_f32(input_path)?;
+    scale_db(&mut samples, gain_db);
+    write_wav_f32(output_path, &samples, &info)?;
+    Ok(output_path.to_string())
+}
+
+/// Average every channel of a WAV into a single-channel 32-bit float WAV at
+/// `output_path`, halving a stereo voice recording. Returns the output path.
+pub fn to_mono(input_path: &str, output_path: &str) -> Result<String, AppError> {
+    let (samples, info) = read_wav_f32(input_path)?;
+    let mono = stereo_to_mono(&samples, info.channels);
+    // write_wav_f32 derives block align and byte rate from these
+    let mono_info = WavInfo {
+        channels: 1,
+        bits_per_sample: 32,
+        is_float: true,
+        data_size: (mono.len() * 4) as u32,
+        ..info
+    };
+    write_wav_f32(output_path, &mono, &mono_info)?;
     Ok(output_path.to_string())
 }
 
+/// Multiply by `gain_db` converted to linear, then soft-limit the peaks.
+fn scale_db(samples: &mut [f32], gain_db: f32) {
+    let gain = 10f32.powf(gain_db / 20.0);
+    for s in samples.iter_mut() {
+        *s *= gain;
+    }
+    soft_limit(samples, LIMITER_THRESHOLD);
+}
+
 // ── Real-time denoiser for capture loop ─────────────────────────────
 
 /// A stateful denoiser that can process audio in streaming fashion.
 /// Designed to be used inside the capture loop without allocations.
-#[allow(dead_code)]
+///
+/// RNNoise works on whole 480-sample frames, so output lags input by up to
+/// one frame: each call returns only what has been denoised so far, and
+/// `flush` returns the rest. Summed over a stream, output length equals
+/// input length exactly.
 pub struct RealtimeDenoiser {
     state: Box<DenoiseState<'static>>,
     intensity: f32,
     channels: u16,
     // Accumulation buffer for partial frames (mono)
     mono_buf: Vec<f32>,
+    // Denoised interleaved output handed back to the caller
+    out: Vec<f32>,
     input_frame: [f32; FRAME_SIZE],
     output_frame: [f32; FRAME_SIZE],
 }
 
-#[allow(dead_code)]
 impl RealtimeDenoiser {
     /// Create a new real-time denoiser.
     /// `intensity`: 0.0 to 1.0 — amount of noise suppression.
     /// `channels`: number of audio channels (1 or 2).
+    #[cfg_attr(not(windows), allow(dead_code))] // only capture builds one
     pub fn new(intensity: f32, channels: u16) -> Self {
         Self {
             state: DenoiseState::new(),
             intensity: intensity.clamp(0.0, 1.0),
-            channels,
+            channels: channels.max(1),
             mono_buf: Vec::with_capacity(FRAME_SIZE * 2),
+            out: Vec::new(),
             input_frame: [0.0f32; FRAME_SIZE],
             output_frame: [0.0f32; FRAME_SIZE],
         }
     }
 
-    /// Process interleaved f32 samples in-place.
-    /// The samples are in [-1.0, 1.0] range (standard WAV float).
-    /// Modifies `samples` in place with denoised audio.
-    pub fn process_interleaved(&mut self, samples: &mut [f32]) {
-        if self.intensity == 0.0 || samples.is_empty() {
-            return;
+    /// Feed interleaved f32 samples in [-1.0, 1.0] and return the denoised
+    /// interleaved samples that are ready. The returned slice may be shorter
+    /// or longer than `samples` (by less than one frame); the remainder is
+    /// kept for the next call or for `flush`.
+    pub fn process_interleaved(&mut self, samples: &[f32]) -> &[f32] {
+        self.out.clear();
+        if self.intensity == 0.0 {
+            self.out.extend_from_slice(samples);
+            return &self.out;
         }
 
+        // Convert to mono and accumulate
         let ch = self.channels as usize;
-
-        // Convert to mono
-        let mono_samples: Vec<f32> = if ch == 1 {
-            samples.to_vec()
-        } else {
+        self.mono_buf.extend(
             samples
                 .chunks_exact(ch)
-                .map(|frame| {
-                    let sum: f32 = frame.iter().sum();
-                    sum / ch as f32
-                })
-                .collect()
-        };
+                .map(|frame| frame.iter().sum::<f32>() / ch as f32),
+        );
 
-        // Accumulate into buffer
-        self.mono_buf.extend_from_slice(&mono_samples);
+        self.denoise_frames();
+        &self.out
+    }
+
+    /// Denoise the buffered partial frame (zero-padded to a full frame) and
+    /// return it, trimmed to the samples actually fed in. Call once at the
+    /// end of the stream.
+    pub fn flush(&mut self) -> Vec<f32> {
+        self.out.clear();
+        let pending = self.mono_buf.len();
+        if pending > 0 {
+            self.mono_buf.resize(FRAME_SIZE, 0.0);
+            self.denoise_frames();
+            self.out.truncate(pending * self.channels as usize);
+        }
+        std::mem::take(&mut self.out)
+    }
 
-        // Process complete frames
-        let mut processed_mono = Vec::with_capacity(mono_samples.len());
+    /// Run every complete frame in `mono_buf` through RNNoise, appending the
+    /// result to `out` spread back to all channels.
+    fn denoise_frames(&mut self) {
+        let ch = self.channels as usize;
         let mut consumed = 0;
 
         while self.mono_buf.len() - consumed >= FRAME_SIZE {
@@ -392,7 +962,8 @@ impl RealtimeDenoiser {
             for i in 0..FRAME_SIZE {
                 let clean = self.output_frame[i] / 32767.0;
                 let original = self.mono_buf[consumed + i];
-                processed_mono.push(clean * self.intensity + original * (1.0 - self.intensity));
+                let mixed = clean * self.intensity + original * (1.0 - self.intensity);
+                self.out.extend(std::iter::repeat_n(mixed, ch));
             }
 
             consumed += FRAME_SIZE;
@@ -400,32 +971,175 @@ impl RealtimeDenoiser {
 
         // Keep unconsumed samples for next call — drain avoids extra allocation
         self.mono_buf.drain(..consumed);
+    }
+}
 
-        // Write back to interleaved output
-        // Only overwrite the portion we have processed mono for
-        let processed_frames = processed_mono.len();
-        if ch == 1 {
-            for (i, &s) in processed_mono.iter().enumerate() {
-                if i < samples.len() {
-                    samples[i] = s;
-                }
-            }
-        } else {
-            // Spread mono back to all channels
-            for (i, &s) in processed_mono.iter().enumerate() {
-                let base = i * ch;
-                for c in 0..ch {
-                    if base + c < samples.len() {
-                        samples[base + c] = s;
-                    }
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_util::{TempWav, WavFixture};
+    use crate::progress::NoProgress;
+
+    #[test]
+    fn spectral_gate_removes_noise_and_keeps_the_tone() {
+        // 0.5 s of noise alone, then the same noise under a 440 Hz tone
+        let samples = WavFixture {
+            channels: 1,
+            tone_onset: 24_000,
+            noise_amplitude: 0.025,
+            ..WavFixture::default()
         }
+        .samples();
+        let rms = |s: &[f32]| (s.iter().map(|x| x * x).sum::<f32>() / s.len() as f32).sqrt();
+
+        let profile = NoiseProfile::Region { start_ms: 0, end_ms: 500 };
+        let gated = spectral_gate(&samples, 48_000, profile).unwrap();
+
+        assert_eq!(gated.len(), samples.len());
+        assert!(rms(&gated[2_000..22_000]) < rms(&samples[2_000..22_000]) * 0.2);
+        let tone_ratio = rms(&gated[26_000..46_000]) / rms(&samples[26_000..46_000]);
+        assert!(tone_ratio > 0.9, "tone kept at {tone_ratio}");
+    }
+
+    #[test]
+    fn denoise_wav_keeps_the_input_format_and_length() {
+        // 48 kHz runs RNNoise directly; 44.1 kHz goes through the resampler
+        let fixtures = [
+            WavFixture::default(),
+            WavFixture { channels: 1, sample_rate: 44_100, frames: 44_100, ..WavFixture::default() },
+        ];
+        for fixture in fixtures {
+            let input = fixture.write();
+            let output = TempWav::new();
+
+            denoise_wav(input.path(), output.path(), &EnhanceOptions::default(), &NoProgress, None).unwrap();
+
+            let (samples, info) = read_wav_f32(output.path()).unwrap();
+            assert_eq!((info.channels, info.sample_rate), (fixture.channels, fixture.sample_rate));
+            assert_eq!(samples.len(), fixture.frames * fixture.channels as usize);
+        }
+    }
+
+    #[test]
+    fn denoise_wav_drops_a_torn_last_frame() {
+        // An interrupted stereo recording: the header still claims the full
+        // size, and the file ends one byte into a frame
+        let fixture = WavFixture::default();
+        let mut bytes = fixture.bytes();
+        bytes.truncate(bytes.len() - 3);
+        let input = TempWav::new();
+        std::fs::write(input.path(), bytes).unwrap();
+        let output = TempWav::new();
+
+        denoise_wav(input.path(), output.path(), &EnhanceOptions::default(), &NoProgress, None).unwrap();
+
+        let (samples, _) = read_wav_f32(output.path()).unwrap();
+        assert_eq!(samples.len(), (fixture.frames - 1) * 2);
+    }
+
+    #[test]
+    fn preview_covers_only_the_first_seconds() {
+        let input = WavFixture::default().write();
+        let output = TempWav::new();
+
+        denoise_preview(input.path(), output.path(), &EnhanceOptions::default(), 0.25, &NoProgress).unwrap();
+
+        let (samples, info) = read_wav_f32(output.path()).unwrap();
+        assert_eq!((info.channels, info.sample_rate), (2, 48_000));
+        assert_eq!(samples.len(), 12_000 * 2);
+    }
+
+    #[test]
+    fn intensity_accepts_a_preset_name_or_a_number() {
+        let parse = |json: &str| serde_json::from_str::<DenoiseIntensity>(json).unwrap().value();
+        assert_eq!(parse(r#""medium""#), 0.6);
+        assert_eq!(parse("0.37"), 0.37);
+        assert_eq!(parse("1.5"), 1.0);
+    }
+
+    #[test]
+    fn fit_to_frames_pads_and_trims_to_whole_frames() {
+        let mut torn = vec![0.5f32; 7];
+        assert!(fit_to_frames(&mut torn, 2, 3));
+        assert_eq!(torn, [0.5; 6]);
+
+        let mut short = vec![0.5f32; 3];
+        assert!(fit_to_frames(&mut short, 2, 3));
+        assert_eq!(short, [0.5, 0.5, 0.5, 0.0, 0.0, 0.0]);
+
+        let mut aligned = vec![0.5f32; 6];
+        assert!(!fit_to_frames(&mut aligned, 2, 3));
+    }
+
+    #[test]
+    fn read_wav_f32_reads_back_what_write_wav_f32_wrote() {
+        let output = TempWav::new();
+        let samples: Vec<f32> = (0..480).map(|i| (i as f32 / 480.0) - 0.5).collect();
+        let info = WavInfo {
+            channels: 2,
+            sample_rate: 44_100,
+            bits_per_sample: 32,
+            is_float: true,
+            data_offset: 44,
+            data_size: 0,
+        };
+
+        write_wav_f32(output.path(), &samples, &info).unwrap();
+        let (read, read_info) = read_wav_f32(output.path()).unwrap();
+
+        assert_eq!((read_info.channels, read_info.sample_rate, read_info.is_float), (2, 44_100, true));
+        assert_eq!(read, samples);
+    }
+
+    #[test]
+    fn scale_db_doubles_amplitude_at_6_db_and_limits_clipping() {
+        let mut quiet = vec![0.1f32, -0.2, 0.3];
+        scale_db(&mut quiet, 6.0);
+        for (scaled, original) in quiet.iter().zip([0.1f32, -0.2, 0.3]) {
+            assert!((scaled / original - 1.995).abs() < 0.01, "{scaled} vs {original}");
+        }
+
+        let mut hot = vec![0.8f32, -0.9];
+        scale_db(&mut hot, 6.0);
+        assert!(hot.iter().all(|s| s.abs() <= 1.0), "{hot:?} exceeds full scale");
+    }
+
+    #[test]
+    fn soft_limit_keeps_hot_signal_below_full_scale() {
+        let mut samples: Vec<f32> = (0..4800)
+            .map(|i| 3.0 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48_000.0).sin())
+            .collect();
+
+        soft_limit(&mut samples, LIMITER_THRESHOLD);
+
+        let peak = samples.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+        assert!(peak <= 1.0, "peak {peak} exceeds full scale");
+        assert!(peak > LIMITER_THRESHOLD);
+    }
+
+    #[test]
+    fn remove_dc_offset_centers_each_channel() {
+        // Stereo sine with a different bias on each channel
+        let mut samples: Vec<f32> = (0..48_000)
+            .flat_map(|i| {
+                let s = 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48_000.0).sin();
+                [s + 0.2, s - 0.1]
+            })
+            .collect();
+
+        remove_dc_offset(&mut samples, 2);
+
+        for c in 0..2 {
+            let mean = samples.iter().skip(c).step_by(2).sum::<f32>() / 48_000.0;
+            assert!(mean.abs() < 1e-4, "channel {c} mean {mean}");
+        }
+    }
 
-        // Zero out any trailing samples that weren't processed
-        // (these are the partial frame that's buffered for next call)
-        let _processed_interleaved = processed_frames * ch;
-        // Trailing samples past processed_interleaved are left as-is
-        // (they correspond to the buffered partial frame for next call)
+    #[test]
+    fn soft_limit_leaves_quiet_samples_untouched() {
+        let original = [0.0, 0.25, -0.5, 0.8];
+        let mut samples = original;
+        soft_limit(&mut samples, LIMITER_THRESHOLD);
+        assert_eq!(samples, original);
     }
 }
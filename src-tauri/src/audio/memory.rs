@@ -0,0 +1,92 @@
+use crate::error::AppError;
+
+use super::resample::{StreamResampler, ASR_SAMPLE_RATE};
+use super::sink::AudioSink;
+use super::{AudioFormat, RealtimeDenoiser};
+
+/// Keeps a capture in memory as mono 16 kHz f32, ready for transcription.
+///
+/// Audio is downmixed and resampled as it arrives, so a clip costs 64 KB
+/// per second whatever the device format, and no WAV is ever written.
+pub struct MemorySink {
+    format: AudioFormat,
+    resampler: StreamResampler,
+    samples: Vec<f32>,
+    /// Denoised copy of the incoming samples.
+    scratch_denoise: Vec<f32>,
+    /// One buffer downmixed to mono, before resampling.
+    mono: Vec<f32>,
+    denoiser: Option<RealtimeDenoiser>,
+}
+
+impl MemorySink {
+    pub fn new(format: AudioFormat) -> Self {
+        Self {
+            format,
+            resampler: StreamResampler::new(format.sample_rate, ASR_SAMPLE_RATE),
+            // ~10 s before the first reallocation
+            samples: Vec::with_capacity(ASR_SAMPLE_RATE as usize * 10),
+            scratch_denoise: Vec::new(),
+            mono: Vec::new(),
+            denoiser: None,
+        }
+    }
+
+    /// Downmix interleaved samples to mono and append them at 16 kHz.
+    fn push(&mut self, samples: &[f32]) {
+        let ch = self.channels();
+        self.mono.clear();
+        self.mono.extend(
+            samples
+                .chunks_exact(ch)
+                .map(|frame| frame.iter().sum::<f32>() / ch as f32),
+        );
+        self.resampler.process(&self.mono, &mut self.samples);
+    }
+}
+
+impl AudioSink for MemorySink {
+    /// Mono 16 kHz samples of the whole capture.
+    type Output = Vec<f32>;
+
+    fn set_denoiser(&mut self, denoiser: RealtimeDenoiser) {
+        self.denoiser = Some(denoiser);
+    }
+
+    fn write_silence(&mut self, frame_count: usize) -> Result<(), AppError> {
+        self.mono.clear();
+        self.mono.resize(frame_count, 0.0);
+        self.resampler.process(&self.mono, &mut self.samples);
+        Ok(())
+    }
+
+    fn write_samples(&mut self, samples: &[f32]) -> Result<(), AppError> {
+        let Some(denoiser) = self.denoiser.as_mut() else {
+            self.push(samples);
+            return Ok(());
+        };
+
+        let mut buf = std::mem::take(&mut self.scratch_denoise);
+        buf.clear();
+        buf.extend_from_slice(denoiser.process_interleaved(samples));
+        self.push(&buf);
+        self.scratch_denoise = buf;
+        Ok(())
+    }
+
+    fn channels(&self) -> usize {
+        self.format.channels as usize
+    }
+
+    fn file_bytes(&self) -> u64 {
+        (self.samples.len() * std::mem::size_of::<f32>()) as u64
+    }
+
+    fn finalize(mut self) -> Result<Vec<f32>, AppError> {
+        if let Some(mut denoiser) = self.denoiser.take() {
+            let tail = denoiser.flush();
+            self.push(&tail);
+        }
+        Ok(self.samples)
+    }
+}
@@ -0,0 +1,109 @@
+use super::aec::EchoCanceller;
+use super::resample::StreamResampler;
+use super::MicMix;
+
+/// How far (in ms) one source may run ahead before it is mixed against silence.
+///
+/// Loopback delivers no packets while nothing is playing, so without this the
+/// microphone would pile up until the next system sound.
+const MAX_LAG_MS: u32 = 100;
+
+/// Mixes the loopback (system) stream with the microphone into one
+/// interleaved buffer in the system stream's format.
+///
+/// The mic is downmixed to mono, resampled to the system rate, and added to
+/// every output channel. With echo cancellation on, the system stream is
+/// used as the reference to remove speaker bleed from the mic first. All
+/// buffers are reused between calls.
+pub struct StreamMixer {
+    channels: usize,
+    system_gain: f32,
+    mic_gain: f32,
+    /// Pending interleaved system samples.
+    system: Vec<f32>,
+    /// Pending mono mic samples, already at the system rate.
+    mic: Vec<f32>,
+    mic_mono: Vec<f32>,
+    resampler: StreamResampler,
+    echo_canceller: Option<EchoCanceller>,
+    max_lag_frames: usize,
+    out: Vec<f32>,
+}
+
+impl StreamMixer {
+    pub fn new(channels: u16, system_rate: u32, mic_rate: u32, mix: MicMix) -> Self {
+        let capacity = system_rate as usize; // ~1 s of headroom per buffer
+        Self {
+            channels: channels.max(1) as usize,
+            system_gain: mix.system_gain,
+            mic_gain: mix.mic_gain,
+            system: Vec::with_capacity(capacity * channels as usize),
+            mic: Vec::with_capacity(capacity),
+            mic_mono: Vec::with_capacity(capacity),
+            resampler: StreamResampler::new(mic_rate, system_rate),
+            echo_canceller: mix.echo_cancel.then(|| EchoCanceller::new(system_rate)),
+            max_lag_frames: (system_rate * MAX_LAG_MS / 1000) as usize,
+            out: Vec::with_capacity(capacity * channels as usize),
+        }
+    }
+
+    /// Queue interleaved system samples.
+    pub fn push_system(&mut self, samples: &[f32]) {
+        self.system.extend_from_slice(samples);
+    }
+
+    /// Queue interleaved mic samples with `channels` channels at the mic rate.
+    pub fn push_mic(&mut self, samples: &[f32], channels: u16) {
+        let ch = channels.max(1) as usize;
+        self.mic_mono.clear();
+        self.mic_mono.extend(
+            samples
+                .chunks_exact(ch)
+                .map(|frame| frame.iter().sum::<f32>() / ch as f32),
+        );
+        self.resampler.process(&self.mic_mono, &mut self.mic);
+    }
+
+    /// Mix every frame both sources have delivered. If one source has run
+    /// ahead by more than `MAX_LAG_MS`, its backlog is mixed against silence.
+    pub fn mix(&mut self) -> &[f32] {
+        let system_frames = self.system.len() / self.channels;
+        let mic_frames = self.mic.len();
+        let both = system_frames.min(mic_frames);
+        let ahead = system_frames.max(mic_frames);
+
+        let frames = if ahead - both > self.max_lag_frames { ahead } else { both };
+        self.mix_frames(frames)
+    }
+
+    /// Mix everything still queued, padding the shorter source with silence.
+    pub fn flush(&mut self) -> &[f32] {
+        let frames = (self.system.len() / self.channels).max(self.mic.len());
+        self.mix_frames(frames)
+    }
+
+    fn mix_frames(&mut self, frames: usize) -> &[f32] {
+        let ch = self.channels;
+        self.out.clear();
+        for f in 0..frames {
+            let system = self.system.get(f * ch..(f + 1) * ch).unwrap_or(&[]);
+            let mut mic = self.mic.get(f).copied().unwrap_or(0.0);
+            if let Some(aec) = self.echo_canceller.as_mut() {
+                let far = system.iter().sum::<f32>() / ch as f32;
+                mic = aec.process(far, mic);
+            }
+            let mic = mic * self.mic_gain;
+            for c in 0..ch {
+                let system = system.get(c).copied().unwrap_or(0.0);
+                self.out.push(system * self.system_gain + mic);
+            }
+        }
+
+        let system_used = (frames * ch).min(self.system.len());
+        self.system.drain(..system_used);
+        let mic_used = frames.min(self.mic.len());
+        self.mic.drain(..mic_used);
+
+        &self.out
+    }
+}
@@ -1,36 +1,378 @@
 #[cfg(windows)]
 mod wasapi;
-#[cfg(windows)]
 mod wav;
+mod flac;
+#[cfg(feature = "mp3")]
+mod mp3;
+mod sink;
+#[cfg(windows)]
+mod memory;
 #[cfg(windows)]
 mod capture;
+#[cfg(windows)]
+mod meter;
+#[cfg(windows)]
+mod mix;
+#[cfg(windows)]
+mod aec;
+#[cfg(windows)]
+mod spectrum;
+#[cfg(windows)]
+mod ring;
+mod transcode;
+mod resample;
+mod wav_format;
+mod wav_reader;
 mod enhance;
+mod split;
+#[cfg(test)]
+pub(crate) mod test_util;
 
 #[cfg(windows)]
 pub use capture::SystemAudioHandle;
-pub use enhance::denoise_wav;
+#[cfg(windows)]
+pub use ring::RingCapture;
+pub use transcode::transcode;
+pub use wav::concat_wavs;
+pub use enhance::{apply_gain, denoise_preview, denoise_wav, to_mono, DenoiseIntensity, EnhanceOptions};
+pub use wav_format::WavInfo;
+pub use wav_reader::{probe_wav, read_wav_f32};
+pub use resample::resample_for_asr;
+pub use split::{split_on_silence, SplitOptions};
+use enhance::RealtimeDenoiser;
+#[cfg(windows)]
+use enhance::RNNOISE_SAMPLE_RATE;
+
+/// Sample layout of a capture or a file being written: the device's mix
+/// format, or whatever a writer was asked to produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    pub is_float: bool,
+}
+
+/// Settings for a capture session, fixed when it starts.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureConfig {
+    /// Render endpoint to capture; `None` uses the default output device.
+    pub device_id: Option<String>,
+    /// Capture one application's audio instead of a device's whole mix;
+    /// `device_id` is then ignored.
+    pub process: Option<ProcessLoopback>,
+    /// Mix the default microphone into the recording.
+    pub mic_mix: Option<MicMix>,
+    /// Container of the output file.
+    pub format: RecordingFormat,
+    /// Sample encoding of the output file (FLAC records `Float32` as 24-bit;
+    /// MP3 ignores it).
+    pub bit_depth: BitDepth,
+    /// MP3 bitrate, one of `MP3_BITRATES_KBPS`; `None` uses 192 kbps.
+    pub bitrate_kbps: Option<u32>,
+    /// Live RNNoise strength (0.0–1.0); 0.0 records the signal untouched.
+    pub denoise_intensity: f32,
+    /// Emit `audio-spectrum` bands alongside the level events.
+    pub spectrum: bool,
+    /// Period of the `audio-level` (and stats/spectrum) events; `None` uses
+    /// ~100 ms.
+    pub emit_interval_ms: Option<u32>,
+    /// Resample to this rate before writing; `None` keeps the device's rate.
+    pub target_sample_rate: Option<u32>,
+    /// What to do when capture stops before any audio arrived.
+    pub empty_capture: EmptyCapture,
+    /// WAV write buffer size; `None` uses 256 KB.
+    pub buffer_bytes: Option<usize>,
+    /// Run the capture thread under MMCSS "Pro Audio" for fewer glitches
+    /// under load. It then preempts normal threads, so a busy capture can
+    /// starve the UI and other apps; meant for dedicated recording setups.
+    pub high_priority: bool,
+    /// Stop on its own once this much wall-clock time has passed (paused
+    /// spans included); `None` records until stopped.
+    pub max_duration_ms: Option<u64>,
+}
+
+/// Signalled when a capture thread exits, however it ended: stopped,
+/// aborted, past `max_duration_ms`, or failed. Lets a caller wait for a
+/// capture without holding the lock on its handle.
+#[derive(Default)]
+pub struct CaptureFinished {
+    finished: std::sync::Mutex<bool>,
+    cond: std::sync::Condvar,
+}
+
+impl CaptureFinished {
+    #[cfg(windows)]
+    fn signal(&self) {
+        *self.finished.lock().unwrap_or_else(std::sync::PoisonError::into_inner) = true;
+        self.cond.notify_all();
+    }
+
+    /// Block until the capture thread has exited.
+    pub fn wait(&self) {
+        let mut finished = self.finished.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        while !*finished {
+            finished = self.cond.wait(finished).unwrap_or_else(std::sync::PoisonError::into_inner);
+        }
+    }
+}
+
+/// Handling of a capture stopped before the first packet arrived (e.g. a
+/// rapid record toggle), which would otherwise leave a header-only file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyCapture {
+    /// Write `EMPTY_CAPTURE_PADDING_MS` of silence so the file is playable.
+    #[default]
+    Pad,
+    /// Fail with `RecordingTooShort` and delete the file.
+    Reject,
+}
+
+/// Silence written for `EmptyCapture::Pad`.
+pub const EMPTY_CAPTURE_PADDING_MS: u32 = 100;
+
+impl EmptyCapture {
+    /// Frames of silence to append to a capture that wrote `total_frames`:
+    /// zero unless it is empty.
+    pub fn padding_frames(
+        self,
+        total_frames: u64,
+        sample_rate: u32,
+    ) -> Result<usize, crate::error::AppError> {
+        match (total_frames, self) {
+            (1.., _) => Ok(0),
+            (0, Self::Pad) => Ok((sample_rate * EMPTY_CAPTURE_PADDING_MS / 1000) as usize),
+            (0, Self::Reject) => Err(crate::error::AppError::RecordingTooShort),
+        }
+    }
+}
+
+/// Container a recording is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    #[default]
+    Wav,
+    /// Lossless FLAC — roughly half the size of 16-bit WAV.
+    Flac,
+    /// Constant-bitrate MP3 for sharing. Needs a build with the `mp3`
+    /// feature (LAME).
+    Mp3,
+}
+
+/// Error for `RecordingFormat::Mp3` in builds without LAME.
+#[cfg(not(feature = "mp3"))]
+fn mp3_unavailable() -> crate::error::AppError {
+    crate::error::AppError::Mp3Encode("MP3 encoding is not available in this build (enable the `mp3` feature)".into())
+}
+
+/// Bitrates an MP3 recording can use, in kbps.
+pub const MP3_BITRATES_KBPS: [u32; 16] =
+    [8, 16, 24, 32, 40, 48, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+
+impl RecordingFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Flac => "flac",
+            Self::Mp3 => "mp3",
+        }
+    }
+}
+
+/// Sample encoding of a recorded file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BitDepth {
+    /// 32-bit IEEE float (lossless w.r.t. WASAPI's shared-mode mix format).
+    #[default]
+    Float32,
+    /// 16-bit integer PCM — half the size, fine for archival and speech.
+    Pcm16,
+}
+
+impl BitDepth {
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            Self::Float32 => 4,
+            Self::Pcm16 => 2,
+        }
+    }
+}
+
+/// Per-source gains when mixing the microphone with system audio.
+#[derive(Debug, Clone, Copy)]
+pub struct MicMix {
+    pub mic_gain: f32,
+    pub system_gain: f32,
+    /// Subtract the system audio the mic picks up from the speakers.
+    pub echo_cancel: bool,
+}
+
+/// Target of a per-process capture (Windows 10 2004 and later).
+#[derive(Debug, Clone, Copy)]
+pub struct ProcessLoopback {
+    pub pid: u32,
+    /// Also capture the processes `pid` started, e.g. a browser's tab
+    /// processes; otherwise only audio `pid` plays itself.
+    pub include_tree: bool,
+}
+
+/// Device details reported once a capture session has started.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CaptureInfo {
+    /// Endpoint ID of the render device actually being captured.
+    pub device_id: String,
+    /// Friendly name of that device.
+    pub device_name: String,
+    /// Friendly name of the microphone, when one is mixed in.
+    pub mic_device_name: Option<String>,
+    /// Mix format the device opened with. Recordings keep it unless
+    /// `target_sample_rate` resamples them.
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub is_float: bool,
+    /// File being recorded to; `None` for memory captures.
+    pub output_path: Option<String>,
+}
 
 #[cfg(windows)]
 pub fn check_system_audio_available() -> bool {
     wasapi::check_available()
 }
 
+/// What a "check your setup" screen needs to know about system audio.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SystemAudioReport {
+    pub available: bool,
+    pub default_device_name: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub is_float: Option<bool>,
+}
+
+/// Describe the default output device without starting a capture. Any
+/// failure just leaves the report unavailable.
+#[cfg(windows)]
+pub fn system_audio_report() -> SystemAudioReport {
+    match wasapi::probe_default_device() {
+        Ok((name, format)) => SystemAudioReport {
+            available: true,
+            default_device_name: Some(name).filter(|n| !n.is_empty()),
+            sample_rate: Some(format.sample_rate),
+            channels: Some(format.channels),
+            is_float: Some(format.is_float),
+        },
+        Err(e) => {
+            eprintln!("[audio] System audio probe failed: {e}");
+            SystemAudioReport::default()
+        }
+    }
+}
+
+/// List active output (render) devices as `(id, friendly_name)` pairs.
+#[cfg(windows)]
+pub fn list_output_devices() -> Result<Vec<(String, String)>, crate::error::AppError> {
+    wasapi::list_output_devices()
+}
+
 // ── Non-Windows stubs ───────────────────────────────────────────────
+
+/// Uninhabited on non-Windows: `start` always fails, so no handle can exist.
 #[cfg(not(windows))]
-pub struct SystemAudioHandle;
+pub struct SystemAudioHandle<T = String> {
+    never: std::convert::Infallible,
+    _output: std::marker::PhantomData<T>,
+}
+
+#[cfg(not(windows))]
+fn capture_unsupported() -> crate::error::AppError {
+    crate::error::AppError::AudioCapture("System audio capture is only supported on Windows".into())
+}
 
 #[cfg(not(windows))]
 impl SystemAudioHandle {
-    pub fn start(_output_path: String) -> Result<Self, crate::error::AppError> {
-        Err(crate::error::AppError::AudioCapture(
-            "System audio capture is only supported on Windows".into(),
-        ))
+    pub fn start(
+        _output_path: String,
+        _config: CaptureConfig,
+        _app: tauri::AppHandle,
+    ) -> Result<Self, crate::error::AppError> {
+        Err(capture_unsupported())
+    }
+}
+
+#[cfg(not(windows))]
+impl SystemAudioHandle<Vec<f32>> {
+    pub fn start_memory(
+        _config: CaptureConfig,
+        _app: tauri::AppHandle,
+    ) -> Result<Self, crate::error::AppError> {
+        Err(capture_unsupported())
+    }
+}
+
+#[cfg(not(windows))]
+impl<T> SystemAudioHandle<T> {
+    pub fn info(&self) -> &CaptureInfo {
+        match self.never {}
+    }
+
+    pub fn elapsed(&self) -> std::time::Duration {
+        match self.never {}
+    }
+
+    pub fn output_path(&self) -> Option<&str> {
+        match self.never {}
+    }
+
+    pub fn pause(&self, _keep_timeline: bool) -> Result<(), crate::error::AppError> {
+        match self.never {}
+    }
+
+    pub fn resume(&self) -> Result<(), crate::error::AppError> {
+        match self.never {}
+    }
+
+    pub fn stop(&mut self) -> Result<T, crate::error::AppError> {
+        match self.never {}
+    }
+
+    pub fn abort(&mut self) -> Result<(), crate::error::AppError> {
+        match self.never {}
+    }
+
+    pub fn finished(&self) -> std::sync::Arc<CaptureFinished> {
+        match self.never {}
+    }
+}
+
+/// Uninhabited on non-Windows, like `SystemAudioHandle`.
+#[cfg(not(windows))]
+pub struct RingCapture {
+    never: std::convert::Infallible,
+}
+
+#[cfg(not(windows))]
+impl RingCapture {
+    pub fn start(
+        _config: CaptureConfig,
+        _app: tauri::AppHandle,
+        _seconds: u32,
+    ) -> Result<Self, crate::error::AppError> {
+        Err(capture_unsupported())
+    }
+
+    pub fn info(&self) -> &CaptureInfo {
+        match self.never {}
+    }
+
+    pub fn save_clip(&self, _path: &str) -> Result<u64, crate::error::AppError> {
+        match self.never {}
     }
 
-    pub fn stop(&mut self) -> Result<String, crate::error::AppError> {
-        Err(crate::error::AppError::AudioCapture(
-            "System audio capture is only supported on Windows".into(),
-        ))
+    pub fn stop(self) -> Result<(), crate::error::AppError> {
+        match self.never {}
     }
 }
 
@@ -38,3 +380,34 @@ impl SystemAudioHandle {
 pub fn check_system_audio_available() -> bool {
     false
 }
+
+#[cfg(not(windows))]
+pub fn system_audio_report() -> SystemAudioReport {
+    SystemAudioReport::default()
+}
+
+#[cfg(not(windows))]
+pub fn list_output_devices() -> Result<Vec<(String, String)>, crate::error::AppError> {
+    Ok(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AppError;
+
+    #[test]
+    fn empty_capture_pad_writes_silence_only_when_nothing_was_captured() {
+        assert_eq!(EmptyCapture::Pad.padding_frames(0, 48_000).unwrap(), 4_800);
+        assert_eq!(EmptyCapture::Pad.padding_frames(1, 48_000).unwrap(), 0);
+    }
+
+    #[test]
+    fn empty_capture_reject_fails_only_when_nothing_was_captured() {
+        assert!(matches!(
+            EmptyCapture::Reject.padding_frames(0, 48_000),
+            Err(AppError::RecordingTooShort)
+        ));
+        assert_eq!(EmptyCapture::Reject.padding_frames(480, 48_000).unwrap(), 0);
+    }
+}
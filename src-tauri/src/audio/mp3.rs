@@ -0,0 +1,207 @@
+use crate::error::AppError;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use mp3lame_encoder::{Bitrate, Builder, Encoder, FlushGap, InterleavedPcm, MonoPcm, Quality};
+
+use super::sink::AudioSink;
+use super::{AudioFormat, RealtimeDenoiser};
+
+const BUF_CAPACITY: usize = 256 * 1024;
+/// Bitrate when the caller doesn't pick one.
+const DEFAULT_BITRATE_KBPS: u32 = 192;
+
+/// Streaming MP3 `AudioSink` backed by LAME, interchangeable with
+/// `AudioWavWriter`. Encodes constant bitrate so players can seek and
+/// estimate duration without a Xing header.
+///
+/// MP3 carries at most two channels: surround devices are recorded as their
+/// front left/right pair. LAME buffers up to a frame plus its look-ahead
+/// internally; `finalize()` flushes it.
+pub struct Mp3Writer {
+    writer: BufWriter<File>,
+    encoder: Encoder,
+    format: AudioFormat,
+    /// Channels handed to LAME: the device's, capped at stereo.
+    out_channels: usize,
+    /// Input reduced to `out_channels`, about to be encoded.
+    pcm: Vec<f32>,
+    /// Encoder output, reused between writes.
+    mp3: Vec<u8>,
+    bytes_written: u64,
+    /// Applied to every buffer before it is encoded, when set.
+    denoiser: Option<RealtimeDenoiser>,
+    scratch_denoise: Vec<f32>,
+    /// Zeros for `write_silence`, reused so gap filling never allocates.
+    scratch_silence: Vec<f32>,
+}
+
+impl Mp3Writer {
+    /// Create a new MP3 file at `path`, encoded at `bitrate_kbps` (one of
+    /// `MP3_BITRATES_KBPS`; `None` uses 192).
+    pub fn create(
+        path: &str,
+        format: AudioFormat,
+        bitrate_kbps: Option<u32>,
+    ) -> Result<Self, AppError> {
+        if format.channels == 0 {
+            return Err(AppError::Mp3Encode("0 channels".into()));
+        }
+        let out_channels = format.channels.min(2);
+
+        let mut builder = Builder::new()
+            .ok_or_else(|| AppError::Mp3Encode("Create LAME encoder".into()))?;
+        builder
+            .set_num_channels(out_channels as u8)
+            .map_err(|e| AppError::Mp3Encode(format!("Set channels: {e:?}")))?;
+        builder
+            .set_sample_rate(format.sample_rate)
+            .map_err(|e| AppError::Mp3Encode(format!("Set sample rate: {e:?}")))?;
+        builder
+            .set_brate(bitrate(bitrate_kbps.unwrap_or(DEFAULT_BITRATE_KBPS))?)
+            .map_err(|e| AppError::Mp3Encode(format!("Set bitrate: {e:?}")))?;
+        builder
+            .set_quality(Quality::Good)
+            .map_err(|e| AppError::Mp3Encode(format!("Set quality: {e:?}")))?;
+        let encoder = builder
+            .build()
+            .map_err(|e| AppError::Mp3Encode(format!("Initialize LAME: {e:?}")))?;
+
+        let file = File::create(path)
+            .map_err(|e| AppError::Mp3Encode(format!("Create MP3 file: {e}")))?;
+
+        Ok(Self {
+            writer: BufWriter::with_capacity(BUF_CAPACITY, file),
+            encoder,
+            format,
+            out_channels: out_channels as usize,
+            pcm: Vec::new(),
+            mp3: Vec::new(),
+            bytes_written: 0,
+            denoiser: None,
+            scratch_denoise: Vec::new(),
+            scratch_silence: Vec::new(),
+        })
+    }
+
+    /// Reduce interleaved device-format samples to `out_channels` and encode
+    /// them.
+    fn encode(&mut self, samples: &[f32]) -> Result<(), AppError> {
+        let ch = self.channels();
+        self.pcm.clear();
+        self.pcm.extend(
+            samples
+                .chunks_exact(ch)
+                .flat_map(|frame| frame[..self.out_channels].iter().copied()),
+        );
+        let frames = self.pcm.len() / self.out_channels;
+        if frames == 0 {
+            return Ok(());
+        }
+
+        self.mp3.clear();
+        self.mp3.reserve(mp3lame_encoder::max_required_buffer_size(frames));
+        let encoded = if self.out_channels == 1 {
+            self.encoder.encode(MonoPcm(&self.pcm), self.mp3.spare_capacity_mut())
+        } else {
+            self.encoder.encode(InterleavedPcm(&self.pcm), self.mp3.spare_capacity_mut())
+        }
+        .map_err(|e| AppError::Mp3Encode(format!("Encode: {e:?}")))?;
+        // SAFETY: LAME initialized the first `encoded` bytes of the spare capacity
+        unsafe { self.mp3.set_len(encoded) };
+
+        self.write_mp3()
+    }
+
+    /// Write whatever the last encode produced.
+    fn write_mp3(&mut self) -> Result<(), AppError> {
+        self.writer.write_all(&self.mp3)
+            .map_err(|e| AppError::Mp3Encode(format!("Write: {e}")))?;
+        self.bytes_written += self.mp3.len() as u64;
+        Ok(())
+    }
+}
+
+/// LAME's bitrate for `kbps`, which must be one of `MP3_BITRATES_KBPS`.
+fn bitrate(kbps: u32) -> Result<Bitrate, AppError> {
+    Ok(match kbps {
+        8 => Bitrate::Kbps8,
+        16 => Bitrate::Kbps16,
+        24 => Bitrate::Kbps24,
+        32 => Bitrate::Kbps32,
+        40 => Bitrate::Kbps40,
+        48 => Bitrate::Kbps48,
+        64 => Bitrate::Kbps64,
+        80 => Bitrate::Kbps80,
+        96 => Bitrate::Kbps96,
+        112 => Bitrate::Kbps112,
+        128 => Bitrate::Kbps128,
+        160 => Bitrate::Kbps160,
+        192 => Bitrate::Kbps192,
+        224 => Bitrate::Kbps224,
+        256 => Bitrate::Kbps256,
+        320 => Bitrate::Kbps320,
+        _ => return Err(AppError::Mp3Encode(format!("Unsupported bitrate: {kbps} kbps"))),
+    })
+}
+
+impl AudioSink for Mp3Writer {
+    type Output = ();
+
+    fn set_denoiser(&mut self, denoiser: RealtimeDenoiser) {
+        self.denoiser = Some(denoiser);
+    }
+
+    fn write_silence(&mut self, frame_count: usize) -> Result<(), AppError> {
+        let mut silence = std::mem::take(&mut self.scratch_silence);
+        silence.clear();
+        silence.resize(frame_count * self.channels(), 0.0);
+        let result = self.encode(&silence);
+        self.scratch_silence = silence;
+        result
+    }
+
+    fn write_samples(&mut self, samples: &[f32]) -> Result<(), AppError> {
+        let Some(denoiser) = self.denoiser.as_mut() else {
+            return self.encode(samples);
+        };
+
+        let mut buf = std::mem::take(&mut self.scratch_denoise);
+        buf.clear();
+        buf.extend_from_slice(denoiser.process_interleaved(samples));
+        let result = self.encode(&buf);
+        self.scratch_denoise = buf;
+        result
+    }
+
+    fn channels(&self) -> usize {
+        self.format.channels as usize
+    }
+
+    /// Current file size in bytes (audio still inside LAME is not counted).
+    fn file_bytes(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Encode the denoiser's tail, drain LAME's buffered frames, and flush.
+    fn finalize(mut self) -> Result<(), AppError> {
+        if let Some(mut denoiser) = self.denoiser.take() {
+            let tail = denoiser.flush();
+            self.encode(&tail)?;
+        }
+
+        self.mp3.clear();
+        self.mp3.reserve(mp3lame_encoder::max_required_buffer_size(0));
+        let flushed = self.encoder
+            .flush::<FlushGap>(self.mp3.spare_capacity_mut())
+            .map_err(|e| AppError::Mp3Encode(format!("Flush encoder: {e:?}")))?;
+        // SAFETY: LAME initialized the first `flushed` bytes of the spare capacity
+        unsafe { self.mp3.set_len(flushed) };
+        self.write_mp3()?;
+
+        self.writer.flush()
+            .map_err(|e| AppError::Mp3Encode(format!("Final flush: {e}")))?;
+
+        Ok(())
+    }
+}
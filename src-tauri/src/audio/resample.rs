@@ -0,0 +1,215 @@
+/// Streaming linear-interpolation resampler for mono f32 audio.
+///
+/// Keeps the last input sample and the fractional read position between
+/// calls, so a stream can be fed packet by packet without discontinuities.
+pub struct StreamResampler {
+    /// Input samples consumed per output sample (`from_rate / to_rate`).
+    step: f64,
+    /// Read position relative to `prev` (index 0) in the next input chunk.
+    pos: f64,
+    /// Last sample of the previous chunk; `None` until the first chunk.
+    prev: Option<f32>,
+}
+
+impl StreamResampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            step: from_rate as f64 / to_rate.max(1) as f64,
+            pos: 0.0,
+            prev: None,
+        }
+    }
+
+    /// Resample `input` and append the produced samples to `out`.
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        let Some(&last) = input.last() else {
+            return;
+        };
+        if self.step == 1.0 {
+            out.extend_from_slice(input);
+            return;
+        }
+
+        // Conceptual sequence: [prev, input[0], input[1], ...]. On the very
+        // first chunk there is no previous sample, so start at input[0].
+        let prev = match self.prev {
+            Some(p) => p,
+            None => {
+                self.pos = 1.0;
+                input[0]
+            }
+        };
+
+        let n = input.len() as f64;
+        let mut pos = self.pos;
+        while pos < n {
+            let i = pos as usize;
+            let frac = (pos - i as f64) as f32;
+            let a = if i == 0 { prev } else { input[i - 1] };
+            let b = input[i];
+            out.push(a + (b - a) * frac);
+            pos += self.step;
+        }
+
+        self.pos = pos - n;
+        self.prev = Some(last);
+    }
+}
+
+/// Resample a whole mono buffer in one go (offline processing).
+pub fn resample(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    let expected = (input.len() as u64 * to_rate as u64 / from_rate.max(1) as u64) as usize;
+    let mut out = Vec::with_capacity(expected + 1);
+    StreamResampler::new(from_rate, to_rate).process(input, &mut out);
+    out
+}
+
+/// Sample rate speech recognition runs at.
+pub const ASR_SAMPLE_RATE: u32 = 16_000;
+
+/// Length of the anti-aliasing filter applied before downsampling.
+const ANTI_ALIAS_TAPS: usize = 63;
+
+/// Downmix interleaved audio to mono and resample it to 16 kHz for
+/// transcription. Higher rates are low-passed first so content above 8 kHz
+/// doesn't fold back into the speech band.
+pub fn resample_for_asr(samples: &[f32], from_rate: u32, channels: u16) -> Vec<f32> {
+    let ch = channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks_exact(ch)
+        .map(|frame| frame.iter().sum::<f32>() / ch as f32)
+        .collect();
+
+    if from_rate == ASR_SAMPLE_RATE {
+        return mono;
+    }
+    let mono = if from_rate > ASR_SAMPLE_RATE {
+        // Cut slightly below the new Nyquist to leave room for the transition band
+        low_pass(&mono, 0.45 * ASR_SAMPLE_RATE as f32 / from_rate as f32)
+    } else {
+        mono
+    };
+    resample(&mono, from_rate, ASR_SAMPLE_RATE)
+}
+
+/// Windowed-sinc (Blackman) FIR low-pass. `cutoff` is in cycles per sample.
+fn low_pass(input: &[f32], cutoff: f32) -> Vec<f32> {
+    let half = ANTI_ALIAS_TAPS / 2;
+    let mut kernel: Vec<f32> = (0..ANTI_ALIAS_TAPS)
+        .map(|n| {
+            let x = n as f32 - half as f32;
+            let sinc = if x == 0.0 {
+                2.0 * cutoff
+            } else {
+                (2.0 * std::f32::consts::PI * cutoff * x).sin() / (std::f32::consts::PI * x)
+            };
+            let phase = 2.0 * std::f32::consts::PI * n as f32 / (ANTI_ALIAS_TAPS - 1) as f32;
+            sinc * (0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos())
+        })
+        .collect();
+    // Unity gain at DC
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|h| *h /= sum);
+
+    (0..input.len())
+        .map(|i| {
+            // Taps that fall outside the input see silence
+            let first = half.saturating_sub(i);
+            let last = (input.len() + half - i).min(ANTI_ALIAS_TAPS);
+            (first..last)
+                .map(|k| input[i + k - half] * kernel[k])
+                .sum()
+        })
+        .collect()
+}
+
+/// Converts interleaved f32 audio to another channel count and sample rate.
+///
+/// Used when a capture switches to a device whose mix format differs from
+/// the file already being written. Buffers are reused between calls.
+#[cfg(windows)]
+pub struct FormatConverter {
+    from_channels: usize,
+    to_channels: usize,
+    /// One resampler per output channel, all advancing in lockstep.
+    resamplers: Vec<StreamResampler>,
+    channel_in: Vec<f32>,
+    channel_out: Vec<Vec<f32>>,
+    out: Vec<f32>,
+}
+
+#[cfg(windows)]
+impl FormatConverter {
+    pub fn new(from_channels: u16, from_rate: u32, to_channels: u16, to_rate: u32) -> Self {
+        let to_channels = to_channels.max(1) as usize;
+        Self {
+            from_channels: from_channels.max(1) as usize,
+            to_channels,
+            resamplers: (0..to_channels)
+                .map(|_| StreamResampler::new(from_rate, to_rate))
+                .collect(),
+            channel_in: Vec::new(),
+            channel_out: vec![Vec::new(); to_channels],
+            out: Vec::new(),
+        }
+    }
+
+    /// Convert `input` and return the converted interleaved samples.
+    pub fn process(&mut self, input: &[f32]) -> &[f32] {
+        for (c, resampler) in self.resamplers.iter_mut().enumerate() {
+            self.channel_in.clear();
+            self.channel_in.extend(
+                input
+                    .chunks_exact(self.from_channels)
+                    .map(|frame| source_sample(frame, c, self.to_channels)),
+            );
+            self.channel_out[c].clear();
+            resampler.process(&self.channel_in, &mut self.channel_out[c]);
+        }
+
+        let frames = self.channel_out.iter().map(Vec::len).min().unwrap_or(0);
+        self.out.clear();
+        for f in 0..frames {
+            self.out.extend(self.channel_out.iter().map(|channel| channel[f]));
+        }
+        &self.out
+    }
+}
+
+/// Pick the input sample feeding output `channel`: downmix to mono when the
+/// output is mono, otherwise map channels by index (repeating the last one).
+#[cfg(windows)]
+#[inline]
+fn source_sample(frame: &[f32], channel: usize, to_channels: usize) -> f32 {
+    if to_channels == 1 && frame.len() > 1 {
+        frame.iter().sum::<f32>() / frame.len() as f32
+    } else {
+        frame[channel.min(frame.len() - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_44100_48000_preserves_length() {
+        let input: Vec<f32> = (0..44_100 * 2)
+            .map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44_100.0).sin())
+            .collect();
+
+        let up = resample(&input, 44_100, 48_000);
+        assert!(up.len().abs_diff(96_000) <= 2, "upsampled to {} samples", up.len());
+
+        let down = resample(&up, 48_000, 44_100);
+        assert!(down.len().abs_diff(input.len()) <= 2, "round trip gave {} samples", down.len());
+
+        // Linear interpolation keeps a 440 Hz tone close to the original
+        let max_err = input
+            .iter()
+            .zip(&down)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0f32, f32::max);
+        assert!(max_err < 0.05, "max error {max_err}");
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tauri::AppHandle;
+
+use crate::error::AppError;
+
+use super::capture::SystemAudioHandle;
+use super::enhance::write_wav_f32;
+use super::sink::AudioSink;
+use super::wav_format::{WavInfo, HEADER_SIZE};
+use super::{AudioFormat, CaptureConfig, CaptureInfo, RealtimeDenoiser};
+
+/// Longest window a ring capture may keep (~110 MB of 48 kHz stereo).
+pub const MAX_RING_SECONDS: u32 = 300;
+
+/// The most recent `capacity_ms` of interleaved f32 audio.
+///
+/// Only whole frames go in and only whole frames are dropped, so a snapshot
+/// always starts on a frame boundary with every channel in place.
+pub struct FrameRing {
+    capacity_ms: u32,
+    channels: usize,
+    sample_rate: u32,
+    /// Capacity in samples (frames × channels).
+    capacity: usize,
+    samples: VecDeque<f32>,
+}
+
+impl FrameRing {
+    /// An empty ring; `set_format` sizes it once the device format is known.
+    pub fn new(capacity_ms: u32) -> Self {
+        Self {
+            capacity_ms,
+            channels: 0,
+            sample_rate: 0,
+            capacity: 0,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Size the ring for `channels` at `sample_rate`, discarding its contents.
+    pub fn set_format(&mut self, channels: u16, sample_rate: u32) {
+        let frames = (sample_rate as u64 * self.capacity_ms as u64 / 1000) as usize;
+        self.channels = channels as usize;
+        self.sample_rate = sample_rate;
+        self.capacity = frames * self.channels;
+        self.samples = VecDeque::with_capacity(self.capacity);
+    }
+
+    /// Append interleaved samples, dropping the oldest frames past capacity.
+    /// A trailing partial frame is ignored.
+    pub fn push(&mut self, samples: &[f32]) {
+        if self.capacity == 0 {
+            return;
+        }
+        let whole = samples.len() - samples.len() % self.channels;
+        // Only the newest `capacity` samples of a long write can survive
+        let samples = &samples[whole.saturating_sub(self.capacity)..whole];
+
+        let overflow = (self.samples.len() + samples.len()).saturating_sub(self.capacity);
+        self.samples.drain(..overflow);
+        self.samples.extend(samples);
+    }
+
+    /// Append `frame_count` frames of silence.
+    pub fn push_silence(&mut self, frame_count: usize) {
+        if self.capacity == 0 {
+            return;
+        }
+        let count = (frame_count * self.channels).min(self.capacity);
+        let overflow = (self.samples.len() + count).saturating_sub(self.capacity);
+        self.samples.drain(..overflow);
+        self.samples.resize(self.samples.len() + count, 0.0);
+    }
+
+    /// Samples currently held (a whole number of frames).
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Copy of the buffered audio, oldest first.
+    pub fn snapshot(&self) -> Vec<f32> {
+        let (front, back) = self.samples.as_slices();
+        [front, back].concat()
+    }
+
+    /// The buffered audio with the 32-bit float WAV format to save it in.
+    /// Fails while the ring is still empty.
+    pub fn clip(&self) -> Result<(Vec<f32>, WavInfo), AppError> {
+        if self.samples.is_empty() {
+            return Err(AppError::AudioCapture("No audio buffered yet".into()));
+        }
+        let samples = self.snapshot();
+        let info = WavInfo {
+            channels: self.channels as u16,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 32,
+            is_float: true,
+            data_offset: HEADER_SIZE,
+            data_size: (samples.len() * 4) as u32,
+        };
+        Ok((samples, info))
+    }
+}
+
+fn lock_ring(ring: &Mutex<FrameRing>) -> Result<MutexGuard<'_, FrameRing>, AppError> {
+    ring.lock().map_err(|e| AppError::LockPoisoned(e.to_string()))
+}
+
+/// Feeds a capture into a shared `FrameRing`, keeping only its tail.
+pub struct RingSink {
+    format: AudioFormat,
+    ring: Arc<Mutex<FrameRing>>,
+    denoiser: Option<RealtimeDenoiser>,
+}
+
+impl RingSink {
+    pub fn new(format: AudioFormat, ring: Arc<Mutex<FrameRing>>) -> Result<Self, AppError> {
+        lock_ring(&ring)?.set_format(format.channels, format.sample_rate);
+        Ok(Self { format, ring, denoiser: None })
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, FrameRing>, AppError> {
+        lock_ring(&self.ring)
+    }
+}
+
+impl AudioSink for RingSink {
+    /// The audio stays in the ring for `RingCapture::save_clip`.
+    type Output = ();
+
+    fn set_denoiser(&mut self, denoiser: RealtimeDenoiser) {
+        self.denoiser = Some(denoiser);
+    }
+
+    fn write_silence(&mut self, frame_count: usize) -> Result<(), AppError> {
+        self.lock()?.push_silence(frame_count);
+        Ok(())
+    }
+
+    fn write_samples(&mut self, samples: &[f32]) -> Result<(), AppError> {
+        // Not `self.lock()`: the denoiser's output borrows `self` mutably
+        let mut ring = lock_ring(&self.ring)?;
+        match self.denoiser.as_mut() {
+            Some(denoiser) => ring.push(denoiser.process_interleaved(samples)),
+            None => ring.push(samples),
+        }
+        Ok(())
+    }
+
+    fn channels(&self) -> usize {
+        self.format.channels as usize
+    }
+
+    fn file_bytes(&self) -> u64 {
+        self.lock().map_or(0, |ring| (ring.sample_count() * std::mem::size_of::<f32>()) as u64)
+    }
+
+    fn finalize(mut self) -> Result<(), AppError> {
+        if let Some(mut denoiser) = self.denoiser.take() {
+            let tail = denoiser.flush();
+            self.lock()?.push(&tail);
+        }
+        Ok(())
+    }
+}
+
+/// A running "record the last N seconds" capture. The ring can be saved
+/// at any time without interrupting the capture.
+pub struct RingCapture {
+    handle: SystemAudioHandle<()>,
+    ring: Arc<Mutex<FrameRing>>,
+}
+
+impl RingCapture {
+    /// Start capturing, keeping the most recent `seconds` of audio.
+    pub fn start(config: CaptureConfig, app: AppHandle, seconds: u32) -> Result<Self, AppError> {
+        if !(1..=MAX_RING_SECONDS).contains(&seconds) {
+            return Err(AppError::AudioCapture(format!(
+                "Ring length must be 1–{MAX_RING_SECONDS} s, got {seconds} s"
+            )));
+        }
+        let ring = Arc::new(Mutex::new(FrameRing::new(seconds * 1000)));
+        let handle = SystemAudioHandle::start_ring(config, app, Arc::clone(&ring))?;
+        Ok(Self { handle, ring })
+    }
+
+    pub fn info(&self) -> &CaptureInfo {
+        self.handle.info()
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, FrameRing>, AppError> {
+        lock_ring(&self.ring)
+    }
+
+    /// Write what the ring holds right now to `path` (32-bit float WAV) and
+    /// return its duration in milliseconds.
+    pub fn save_clip(&self, path: &str) -> Result<u64, AppError> {
+        // Copy under the lock, write after releasing it, so the capture
+        // thread is only held up for the copy
+        let (samples, info) = self.lock()?.clip()?;
+        write_wav_f32(path, &samples, &info)?;
+        Ok(info.duration_ms())
+    }
+
+    pub fn stop(mut self) -> Result<(), AppError> {
+        self.handle.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_util::TempWav;
+    use super::super::wav_reader::read_wav_f32;
+
+    #[test]
+    fn push_keeps_only_the_newest_whole_frames() {
+        // 10 ms at 1 kHz stereo = 10 frames, 20 samples
+        let mut ring = FrameRing::new(10);
+        ring.set_format(2, 1_000);
+
+        // Frame n is [n, -n], pushed 3 frames at a time
+        let samples: Vec<f32> = (0..25).flat_map(|n| [n as f32, -(n as f32)]).collect();
+        for chunk in samples.chunks(6) {
+            ring.push(chunk);
+        }
+        // A stray half frame is ignored rather than shifting the channels
+        ring.push(&[99.0]);
+
+        let kept = ring.snapshot();
+        assert_eq!(kept.len(), 20);
+        for (i, frame) in kept.chunks_exact(2).enumerate() {
+            assert_eq!(frame[0], -frame[1], "frame {i} torn: {frame:?}");
+        }
+        assert_eq!(kept[18], 24.0);
+    }
+
+    #[test]
+    fn push_silence_past_capacity_keeps_the_newest_frames() {
+        let mut ring = FrameRing::new(10);
+        ring.set_format(2, 1_000);
+
+        ring.push(&[1.0; 16]);
+        ring.push_silence(4);
+        // 8 frames pushed, then 4 more: the 2 oldest fall off
+        let kept = ring.snapshot();
+        assert_eq!(kept.len(), 20);
+        assert_eq!(&kept[..12], &[1.0; 12]);
+        assert_eq!(&kept[12..], &[0.0; 8]);
+
+        // A gap longer than the whole ring leaves only silence
+        ring.push_silence(1_000);
+        assert_eq!(ring.snapshot(), vec![0.0; 20]);
+    }
+
+    #[test]
+    fn set_format_discards_buffered_audio() {
+        let mut ring = FrameRing::new(10);
+        ring.set_format(2, 1_000);
+        ring.push(&[0.5; 8]);
+
+        ring.set_format(1, 2_000);
+        assert_eq!(ring.sample_count(), 0);
+
+        // Resized for the new format: 10 ms at 2 kHz mono = 20 samples
+        ring.push(&[0.25; 30]);
+        assert_eq!(ring.snapshot(), vec![0.25; 20]);
+    }
+
+    #[test]
+    fn clip_saves_what_the_ring_holds() {
+        let mut ring = FrameRing::new(1_000);
+        ring.set_format(2, 8_000);
+        assert!(ring.clip().is_err(), "an empty ring has nothing to save");
+
+        // 250 ms of stereo
+        let samples: Vec<f32> = (0..4_000).map(|i| (i as f32 * 0.01).sin() * 0.5).collect();
+        ring.push(&samples);
+
+        let (clip, info) = ring.clip().unwrap();
+        let output = TempWav::new();
+        write_wav_f32(output.path(), &clip, &info).unwrap();
+
+        assert_eq!(info.duration_ms(), 250);
+        let (read, read_info) = read_wav_f32(output.path()).unwrap();
+        assert_eq!((read_info.channels, read_info.sample_rate), (2, 8_000));
+        assert_eq!(read_info.duration_ms(), 250);
+        assert_eq!(read, samples);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::error::AppError;
+
+use super::RealtimeDenoiser;
+#[cfg(windows)]
+use super::AudioFormat;
+
+/// Destination for captured audio.
+///
+/// The capture loop only talks to this trait, so the WASAPI side stays the
+/// same whether samples end up in a WAV file, a FLAC file, or memory.
+// Off Windows only `transcode` and `concat_wavs` use sinks, and they never
+// denoise, pad with silence or watch the file size.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub trait AudioSink {
+    /// What `finalize` hands back (nothing for file sinks).
+    type Output;
+
+    /// Denoise everything written from now on. The denoiser must match the
+    /// sink's channel count and run at 48 kHz.
+    fn set_denoiser(&mut self, denoiser: RealtimeDenoiser);
+
+    /// Write silence for `frame_count` frames.
+    fn write_silence(&mut self, frame_count: usize) -> Result<(), AppError>;
+
+    /// Write interleaved f32 samples that are already in the sink's format
+    /// (see [`append_raw_samples`] for turning WASAPI buffers into these).
+    fn write_samples(&mut self, samples: &[f32]) -> Result<(), AppError>;
+
+    fn channels(&self) -> usize;
+
+    /// Bytes stored so far, including any header.
+    fn file_bytes(&self) -> u64;
+
+    /// Write anything still buffered, the denoiser's last partial frame
+    /// included, and close the sink.
+    fn finalize(self) -> Result<Self::Output, AppError>;
+}
+
+/// Append `frame_count` frames of raw WASAPI audio in `format` to `out` as
+/// interleaved f32. Only 16-bit PCM and 32-bit float are supported.
+///
+/// # Safety
+/// `ptr` must point to valid audio data of at least `frame_count` frames.
+#[cfg(windows)]
+pub unsafe fn append_raw_samples(
+    ptr: *const u8,
+    frame_count: usize,
+    format: &AudioFormat,
+    out: &mut Vec<f32>,
+) -> Result<(), AppError> {
+    let sample_count = frame_count * format.channels as usize;
+
+    if !format.is_float && format.bits_per_sample == 16 {
+        // SAFETY: caller guarantees ptr is valid for sample_count i16 samples
+        let src = unsafe { std::slice::from_raw_parts(ptr as *const i16, sample_count) };
+        out.extend(src.iter().map(|&s| s as f32 / 32768.0));
+        Ok(())
+    } else if format.is_float && format.bits_per_sample == 32 {
+        // SAFETY: caller guarantees ptr is valid for sample_count f32 samples
+        let src = unsafe { std::slice::from_raw_parts(ptr as *const f32, sample_count) };
+        out.extend_from_slice(src);
+        Ok(())
+    } else {
+        Err(AppError::UnsupportedAudioFormat {
+            bits: format.bits_per_sample,
+            is_float: format.is_float,
+        })
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+/// Number of bars in an `audio-spectrum` event.
+pub const SPECTRUM_BANDS: usize = 32;
+
+/// ~21 ms at 48 kHz: enough resolution for the lowest bands without
+/// smearing transients across a whole emit interval.
+const FFT_SIZE: usize = 1024;
+/// Lower edge of the first band; below this is mostly rumble.
+const MIN_FREQ: f32 = 40.0;
+/// Band magnitudes are mapped from this level (0.0) up to 0 dBFS (1.0).
+const FLOOR_DB: f32 = -80.0;
+
+/// Log-spaced magnitude bands over the most recent `FFT_SIZE` frames.
+///
+/// `push*` only copies a mono downmix into a ring buffer, so the capture
+/// loop can feed every packet cheaply; the FFT runs in `bands()`, once per
+/// emit. All buffers are allocated up front.
+pub struct SpectrumAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    /// Ring of the latest mono samples; `pos` is the oldest.
+    history: Vec<f32>,
+    pos: usize,
+    /// Hann window, pre-scaled so a full-scale sine reads 0 dBFS.
+    window: Vec<f32>,
+    buffer: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    /// FFT bin range `[start, end)` of each band.
+    band_bins: [(usize, usize); SPECTRUM_BANDS],
+    bands: [f32; SPECTRUM_BANDS],
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(sample_rate: u32) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(FFT_SIZE);
+        let scratch = vec![Complex::default(); fft.get_inplace_scratch_len()];
+
+        // Hann has a coherent gain of 0.5; a real sine splits across ±f
+        let scale = 4.0 / FFT_SIZE as f32;
+        let window = (0..FFT_SIZE)
+            .map(|i| {
+                let phase = 2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32;
+                scale * 0.5 * (1.0 - phase.cos())
+            })
+            .collect();
+
+        let nyquist = sample_rate as f32 / 2.0;
+        let bin_hz = sample_rate as f32 / FFT_SIZE as f32;
+        let last_bin = FFT_SIZE / 2;
+        let edge = |i: usize| MIN_FREQ * (nyquist / MIN_FREQ).powf(i as f32 / SPECTRUM_BANDS as f32);
+        let band_bins = std::array::from_fn(|i| {
+            let start = ((edge(i) / bin_hz).round() as usize).min(last_bin - 1);
+            let end = ((edge(i + 1) / bin_hz).round() as usize).clamp(start + 1, last_bin);
+            (start, end)
+        });
+
+        Self {
+            fft,
+            history: vec![0.0; FFT_SIZE],
+            pos: 0,
+            window,
+            buffer: vec![Complex::default(); FFT_SIZE],
+            scratch,
+            band_bins,
+            bands: [0.0; SPECTRUM_BANDS],
+        }
+    }
+
+    /// Queue interleaved f32 samples.
+    pub fn push(&mut self, samples: &[f32], channels: usize) {
+        let ch = channels.max(1);
+        for frame in samples.chunks_exact(ch) {
+            self.push_mono(frame.iter().sum::<f32>() / ch as f32);
+        }
+    }
+
+    /// Queue `frame_count` frames of silence.
+    pub fn push_silence(&mut self, frame_count: usize) {
+        for _ in 0..frame_count.min(FFT_SIZE) {
+            self.push_mono(0.0);
+        }
+    }
+
+    #[inline]
+    fn push_mono(&mut self, sample: f32) {
+        self.history[self.pos] = sample;
+        self.pos = (self.pos + 1) % FFT_SIZE;
+    }
+
+    /// Band levels of the latest window, 0.0 (−80 dBFS or quieter) to 1.0
+    /// (0 dBFS), lowest frequency first.
+    pub fn bands(&mut self) -> &[f32; SPECTRUM_BANDS] {
+        let (newer, older) = self.history.split_at(self.pos);
+        for ((out, &s), &w) in self.buffer.iter_mut().zip(older.iter().chain(newer)).zip(&self.window) {
+            *out = Complex::new(s * w, 0.0);
+        }
+        self.fft.process_with_scratch(&mut self.buffer, &mut self.scratch);
+
+        for (band, &(start, end)) in self.bands.iter_mut().zip(&self.band_bins) {
+            let peak = self.buffer[start..end]
+                .iter()
+                .map(|c| c.norm())
+                .fold(0.0f32, f32::max);
+            let db = 20.0 * peak.max(1e-10).log10();
+            *band = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+        }
+        &self.bands
+    }
+}
@@ -0,0 +1,141 @@
+use std::ops::Range;
+use std::path::Path;
+
+use crate::error::AppError;
+use super::enhance::write_wav_f32;
+use super::wav_reader::read_wav_f32;
+
+/// Loudness is measured over windows of this length.
+const WINDOW_MS: u32 = 10;
+
+/// How `split_on_silence` finds the gaps.
+#[derive(Debug, Clone, Copy)]
+pub struct SplitOptions {
+    /// Shortest quiet stretch that ends a segment.
+    pub min_silence_ms: u32,
+    /// Windows whose RMS is below this level (dBFS) count as silent.
+    pub threshold_db: f32,
+    /// Audio kept on each side of a segment, so words aren't clipped.
+    pub padding_ms: u32,
+}
+
+impl Default for SplitOptions {
+    fn default() -> Self {
+        Self {
+            min_silence_ms: 700,
+            threshold_db: -45.0,
+            padding_ms: 150,
+        }
+    }
+}
+
+/// Split a WAV at silent gaps, writing each non-silent stretch to
+/// `output_dir` as `segment_000.wav`, `segment_001.wav`, ... (32-bit float,
+/// input channels and rate). Returns the written paths in order; a silent
+/// input produces none.
+pub fn split_on_silence(
+    input_path: &str,
+    output_dir: &str,
+    options: &SplitOptions,
+) -> Result<Vec<String>, AppError> {
+    let (samples, info) = read_wav_f32(input_path)?;
+    let channels = info.channels.max(1) as usize;
+    let segments = find_segments(&samples, channels, info.sample_rate, options);
+
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| AppError::AudioEnhance(format!("Create output folder: {e}")))?;
+
+    segments
+        .into_iter()
+        .enumerate()
+        .map(|(i, frames)| {
+            let path = Path::new(output_dir)
+                .join(format!("segment_{i:03}.wav"))
+                .to_string_lossy()
+                .to_string();
+            let range = frames.start * channels..frames.end * channels;
+            write_wav_f32(&path, &samples[range], &info)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Frame ranges of the non-silent stretches in interleaved `samples`,
+/// widened by the padding without overlapping each other.
+fn find_segments(
+    samples: &[f32],
+    channels: usize,
+    sample_rate: u32,
+    options: &SplitOptions,
+) -> Vec<Range<usize>> {
+    let total_frames = samples.len() / channels;
+    let window = ((sample_rate * WINDOW_MS / 1000) as usize).max(1);
+    let min_silent_windows = (options.min_silence_ms / WINDOW_MS).max(1) as usize;
+    let padding = (sample_rate as u64 * options.padding_ms as u64 / 1000) as usize;
+    let threshold = 10f32.powf(options.threshold_db / 20.0);
+
+    // Non-silent stretches in frames, before padding
+    let mut voiced: Vec<Range<usize>> = Vec::new();
+    let mut silent_run = 0;
+    for (w, chunk) in samples.chunks(window * channels).enumerate() {
+        let rms = (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt();
+        let start = w * window;
+        let end = (start + window).min(total_frames);
+
+        if rms < threshold {
+            silent_run += 1;
+            continue;
+        }
+        match voiced.last_mut() {
+            // A gap too short to split on stays inside the segment
+            Some(last) if silent_run < min_silent_windows => last.end = end,
+            _ => voiced.push(start..end),
+        }
+        silent_run = 0;
+    }
+
+    let mut segments: Vec<Range<usize>> = Vec::with_capacity(voiced.len());
+    for range in voiced {
+        let start = range.start.saturating_sub(padding);
+        let start = segments.last().map_or(start, |prev| start.max(prev.end));
+        segments.push(start..(range.end + padding).min(total_frames));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1 kHz mono: `tone` for each true, silence for each false, 100 ms each.
+    fn pattern(blocks: &[bool]) -> Vec<f32> {
+        blocks
+            .iter()
+            .flat_map(|&tone| {
+                (0..1_000).map(move |i| {
+                    if tone {
+                        0.5 * (2.0 * std::f32::consts::PI * i as f32 / 10.0).sin()
+                    } else {
+                        0.0
+                    }
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_at_long_gaps_and_pads_segments() {
+        let options = SplitOptions { min_silence_ms: 300, threshold_db: -40.0, padding_ms: 50 };
+        // tone, short gap (kept), tone, long gap (split), tone
+        let samples = pattern(&[true, false, true, false, false, false, false, true]);
+
+        let segments = find_segments(&samples, 1, 10_000, &options);
+        assert_eq!(segments, [0..3_500, 6_500..8_000]);
+    }
+
+    #[test]
+    fn silent_input_has_no_segments() {
+        let samples = pattern(&[false; 10]);
+        assert!(find_segments(&samples, 1, 10_000, &SplitOptions::default()).is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+// Synthetic WAV files for tests, so nothing depends on real recordings.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::wav_format::write_header;
+
+/// A tone plus white noise, encoded at any depth `read_wav_f32` supports.
+/// Build with struct update syntax over `Default`:
+///
+/// `WavFixture { channels: 1, bits_per_sample: 24, ..WavFixture::default() }`
+#[derive(Debug, Clone, Copy)]
+pub struct WavFixture {
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// 8, 16 or 24 for PCM; 32 with `is_float`.
+    pub bits_per_sample: u16,
+    pub is_float: bool,
+    pub frames: usize,
+    /// Sine frequency; the same on every channel.
+    pub tone_hz: f32,
+    /// Peak of the sine (0 for noise only).
+    pub tone_amplitude: f32,
+    /// Frame the sine starts at; the frames before it are noise alone.
+    pub tone_onset: usize,
+    /// Peak of the uniform white noise (0 for a clean tone).
+    pub noise_amplitude: f32,
+}
+
+impl Default for WavFixture {
+    /// One second of a 440 Hz tone in 16-bit 48 kHz stereo, with light noise.
+    fn default() -> Self {
+        Self {
+            channels: 2,
+            sample_rate: 48_000,
+            bits_per_sample: 16,
+            is_float: false,
+            frames: 48_000,
+            tone_hz: 440.0,
+            tone_amplitude: 0.5,
+            tone_onset: 0,
+            noise_amplitude: 0.05,
+        }
+    }
+}
+
+impl WavFixture {
+    /// Interleaved samples before encoding. The noise is seeded, so every
+    /// call returns the same signal.
+    pub fn samples(&self) -> Vec<f32> {
+        let mut seed = 0x2545_f491_u32;
+        let mut samples = Vec::with_capacity(self.frames * self.channels as usize);
+        for i in 0..self.frames {
+            let phase = 2.0 * std::f32::consts::PI * self.tone_hz * i as f32 / self.sample_rate as f32;
+            let tone = if i >= self.tone_onset { self.tone_amplitude * phase.sin() } else { 0.0 };
+            for _ in 0..self.channels {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                let noise = self.noise_amplitude * (2.0 * seed as f32 / u32::MAX as f32 - 1.0);
+                samples.push((tone + noise).clamp(-1.0, 1.0));
+            }
+        }
+        samples
+    }
+
+    /// The whole file: canonical 44-byte header, then the encoded samples.
+    pub fn bytes(&self) -> Vec<u8> {
+        let data: Vec<u8> = self
+            .samples()
+            .iter()
+            .flat_map(|&s| encode(s, self.bits_per_sample, self.is_float))
+            .collect();
+
+        let mut out = Vec::with_capacity(44 + data.len());
+        write_header(
+            &mut out,
+            self.channels,
+            self.sample_rate,
+            self.bits_per_sample,
+            self.is_float,
+            data.len() as u32,
+        )
+        .expect("writing to a Vec can't fail");
+        out.extend_from_slice(&data);
+        out
+    }
+
+    /// Write the file to a unique temp path, deleted when the result drops.
+    pub fn write(&self) -> TempWav {
+        let file = TempWav::new();
+        std::fs::write(file.path(), self.bytes()).expect("write WAV fixture");
+        file
+    }
+}
+
+/// Little-endian bytes of one sample at the given depth.
+fn encode(sample: f32, bits: u16, is_float: bool) -> Vec<u8> {
+    match (is_float, bits) {
+        (true, 32) => sample.to_le_bytes().to_vec(),
+        (false, 24) => {
+            let value = (sample * 8_388_607.0).round() as i32;
+            value.to_le_bytes()[..3].to_vec()
+        }
+        (false, 16) => ((sample * 32_767.0).round() as i16).to_le_bytes().to_vec(),
+        (false, 8) => vec![(sample * 127.0 + 128.0).round() as u8],
+        _ => panic!("no fixture encoding for {bits}-bit (float: {is_float})"),
+    }
+}
+
+/// A unique temp WAV path, removed on drop if anything was written there.
+pub struct TempWav {
+    path: String,
+}
+
+impl TempWav {
+    /// A fresh path for a test to write its output to.
+    pub fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir()
+            .join(format!("wav_fixture_{}_{n}.wav", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        Self { path }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Drop for TempWav {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
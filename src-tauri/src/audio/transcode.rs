@@ -0,0 +1,96 @@
+use crate::error::AppError;
+
+use super::flac::FlacWriter;
+#[cfg(feature = "mp3")]
+use super::mp3::Mp3Writer;
+use super::sink::AudioSink;
+use super::wav::AudioWavWriter;
+use super::wav_reader::read_wav_f32;
+use super::{AudioFormat, BitDepth, RecordingFormat};
+
+/// Frames handed to the encoder per write. The input is still read whole;
+/// this only keeps each write, and the encoders' scratch buffers, small.
+const CHUNK_FRAMES: usize = 4096;
+
+/// Re-encode the WAV at `input_path` as `format` at `output_path`, keeping
+/// its channel count and sample rate. 16-bit input stays 16-bit in WAV and
+/// FLAC; anything else becomes 32-bit float WAV or 24-bit FLAC.
+/// `bitrate_kbps` only applies to MP3. Returns the output size in bytes.
+pub fn transcode(
+    input_path: &str,
+    output_path: &str,
+    format: RecordingFormat,
+    bitrate_kbps: Option<u32>,
+) -> Result<u64, AppError> {
+    let (samples, info) = read_wav_f32(input_path)?;
+    let audio_format = AudioFormat {
+        sample_rate: info.sample_rate,
+        channels: info.channels,
+        bits_per_sample: 32,
+        is_float: true,
+    };
+    let bit_depth = if !info.is_float && info.bits_per_sample <= 16 {
+        BitDepth::Pcm16
+    } else {
+        BitDepth::Float32
+    };
+
+    match format {
+        RecordingFormat::Wav => encode(
+            AudioWavWriter::create(output_path, audio_format, bit_depth, None)?,
+            &samples,
+        )?,
+        RecordingFormat::Flac => encode(
+            FlacWriter::create(output_path, audio_format, bit_depth)?,
+            &samples,
+        )?,
+        #[cfg(feature = "mp3")]
+        RecordingFormat::Mp3 => encode(
+            Mp3Writer::create(output_path, audio_format, bitrate_kbps)?,
+            &samples,
+        )?,
+        #[cfg(not(feature = "mp3"))]
+        RecordingFormat::Mp3 => {
+            let _ = bitrate_kbps;
+            return Err(super::mp3_unavailable());
+        }
+    }
+
+    Ok(std::fs::metadata(output_path)?.len())
+}
+
+fn encode<S: AudioSink>(mut sink: S, samples: &[f32]) -> Result<(), AppError> {
+    let chunk = CHUNK_FRAMES * sink.channels();
+    for block in samples.chunks(chunk) {
+        sink.write_samples(block)?;
+    }
+    sink.finalize().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_util::{TempWav, WavFixture};
+
+    #[test]
+    fn flac_transcode_keeps_the_format_and_shrinks_the_file() {
+        let fixture = WavFixture {
+            sample_rate: 44_100,
+            frames: 44_100,
+            tone_hz: 220.0,
+            tone_amplitude: 0.3,
+            noise_amplitude: 0.0,
+            ..WavFixture::default()
+        };
+        let input = fixture.write();
+        let output = TempWav::new();
+
+        let size = transcode(input.path(), output.path(), RecordingFormat::Flac, None).unwrap();
+        let bytes = std::fs::read(output.path()).unwrap();
+        let wav_size = std::fs::metadata(input.path()).unwrap().len();
+
+        assert_eq!(size, bytes.len() as u64);
+        assert_eq!(&bytes[..4], b"fLaC");
+        assert!(size < wav_size, "{size} bytes of FLAC vs {wav_size} of WAV");
+    }
+}
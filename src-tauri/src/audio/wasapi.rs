@@ -1,68 +1,180 @@
 use crate::error::AppError;
-use windows::core::GUID;
-use windows::Win32::Foundation::HANDLE;
+use std::mem::ManuallyDrop;
+use windows::core::{implement, w, Interface, IUnknown, Ref, GUID, HRESULT, PWSTR};
+use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+use windows::Win32::Foundation::{
+    CloseHandle, ERROR_INVALID_PARAMETER, HANDLE, RPC_E_CHANGED_MODE, S_FALSE, S_OK, WAIT_EVENT,
+    WAIT_FAILED, WAIT_OBJECT_0, WAIT_TIMEOUT,
+};
 use windows::Win32::Media::Audio::{
-    eConsole, eRender, IAudioCaptureClient, IAudioClient, IMMDeviceEnumerator,
-    MMDeviceEnumerator, AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_LOOPBACK,
-    AUDCLNT_STREAMFLAGS_EVENTCALLBACK, WAVEFORMATEX, WAVEFORMATEXTENSIBLE,
+    eCapture, eConsole, eRender, ActivateAudioInterfaceAsync, EDataFlow, IActivateAudioInterfaceAsyncOperation,
+    IActivateAudioInterfaceCompletionHandler, IActivateAudioInterfaceCompletionHandler_Impl,
+    IAudioCaptureClient, IAudioClient, IMMDevice, IMMDeviceEnumerator, MMDeviceEnumerator,
+    AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM, AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
+    AUDCLNT_STREAMFLAGS_LOOPBACK, AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY,
+    AUDIOCLIENT_ACTIVATION_PARAMS, AUDIOCLIENT_ACTIVATION_PARAMS_0,
+    AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK, AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS,
+    DEVICE_STATE_ACTIVE, PROCESS_LOOPBACK_MODE_EXCLUDE_TARGET_PROCESS_TREE,
+    PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE, VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+    WAVEFORMATEX, WAVEFORMATEXTENSIBLE,
+};
+use windows::Win32::System::Com::StructuredStorage::{
+    PropVariantClear, PropVariantToStringAlloc, PROPVARIANT, PROPVARIANT_0, PROPVARIANT_0_0,
+    PROPVARIANT_0_0_0,
 };
 use windows::Win32::System::Com::{
-    CoCreateInstance, CoInitializeEx, CoUninitialize, CoTaskMemFree,
-    CLSCTX_ALL, COINIT_APARTMENTTHREADED,
+    CoCreateInstance, CoInitializeEx, CoTaskMemAlloc, CoTaskMemFree, CoUninitialize, BLOB,
+    CLSCTX_ALL, COINIT_APARTMENTTHREADED, STGM_READ,
 };
-use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject};
+use windows::Win32::System::Threading::{
+    AvRevertMmThreadCharacteristics, AvSetMmThreadCharacteristicsW, CreateEventW, OpenProcess,
+    QueryFullProcessImageNameW, SetEvent, WaitForMultipleObjects, WaitForSingleObject,
+    PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::System::Variant::VT_BLOB;
+
+use super::AudioFormat;
 
 const REFTIMES_PER_SEC: i64 = 10_000_000;
 /// Timeout for WaitForSingleObject in milliseconds.
 /// 100 ms is generous — at 48 kHz the buffer fills every ~10 ms.
 const EVENT_WAIT_TIMEOUT_MS: u32 = 100;
+/// How long to wait for a process loopback activation to complete.
+const ACTIVATION_TIMEOUT_MS: u32 = 5_000;
 
 const KSDATAFORMAT_SUBTYPE_IEEE_FLOAT: GUID =
     GUID::from_u128(0x00000003_0000_0010_8000_00aa00389b71);
 
-/// Audio format information extracted from the WASAPI device.
-#[derive(Debug, Clone, Copy)]
-pub struct AudioFormat {
-    pub sample_rate: u32,
-    pub channels: u16,
-    pub bits_per_sample: u16,
-    pub is_float: bool,
+/// How a wait for the buffer-ready event ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferWait {
+    Ready,
+    /// Nothing signalled within `EVENT_WAIT_TIMEOUT_MS` — normal in the
+    /// polling fallback, otherwise usually nothing is playing.
+    TimedOut,
 }
 
+impl BufferWait {
+    /// Map a wait result; `WAIT_FAILED` (e.g. the handle was closed) is an
+    /// error so the capture loop stops instead of spinning.
+    fn from_wait(result: WAIT_EVENT) -> Result<Self, AppError> {
+        match result {
+            WAIT_TIMEOUT => Ok(Self::TimedOut),
+            WAIT_FAILED => Err(AppError::AudioCapture(format!(
+                "Waiting for audio buffer failed: {}",
+                windows::core::Error::from_win32()
+            ))),
+            _ => Ok(Self::Ready),
+        }
+    }
+}
 
 // ── COM RAII ────────────────────────────────────────────────────────
 
+/// What `CoInitializeEx` did on the current thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComInit {
+    /// COM was initialized here (`S_OK`).
+    Initialized,
+    /// COM was already initialized in the same mode (`S_FALSE`). The call
+    /// still counts, so it must be balanced like `Initialized`.
+    AlreadyInitialized,
+    /// COM was already initialized as multithreaded (`RPC_E_CHANGED_MODE`).
+    /// It is usable, but this call took no reference to release.
+    ChangedMode,
+    /// COM is not usable on this thread.
+    Failed(HRESULT),
+}
+
+impl ComInit {
+    fn from_hresult(hr: HRESULT) -> Self {
+        match hr {
+            S_OK => Self::Initialized,
+            S_FALSE => Self::AlreadyInitialized,
+            RPC_E_CHANGED_MODE => Self::ChangedMode,
+            hr => Self::Failed(hr),
+        }
+    }
+
+    /// Whether `CoUninitialize` is owed for this call.
+    fn needs_uninit(self) -> bool {
+        matches!(self, Self::Initialized | Self::AlreadyInitialized)
+    }
+}
+
+/// Initializes COM (single-threaded apartment) for the current thread and
+/// balances it with `CoUninitialize` on drop when the call took a reference.
 pub struct ComGuard {
-    initialized: bool,
+    state: ComInit,
 }
 
 impl ComGuard {
     pub fn init() -> Self {
-        let result = unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) };
-        Self { initialized: result.is_ok() }
+        let state = ComInit::from_hresult(unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) });
+        if let ComInit::Failed(hr) = state {
+            eprintln!("[wasapi] CoInitializeEx failed: {}", windows::core::Error::from(hr));
+        }
+        Self { state }
     }
 }
 
 impl Drop for ComGuard {
     fn drop(&mut self) {
-        if self.initialized {
+        if self.state.needs_uninit() {
             unsafe { CoUninitialize() };
         }
     }
 }
 
+// ── MMCSS RAII ──────────────────────────────────────────────────────
+
+/// Registers the current thread with the MMCSS "Pro Audio" task, which
+/// schedules it ahead of normal threads, until dropped.
+pub struct MmcssGuard {
+    handle: HANDLE,
+}
+
+impl MmcssGuard {
+    /// `None` where MMCSS is unavailable (e.g. its service is disabled); the
+    /// thread then keeps its normal priority.
+    pub fn pro_audio() -> Option<Self> {
+        let mut task_index = 0u32;
+        match unsafe { AvSetMmThreadCharacteristicsW(w!("Pro Audio"), &mut task_index) } {
+            Ok(handle) => Some(Self { handle }),
+            Err(e) => {
+                eprintln!("[capture] MMCSS unavailable, keeping normal priority: {e}");
+                None
+            }
+        }
+    }
+}
+
+impl Drop for MmcssGuard {
+    fn drop(&mut self) {
+        let _ = unsafe { AvRevertMmThreadCharacteristics(self.handle) };
+    }
+}
+
 // ── Loopback session ────────────────────────────────────────────────
 
 /// RAII loopback capture session.
 ///
 /// On drop: stops the audio client and frees the WASAPI format memory.
 /// The caller only needs to call `start()` and read packets — cleanup is automatic.
+///
+/// The same wrapper also captures a microphone (see `open_microphone`), in
+/// which case the stream is opened without the loopback flag.
 pub struct LoopbackSession {
     audio_client: IAudioClient,
     pub capture_client: IAudioCaptureClient,
     pub format: AudioFormat,
     format_ptr: *const WAVEFORMATEX,
-    /// Event handle signalled by WASAPI when a buffer is ready.
+    /// Endpoint ID of the render device being captured.
+    pub device_id: String,
+    /// Friendly name of the render device (empty if the property store is unavailable).
+    pub device_name: String,
+    /// Event handle signalled by WASAPI when a buffer is ready. In the
+    /// polling fallback it is never signalled and waits on it just time out.
     pub buffer_event: HANDLE,
     started: bool,
 }
@@ -82,13 +194,85 @@ impl LoopbackSession {
         // SAFETY: all COM/WASAPI calls require COM to be initialized on this thread.
         // The caller guarantees this via ComGuard.
         unsafe {
-            let enumerator: IMMDeviceEnumerator =
-                CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)
-                    .map_err(|e| AppError::AudioCapture(format!("Device enumerator: {e}")))?;
+            let enumerator = create_enumerator()?;
+            let device = default_render_device(&enumerator)?;
+            Self::open_device(&device, eRender)
+        }
+    }
+
+    /// Open a loopback session on the render endpoint whose ID is `device_id`.
+    ///
+    /// Falls back to the default render device if no active endpoint matches.
+    /// Check `device_id` on the returned session to see which one was used.
+    ///
+    /// # Safety
+    /// Must be called on a thread with COM initialized (use `ComGuard`).
+    pub unsafe fn open_with_device(device_id: &str) -> Result<Self, AppError> {
+        // SAFETY: the caller guarantees COM is initialized on this thread.
+        unsafe {
+            let enumerator = create_enumerator()?;
+            let device = match find_render_device(&enumerator, device_id)? {
+                Some(device) => device,
+                None => {
+                    eprintln!("[wasapi] Output device {device_id} not found, using default");
+                    default_render_device(&enumerator)?
+                }
+            };
+            Self::open_device(&device, eRender)
+        }
+    }
 
+    /// Open a capture session on the default microphone (capture endpoint).
+    ///
+    /// # Safety
+    /// Must be called on a thread with COM initialized (use `ComGuard`).
+    pub unsafe fn open_microphone() -> Result<Self, AppError> {
+        // SAFETY: the caller guarantees COM is initialized on this thread.
+        unsafe {
+            let enumerator = create_enumerator()?;
             let device = enumerator
-                .GetDefaultAudioEndpoint(eRender, eConsole)
-                .map_err(|e| AppError::AudioCapture(format!("No default audio device: {e}")))?;
+                .GetDefaultAudioEndpoint(eCapture, eConsole)
+                .map_err(|e| AppError::AudioCapture(format!("No default microphone: {e}")))?;
+            Self::open_device(&device, eCapture)
+        }
+    }
+
+    /// Open a loopback session on the audio of one process instead of a
+    /// whole device. With `include_tree` the processes it started are
+    /// captured too (a browser plays through its child processes).
+    ///
+    /// Needs the process loopback API of Windows 10 version 2004 or later;
+    /// older systems get an `AudioCapture` error. The stream has no mix
+    /// format of its own, so it is opened as 48 kHz stereo float and WASAPI
+    /// converts whatever the process plays.
+    ///
+    /// # Safety
+    /// Must be called on a thread with COM initialized (use `ComGuard`).
+    pub unsafe fn open_for_process(pid: u32, include_tree: bool) -> Result<Self, AppError> {
+        let device_name = process_name(pid)?;
+        // SAFETY: the caller guarantees COM is initialized on this thread.
+        unsafe {
+            let audio_client = activate_process_loopback(pid, include_tree)?;
+            let pwfx = process_loopback_format()?;
+            Self::from_client(
+                audio_client,
+                pwfx,
+                AUDCLNT_STREAMFLAGS_LOOPBACK
+                    | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM
+                    | AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY,
+                format!("process:{pid}"),
+                device_name,
+            )
+        }
+    }
+
+    /// Activate `device` and initialize a shared-mode capture stream on it.
+    /// Render endpoints (`eRender`) are opened in loopback mode.
+    unsafe fn open_device(device: &IMMDevice, flow: EDataFlow) -> Result<Self, AppError> {
+        // SAFETY: the caller guarantees COM is initialized on this thread.
+        unsafe {
+            let device_id = endpoint_id(device)?;
+            let device_name = friendly_name(device).unwrap_or_default();
 
             let audio_client: IAudioClient = device
                 .Activate(CLSCTX_ALL, None)
@@ -98,15 +282,74 @@ impl LoopbackSession {
                 .GetMixFormat()
                 .map_err(|e| AppError::AudioCapture(format!("GetMixFormat: {e}")))?;
 
+            let stream_flags = if flow == eRender {
+                AUDCLNT_STREAMFLAGS_LOOPBACK
+            } else {
+                0
+            };
+            Self::from_client(audio_client, pwfx, stream_flags, device_id, device_name)
+        }
+    }
+
+    /// Initialize a stream on `audio_client` in the CoTaskMemAlloc'd format
+    /// `pwfx`, which the session takes ownership of (freed on error too).
+    unsafe fn from_client(
+        audio_client: IAudioClient,
+        pwfx: *mut WAVEFORMATEX,
+        stream_flags: u32,
+        device_id: String,
+        device_name: String,
+    ) -> Result<Self, AppError> {
+        // SAFETY: the caller guarantees COM is initialized and `pwfx` is valid.
+        unsafe {
             let format = Self::parse_format(&*pwfx, pwfx);
 
-            let event = CreateEventW(None, false, false, None)
-                .map_err(|e| AppError::AudioCapture(format!("CreateEvent: {e}")))?;
+            let event = match CreateEventW(None, false, false, None) {
+                Ok(event) => event,
+                Err(e) => {
+                    CoTaskMemFree(Some(pwfx as *const _));
+                    return Err(AppError::AudioCapture(format!("CreateEvent: {e}")));
+                }
+            };
+
+            let capture_client = match Self::initialize(&audio_client, pwfx, event, stream_flags) {
+                Ok(client) => client,
+                Err(e) => {
+                    // Not owned by a session yet, so Drop won't free them
+                    let _ = CloseHandle(event);
+                    CoTaskMemFree(Some(pwfx as *const _));
+                    return Err(e);
+                }
+            };
+
+            Ok(Self {
+                audio_client,
+                capture_client,
+                format,
+                format_ptr: pwfx,
+                device_id,
+                device_name,
+                buffer_event: event,
+                started: false,
+            })
+        }
+    }
 
+    /// Initialize the shared-mode stream, event-driven when the driver
+    /// allows it, and get its capture client.
+    unsafe fn initialize(
+        audio_client: &IAudioClient,
+        pwfx: *const WAVEFORMATEX,
+        event: HANDLE,
+        stream_flags: u32,
+    ) -> Result<IAudioCaptureClient, AppError> {
+        // SAFETY: the caller guarantees COM is initialized and `pwfx` is a
+        // format the client accepts.
+        unsafe {
             // Try event-driven mode first (loopback + event callback)
             let init_result = audio_client.Initialize(
                 AUDCLNT_SHAREMODE_SHARED,
-                AUDCLNT_STREAMFLAGS_LOOPBACK | AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
+                stream_flags | AUDCLNT_STREAMFLAGS_EVENTCALLBACK,
                 REFTIMES_PER_SEC,
                 0,
                 pwfx,
@@ -119,7 +362,7 @@ impl LoopbackSession {
                 audio_client
                     .Initialize(
                         AUDCLNT_SHAREMODE_SHARED,
-                        AUDCLNT_STREAMFLAGS_LOOPBACK,
+                        stream_flags,
                         REFTIMES_PER_SEC,
                         0,
                         pwfx,
@@ -132,18 +375,9 @@ impl LoopbackSession {
                     .map_err(|e| AppError::AudioCapture(format!("SetEventHandle: {e}")))?;
             }
 
-            let capture_client: IAudioCaptureClient = audio_client
+            audio_client
                 .GetService()
-                .map_err(|e| AppError::AudioCapture(format!("GetService: {e}")))?;
-
-            Ok(Self {
-                audio_client,
-                capture_client,
-                format,
-                format_ptr: pwfx,
-                buffer_event: event,
-                started: false,
-            })
+                .map_err(|e| AppError::AudioCapture(format!("GetService: {e}")))
         }
     }
 
@@ -167,6 +401,24 @@ impl LoopbackSession {
         }
     }
 
+    /// Query the device's mix format now. It can change under a running
+    /// stream, e.g. when the user picks another default format for the device.
+    ///
+    /// # Safety
+    /// Must be called on a thread with COM initialized (use `ComGuard`).
+    pub unsafe fn current_mix_format(&self) -> Result<AudioFormat, AppError> {
+        // SAFETY: the caller guarantees COM is initialized on this thread.
+        unsafe {
+            let pwfx = self
+                .audio_client
+                .GetMixFormat()
+                .map_err(|e| AppError::AudioCapture(format!("GetMixFormat: {e}")))?;
+            let format = Self::parse_format(&*pwfx, pwfx);
+            CoTaskMemFree(Some(pwfx as *const _));
+            Ok(format)
+        }
+    }
+
     /// Start the audio stream.
     pub unsafe fn start(&mut self) -> Result<(), AppError> {
         // SAFETY: caller ensures COM is initialized and session is valid
@@ -182,10 +434,22 @@ impl LoopbackSession {
     /// Wait for the WASAPI buffer-ready event (or timeout).
     /// Returns immediately if data is already available.
     #[inline]
-    pub fn wait_for_buffer(&self) {
-        unsafe {
-            WaitForSingleObject(self.buffer_event, EVENT_WAIT_TIMEOUT_MS);
-        }
+    pub fn wait_for_buffer(&self) -> Result<BufferWait, AppError> {
+        let result = unsafe { WaitForSingleObject(self.buffer_event, EVENT_WAIT_TIMEOUT_MS) };
+        BufferWait::from_wait(result)
+    }
+
+    /// Wait until either this session or `other` has a buffer ready (or timeout).
+    #[inline]
+    pub fn wait_for_either(&self, other: &LoopbackSession) -> Result<BufferWait, AppError> {
+        let result = unsafe {
+            WaitForMultipleObjects(
+                &[self.buffer_event, other.buffer_event],
+                false,
+                EVENT_WAIT_TIMEOUT_MS,
+            )
+        };
+        BufferWait::from_wait(result)
     }
 }
 
@@ -196,14 +460,338 @@ impl Drop for LoopbackSession {
                 let _ = self.audio_client.Stop();
             }
             CoTaskMemFree(Some(self.format_ptr as *const _));
-            // CloseHandle is not strictly needed — Windows cleans up on thread exit —
-            // but we could add it here if we import it.
+            // Kernel handles are per process, not per thread: without this every
+            // start/stop cycle leaked one. The event exists in polling mode too.
+            if !self.buffer_event.is_invalid() {
+                let _ = CloseHandle(self.buffer_event);
+            }
         }
     }
 }
 
+// ── Process loopback ────────────────────────────────────────────────
+
+/// Signals `done` when `ActivateAudioInterfaceAsync` completes. The callback
+/// runs on a worker thread; `#[implement]` objects are agile, as required.
+///
+/// The handler owns `done`: an activation that outlives our wait still holds
+/// a reference, so the event is only closed once nothing can signal it.
+#[implement(IActivateAudioInterfaceCompletionHandler)]
+struct ActivationHandler {
+    done: HANDLE,
+}
+
+impl Drop for ActivationHandler {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.done);
+        }
+    }
+}
+
+impl IActivateAudioInterfaceCompletionHandler_Impl for ActivationHandler_Impl {
+    fn ActivateCompleted(
+        &self,
+        _operation: Ref<'_, IActivateAudioInterfaceAsyncOperation>,
+    ) -> windows::core::Result<()> {
+        unsafe { SetEvent(self.done) }
+    }
+}
+
+/// Activate an audio client on the process loopback virtual device and wait
+/// for it. Windows before 10 version 2004 fails here.
+unsafe fn activate_process_loopback(pid: u32, include_tree: bool) -> Result<IAudioClient, AppError> {
+    let unsupported = |e: windows::core::Error| {
+        AppError::AudioCapture(format!(
+            "Process audio capture failed (needs Windows 10 version 2004 or later): {e}"
+        ))
+    };
+
+    let mut params = AUDIOCLIENT_ACTIVATION_PARAMS {
+        ActivationType: AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK,
+        Anonymous: AUDIOCLIENT_ACTIVATION_PARAMS_0 {
+            ProcessLoopbackParams: AUDIOCLIENT_PROCESS_LOOPBACK_PARAMS {
+                TargetProcessId: pid,
+                ProcessLoopbackMode: if include_tree {
+                    PROCESS_LOOPBACK_MODE_INCLUDE_TARGET_PROCESS_TREE
+                } else {
+                    PROCESS_LOOPBACK_MODE_EXCLUDE_TARGET_PROCESS_TREE
+                },
+            },
+        },
+    };
+
+    // SAFETY: the caller guarantees COM is initialized on this thread. The
+    // blob borrows `params`, which outlives the activation; the PROPVARIANT
+    // is never passed to PropVariantClear, so nothing tries to free it.
+    unsafe {
+        let activation_params = PROPVARIANT {
+            Anonymous: PROPVARIANT_0 {
+                Anonymous: ManuallyDrop::new(PROPVARIANT_0_0 {
+                    vt: VT_BLOB,
+                    wReserved1: 0,
+                    wReserved2: 0,
+                    wReserved3: 0,
+                    Anonymous: PROPVARIANT_0_0_0 {
+                        blob: BLOB {
+                            cbSize: size_of::<AUDIOCLIENT_ACTIVATION_PARAMS>() as u32,
+                            pBlobData: std::ptr::addr_of_mut!(params).cast(),
+                        },
+                    },
+                }),
+            },
+        };
+
+        let done = CreateEventW(None, false, false, None)
+            .map_err(|e| AppError::AudioCapture(format!("CreateEvent: {e}")))?;
+        let handler: IActivateAudioInterfaceCompletionHandler = ActivationHandler { done }.into();
+
+        ActivateAudioInterfaceAsync(
+            VIRTUAL_AUDIO_DEVICE_PROCESS_LOOPBACK,
+            &IAudioClient::IID,
+            Some(std::ptr::addr_of!(activation_params)),
+            &handler,
+        )
+        .map_err(unsupported)
+        .and_then(|operation| {
+            match WaitForSingleObject(done, ACTIVATION_TIMEOUT_MS) {
+                WAIT_OBJECT_0 => {}
+                WAIT_TIMEOUT => {
+                    return Err(AppError::AudioCapture("Process audio activation timed out".into()));
+                }
+                _ => {
+                    return Err(AppError::AudioCapture(format!(
+                        "Waiting for process audio activation failed: {}",
+                        windows::core::Error::from_win32()
+                    )));
+                }
+            }
+            let mut result = HRESULT(0);
+            let mut client: Option<IUnknown> = None;
+            operation.GetActivateResult(&mut result, &mut client).map_err(unsupported)?;
+            result.ok().map_err(unsupported)?;
+            client
+                .ok_or_else(|| AppError::AudioCapture("Process audio activation returned no client".into()))?
+                .cast::<IAudioClient>()
+                .map_err(|e| AppError::AudioCapture(format!("Process audio client: {e}")))
+        })
+    }
+}
+
+/// 48 kHz stereo float, CoTaskMemAlloc'd so the session frees it like a
+/// mix format.
+unsafe fn process_loopback_format() -> Result<*mut WAVEFORMATEX, AppError> {
+    // SAFETY: the allocation is checked and fully written before use.
+    unsafe {
+        let pwfx = CoTaskMemAlloc(size_of::<WAVEFORMATEX>()).cast::<WAVEFORMATEX>();
+        if pwfx.is_null() {
+            return Err(AppError::AudioCapture("Out of memory for the capture format".into()));
+        }
+        pwfx.write(WAVEFORMATEX {
+            wFormatTag: 3, // WAVE_FORMAT_IEEE_FLOAT
+            nChannels: 2,
+            nSamplesPerSec: 48_000,
+            nAvgBytesPerSec: 48_000 * 8,
+            nBlockAlign: 8,
+            wBitsPerSample: 32,
+            cbSize: 0,
+        });
+        Ok(pwfx)
+    }
+}
+
+/// Executable name of `pid`, e.g. `chrome.exe`, to report as the device
+/// name. Fails only if no such process is running; a process this one may
+/// not inspect (e.g. elevated) is just named by its ID.
+fn process_name(pid: u32) -> Result<String, AppError> {
+    // SAFETY: plain Win32 calls on a handle closed before returning.
+    unsafe {
+        let process = match OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) {
+            Ok(process) => process,
+            Err(e) if e.code() == ERROR_INVALID_PARAMETER.to_hresult() => {
+                return Err(AppError::AudioCapture(format!("No running process with ID {pid}")));
+            }
+            Err(_) => return Ok(format!("Process {pid}")),
+        };
+
+        let mut buf = [0u16; 1024];
+        let mut len = buf.len() as u32;
+        let named = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buf.as_mut_ptr()), &mut len);
+        let _ = CloseHandle(process);
+
+        Ok(match named {
+            Ok(()) => {
+                let path = String::from_utf16_lossy(&buf[..len as usize]);
+                path.rsplit('\\').next().unwrap_or(&path).to_string()
+            }
+            Err(_) => format!("Process {pid}"),
+        })
+    }
+}
+
+// ── Device enumeration ──────────────────────────────────────────────
+
+/// Polls the default render endpoint so a capture can follow it when the
+/// user switches outputs or unplugs the current one.
+///
+/// Must stay on the thread that created it (COM apartment).
+pub struct DefaultDeviceWatcher {
+    enumerator: IMMDeviceEnumerator,
+    current_id: String,
+}
+
+impl DefaultDeviceWatcher {
+    /// # Safety
+    /// Must be called on a thread with COM initialized (use `ComGuard`).
+    pub unsafe fn new(current_id: String) -> Result<Self, AppError> {
+        // SAFETY: the caller guarantees COM is initialized on this thread.
+        let enumerator = unsafe { create_enumerator()? };
+        Ok(Self { enumerator, current_id })
+    }
+
+    /// Return the default endpoint's ID if it is no longer `current_id`.
+    /// `None` also covers "no default device right now".
+    pub fn poll(&self) -> Option<String> {
+        // SAFETY: the enumerator was created on this thread, which has COM initialized.
+        unsafe {
+            let device = default_render_device(&self.enumerator).ok()?;
+            let id = endpoint_id(&device).ok()?;
+            (id != self.current_id).then_some(id)
+        }
+    }
+
+    pub fn set_current(&mut self, device_id: String) {
+        self.current_id = device_id;
+    }
+}
+
+unsafe fn create_enumerator() -> Result<IMMDeviceEnumerator, AppError> {
+    // SAFETY: the caller guarantees COM is initialized on this thread.
+    unsafe {
+        CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)
+            .map_err(|e| AppError::AudioCapture(format!("Device enumerator: {e}")))
+    }
+}
+
+unsafe fn default_render_device(enumerator: &IMMDeviceEnumerator) -> Result<IMMDevice, AppError> {
+    // SAFETY: the caller guarantees COM is initialized on this thread.
+    unsafe {
+        enumerator
+            .GetDefaultAudioEndpoint(eRender, eConsole)
+            .map_err(|e| AppError::AudioCapture(format!("No default audio device: {e}")))
+    }
+}
+
+/// Collect all active render endpoints.
+unsafe fn active_render_devices(enumerator: &IMMDeviceEnumerator) -> Result<Vec<IMMDevice>, AppError> {
+    // SAFETY: the caller guarantees COM is initialized on this thread.
+    unsafe {
+        let collection = enumerator
+            .EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)
+            .map_err(|e| AppError::AudioCapture(format!("EnumAudioEndpoints: {e}")))?;
+        let count = collection
+            .GetCount()
+            .map_err(|e| AppError::AudioCapture(format!("Device count: {e}")))?;
+
+        (0..count)
+            .map(|i| {
+                collection
+                    .Item(i)
+                    .map_err(|e| AppError::AudioCapture(format!("Device item {i}: {e}")))
+            })
+            .collect()
+    }
+}
+
+/// Find an active render endpoint by its ID string.
+unsafe fn find_render_device(
+    enumerator: &IMMDeviceEnumerator,
+    device_id: &str,
+) -> Result<Option<IMMDevice>, AppError> {
+    // SAFETY: the caller guarantees COM is initialized on this thread.
+    unsafe {
+        for device in active_render_devices(enumerator)? {
+            if endpoint_id(&device)? == device_id {
+                return Ok(Some(device));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Read the endpoint ID string, freeing the COM-allocated buffer.
+unsafe fn endpoint_id(device: &IMMDevice) -> Result<String, AppError> {
+    // SAFETY: GetId returns a CoTaskMemAlloc'd string that we own and must free.
+    unsafe {
+        let raw = device
+            .GetId()
+            .map_err(|e| AppError::AudioCapture(format!("Device ID: {e}")))?;
+        let id = raw.to_string();
+        CoTaskMemFree(Some(raw.0 as *const _));
+        id.map_err(|e| AppError::AudioCapture(format!("Device ID encoding: {e}")))
+    }
+}
+
+/// Read `PKEY_Device_FriendlyName` from the endpoint property store.
+unsafe fn friendly_name(device: &IMMDevice) -> Result<String, AppError> {
+    // SAFETY: the caller guarantees COM is initialized on this thread.
+    unsafe {
+        let store = device
+            .OpenPropertyStore(STGM_READ)
+            .map_err(|e| AppError::AudioCapture(format!("OpenPropertyStore: {e}")))?;
+        let mut value = store
+            .GetValue(&PKEY_Device_FriendlyName)
+            .map_err(|e| AppError::AudioCapture(format!("Friendly name: {e}")))?;
+        // Both the PROPVARIANT and the string copied out of it are ours to free
+        let raw = PropVariantToStringAlloc(&value);
+        let _ = PropVariantClear(&mut value);
+        let raw = raw.map_err(|e| AppError::AudioCapture(format!("Friendly name: {e}")))?;
+        let name = raw.to_string();
+        CoTaskMemFree(Some(raw.0 as *const _));
+        name.map_err(|e| AppError::AudioCapture(format!("Friendly name encoding: {e}")))
+    }
+}
+
+/// List active render endpoints as `(id, friendly_name)` pairs.
+pub fn list_output_devices() -> Result<Vec<(String, String)>, AppError> {
+    let _com = ComGuard::init();
+    unsafe {
+        let enumerator = create_enumerator()?;
+        active_render_devices(&enumerator)?
+            .iter()
+            .map(|device| {
+                let id = endpoint_id(device)?;
+                let name = friendly_name(device).unwrap_or_else(|_| id.clone());
+                Ok((id, name))
+            })
+            .collect()
+    }
+}
+
 // ── Availability check ──────────────────────────────────────────────
 
+/// Friendly name and mix format of the default render device, read without
+/// initializing a stream.
+pub fn probe_default_device() -> Result<(String, AudioFormat), AppError> {
+    let _com = ComGuard::init();
+    unsafe {
+        let enumerator = create_enumerator()?;
+        let device = default_render_device(&enumerator)?;
+        let name = friendly_name(&device).unwrap_or_default();
+
+        let audio_client: IAudioClient = device
+            .Activate(CLSCTX_ALL, None)
+            .map_err(|e| AppError::AudioCapture(format!("Activate audio client: {e}")))?;
+        let pwfx = audio_client
+            .GetMixFormat()
+            .map_err(|e| AppError::AudioCapture(format!("GetMixFormat: {e}")))?;
+        let format = LoopbackSession::parse_format(&*pwfx, pwfx);
+        CoTaskMemFree(Some(pwfx as *const _));
+
+        Ok((name, format))
+    }
+}
+
 pub fn check_available() -> bool {
     let _com = ComGuard::init();
     unsafe {
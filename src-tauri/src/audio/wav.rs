@@ -2,15 +2,10 @@ use crate::error::AppError;
 use std::fs::File;
 use std::io::{BufWriter, Seek, SeekFrom, Write};
 
-use super::wasapi::AudioFormat;
-
-// WAV header constants
-const RIFF: &[u8; 4] = b"RIFF";
-const WAVE: &[u8; 4] = b"WAVE";
-const FMT_: &[u8; 4] = b"fmt ";
-const DATA: &[u8; 4] = b"data";
-// WAVE_FORMAT_IEEE_FLOAT
-const WAVE_FORMAT_FLOAT: u16 = 3;
+use super::sink::AudioSink;
+use super::wav_format::{self, HEADER_SIZE};
+use super::wav_reader::{probe_wav, read_wav_f32};
+use super::{AudioFormat, BitDepth, RealtimeDenoiser};
 
 /// Zero-overhead WAV writer.
 ///
@@ -18,132 +13,168 @@ const WAVE_FORMAT_FLOAT: u16 = 3;
 /// directly to a `BufWriter<File>`. No per-sample function calls, no
 /// bounds checks — just `memcpy` via `write_all`.
 ///
+/// With `BitDepth::Pcm16` samples are converted through reusable scratch
+/// buffers instead, so the hot path still never allocates once warmed up.
+///
 /// On `finalize()`, seeks back and patches the header with the final size.
 pub struct AudioWavWriter {
     writer: BufWriter<File>,
     format: AudioFormat,
+    bit_depth: BitDepth,
     data_bytes_written: u64,
+    /// Encoded output bytes when a conversion is needed.
+    scratch_bytes: Vec<u8>,
+    /// Applied to every buffer before it is encoded, when set.
+    denoiser: Option<RealtimeDenoiser>,
+    /// Copy of the incoming samples for the denoiser to work on in place.
+    scratch_denoise: Vec<f32>,
 }
 
-/// Size of the BufWriter internal buffer.
+/// Default size of the BufWriter internal buffer.
 /// 256 KB ≈ 1.3 s of stereo 48 kHz f32 audio → one syscall per ~1 s.
 const BUF_CAPACITY: usize = 256 * 1024;
 
 impl AudioWavWriter {
     /// Create a new WAV file at `path`. Writes the header immediately.
-    pub fn create(path: &str, format: AudioFormat) -> Result<Self, AppError> {
+    ///
+    /// `buffer_bytes` sizes the write buffer (default [`BUF_CAPACITY`]):
+    /// smaller saves memory for short clips, larger means fewer syscalls
+    /// for high channel counts.
+    pub fn create(
+        path: &str,
+        format: AudioFormat,
+        bit_depth: BitDepth,
+        buffer_bytes: Option<usize>,
+    ) -> Result<Self, AppError> {
         let file = File::create(path)
             .map_err(|e| AppError::WavEncode(format!("Create WAV file: {e}")))?;
-        let mut writer = BufWriter::with_capacity(BUF_CAPACITY, file);
+        let mut writer = BufWriter::with_capacity(buffer_bytes.unwrap_or(BUF_CAPACITY), file);
 
         // Write placeholder header — finalize() patches the sizes
-        Self::write_header(&mut writer, &format, 0)?;
+        Self::write_header(&mut writer, &format, bit_depth, 0)?;
 
         Ok(Self {
             writer,
             format,
+            bit_depth,
             data_bytes_written: 0,
+            scratch_bytes: Vec::new(),
+            denoiser: None,
+            scratch_denoise: Vec::new(),
         })
     }
 
     /// Write the 44-byte WAV header. `data_size` can be 0 for the initial write.
-    fn write_header(w: &mut impl Write, fmt: &AudioFormat, data_size: u32) -> Result<(), AppError> {
-        let channels = fmt.channels;
-        let sample_rate = fmt.sample_rate;
-        let bits_per_sample: u16 = 32; // always write f32
-        let block_align = channels * (bits_per_sample / 8);
-        let byte_rate = sample_rate * block_align as u32;
-        let chunk_size = 36 + data_size;
-
-        let mut header = [0u8; 44];
-        header[0..4].copy_from_slice(RIFF);
-        header[4..8].copy_from_slice(&chunk_size.to_le_bytes());
-        header[8..12].copy_from_slice(WAVE);
-        header[12..16].copy_from_slice(FMT_);
-        header[16..20].copy_from_slice(&16u32.to_le_bytes()); // fmt chunk size
-        header[20..22].copy_from_slice(&WAVE_FORMAT_FLOAT.to_le_bytes());
-        header[22..24].copy_from_slice(&channels.to_le_bytes());
-        header[24..28].copy_from_slice(&sample_rate.to_le_bytes());
-        header[28..32].copy_from_slice(&byte_rate.to_le_bytes());
-        header[32..34].copy_from_slice(&block_align.to_le_bytes());
-        header[34..36].copy_from_slice(&bits_per_sample.to_le_bytes());
-        header[36..40].copy_from_slice(DATA);
-        header[40..44].copy_from_slice(&data_size.to_le_bytes());
-
-        w.write_all(&header)
+    fn write_header(
+        w: &mut impl Write,
+        fmt: &AudioFormat,
+        bit_depth: BitDepth,
+        data_size: u32,
+    ) -> Result<(), AppError> {
+        let bits_per_sample = match bit_depth {
+            BitDepth::Float32 => 32,
+            BitDepth::Pcm16 => 16,
+        };
+        let is_float = bit_depth == BitDepth::Float32;
+        wav_format::write_header(w, fmt.channels, fmt.sample_rate, bits_per_sample, is_float, data_size)
             .map_err(|e| AppError::WavEncode(format!("Write WAV header: {e}")))
     }
 
+    #[inline]
+    fn encode_samples(&mut self, samples: &[f32]) -> Result<(), AppError> {
+        match self.bit_depth {
+            BitDepth::Float32 => {
+                // SAFETY: any initialized f32 slice can be viewed as bytes
+                let bytes = unsafe {
+                    std::slice::from_raw_parts(samples.as_ptr() as *const u8, samples.len() * 4)
+                };
+                self.write_bytes(bytes)
+            }
+            BitDepth::Pcm16 => {
+                let mut bytes = std::mem::take(&mut self.scratch_bytes);
+                bytes.clear();
+                for &s in samples {
+                    bytes.extend_from_slice(&f32_to_i16(s).to_le_bytes());
+                }
+                let result = self.write_bytes(&bytes);
+                self.scratch_bytes = bytes;
+                result
+            }
+        }
+    }
+
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), AppError> {
+        self.writer.write_all(bytes)
+            .map_err(|e| AppError::WavEncode(format!("Write audio: {e}")))?;
+        self.data_bytes_written += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+impl AudioSink for AudioWavWriter {
+    type Output = ();
+
+    /// Denoise everything written from now on. The denoiser must match the
+    /// file's channel count and run at 48 kHz.
+    fn set_denoiser(&mut self, denoiser: RealtimeDenoiser) {
+        self.denoiser = Some(denoiser);
+    }
+
     /// Write silence for `frame_count` frames.
     ///
     /// Uses a stack-allocated zero buffer to avoid heap allocation in the
     /// capture hot path (rule: no allocations in audio capture loop).
     #[inline]
-    pub fn write_silence(&mut self, frame_count: usize) -> Result<(), AppError> {
+    fn write_silence(&mut self, frame_count: usize) -> Result<(), AppError> {
         const ZERO_BUF: [u8; 4096] = [0u8; 4096];
-        let mut remaining = frame_count * self.format.channels as usize * 4;
+        let byte_len = frame_count * self.format.channels as usize * self.bit_depth.bytes_per_sample();
+        let mut remaining = byte_len;
         while remaining > 0 {
             let n = remaining.min(ZERO_BUF.len());
             self.writer.write_all(&ZERO_BUF[..n])
                 .map_err(|e| AppError::WavEncode(format!("Write silence: {e}")))?;
             remaining -= n;
         }
-        self.data_bytes_written += (frame_count * self.format.channels as usize * 4) as u64;
+        self.data_bytes_written += byte_len as u64;
         Ok(())
     }
 
-    /// Write raw WASAPI audio data, converting to f32 if needed.
-    /// Returns the RMS level (0.0–1.0) of the written audio for spectrum visualization.
-    ///
-    /// # Safety
-    /// `ptr` must point to valid audio data of at least `frame_count` frames.
+    /// Write interleaved f32 samples that are already in the file's format
+    /// (e.g. the output of the mic/system mixer), encoding to the file's
+    /// bit depth.
     #[inline]
-    pub unsafe fn write_raw(&mut self, ptr: *const u8, frame_count: usize) -> Result<f32, AppError> {
-        let channels = self.format.channels as usize;
-        let sample_count = frame_count * channels;
-
-        if self.format.is_float && self.format.bits_per_sample == 32 {
-            // Fast path: source is already f32 — raw memcpy
-            let byte_len = sample_count * 4;
-            // SAFETY: caller guarantees ptr is valid for byte_len bytes of f32 audio
-            let bytes = unsafe { std::slice::from_raw_parts(ptr, byte_len) };
-            let samples = unsafe { std::slice::from_raw_parts(ptr as *const f32, sample_count) };
-            let rms = compute_rms(samples);
-            self.writer.write_all(bytes)
-                .map_err(|e| AppError::WavEncode(format!("Write audio: {e}")))?;
-            self.data_bytes_written += byte_len as u64;
-            Ok(rms)
-        } else if !self.format.is_float && self.format.bits_per_sample == 16 {
-            // Convert i16 → f32
-            // SAFETY: caller guarantees ptr is valid for sample_count i16 samples
-            let src = unsafe { std::slice::from_raw_parts(ptr as *const i16, sample_count) };
-            let mut buf = Vec::with_capacity(sample_count);
-            for &s in src {
-                buf.push(s as f32 / 32768.0);
-            }
-            let rms = compute_rms(&buf);
-            // SAFETY: buf is a valid Vec<f32> we just created; reinterpreting as bytes
-            let bytes = unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const u8, sample_count * 4) };
-            self.writer.write_all(bytes)
-                .map_err(|e| AppError::WavEncode(format!("Write audio: {e}")))?;
-            self.data_bytes_written += (sample_count * 4) as u64;
-            Ok(rms)
-        } else {
-            // Fallback: treat as f32
-            let byte_len = sample_count * 4;
-            // SAFETY: caller guarantees ptr is valid for byte_len bytes
-            let bytes = unsafe { std::slice::from_raw_parts(ptr, byte_len) };
-            let samples = unsafe { std::slice::from_raw_parts(ptr as *const f32, sample_count) };
-            let rms = compute_rms(samples);
-            self.writer.write_all(bytes)
-                .map_err(|e| AppError::WavEncode(format!("Write audio: {e}")))?;
-            self.data_bytes_written += byte_len as u64;
-            Ok(rms)
-        }
+    fn write_samples(&mut self, samples: &[f32]) -> Result<(), AppError> {
+        let Some(denoiser) = self.denoiser.as_mut() else {
+            return self.encode_samples(samples);
+        };
+
+        // Output lags input by up to one RNNoise frame; `finalize` writes the rest
+        let mut buf = std::mem::take(&mut self.scratch_denoise);
+        buf.clear();
+        buf.extend_from_slice(denoiser.process_interleaved(samples));
+        let result = self.encode_samples(&buf);
+        self.scratch_denoise = buf;
+        result
     }
 
-    /// Flush the buffer, seek back, and patch the WAV header with final sizes.
-    pub fn finalize(mut self) -> Result<(), AppError> {
+    fn channels(&self) -> usize {
+        self.format.channels as usize
+    }
+
+    /// Current file size in bytes, including the header.
+    fn file_bytes(&self) -> u64 {
+        HEADER_SIZE + self.data_bytes_written
+    }
+
+    /// Write the denoiser's buffered tail, flush, seek back, and patch the
+    /// WAV header with final sizes.
+    fn finalize(mut self) -> Result<(), AppError> {
+        if let Some(mut denoiser) = self.denoiser.take() {
+            let tail = denoiser.flush();
+            self.encode_samples(&tail)?;
+        }
+
         self.writer.flush()
             .map_err(|e| AppError::WavEncode(format!("Flush: {e}")))?;
 
@@ -153,7 +184,7 @@ impl AudioWavWriter {
         self.writer.seek(SeekFrom::Start(0))
             .map_err(|e| AppError::WavEncode(format!("Seek: {e}")))?;
 
-        Self::write_header(&mut self.writer, &self.format, data_size)?;
+        Self::write_header(&mut self.writer, &self.format, self.bit_depth, data_size)?;
 
         self.writer.flush()
             .map_err(|e| AppError::WavEncode(format!("Final flush: {e}")))?;
@@ -162,23 +193,114 @@ impl AudioWavWriter {
     }
 }
 
-/// Compute RMS level of f32 samples, clamped to 0.0–1.0.
+/// Join `inputs` end to end into a new WAV at `output_path`, one input in
+/// memory at a time. All inputs must share a channel count and sample rate;
+/// the output is 16-bit when every input is, 32-bit float otherwise.
+pub fn concat_wavs(inputs: &[String], output_path: &str) -> Result<String, AppError> {
+    let Some(first) = inputs.first() else {
+        return Err(AppError::WavEncode("No input files to concatenate".into()));
+    };
+    let infos = inputs
+        .iter()
+        .map(|path| probe_wav(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let reference = &infos[0];
+    for (path, info) in inputs.iter().zip(&infos).skip(1) {
+        if info.channels != reference.channels || info.sample_rate != reference.sample_rate {
+            return Err(AppError::WavEncode(format!(
+                "{path} is {} ch @ {} Hz but {first} is {} ch @ {} Hz",
+                info.channels, info.sample_rate, reference.channels, reference.sample_rate,
+            )));
+        }
+    }
+
+    let all_pcm16 = infos.iter().all(|i| !i.is_float && i.bits_per_sample == 16);
+    let format = AudioFormat {
+        sample_rate: reference.sample_rate,
+        channels: reference.channels,
+        bits_per_sample: 32,
+        is_float: true,
+    };
+    let bit_depth = if all_pcm16 { BitDepth::Pcm16 } else { BitDepth::Float32 };
+
+    let mut writer = AudioWavWriter::create(output_path, format, bit_depth, None)?;
+    for path in inputs {
+        let (samples, _) = read_wav_f32(path)?;
+        writer.write_samples(&samples)?;
+    }
+    writer.finalize()?;
+
+    Ok(output_path.to_string())
+}
+
+/// Convert a float sample to 16-bit PCM, clamping out-of-range values.
 #[inline]
-fn compute_rms(samples: &[f32]) -> f32 {
-    if samples.is_empty() {
-        return 0.0;
+fn f32_to_i16(s: f32) -> i16 {
+    (s.clamp(-1.0, 1.0) * 32767.0).round() as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_util::{TempWav, WavFixture};
+
+    #[test]
+    fn denoised_recording_is_as_long_as_the_dry_one() {
+        let format = AudioFormat { sample_rate: 48_000, channels: 2, bits_per_sample: 32, is_float: true };
+        // 1001 frames per write, so the stream never ends on a 480-sample boundary
+        let chunk: Vec<f32> = (0..1_001 * 2).map(|i| (i as f32 * 0.01).sin() * 0.3).collect();
+
+        let mut frames = Vec::new();
+        for denoise in [false, true] {
+            let output = TempWav::new();
+            let mut writer = AudioWavWriter::create(output.path(), format, BitDepth::Float32, None).unwrap();
+            if denoise {
+                writer.set_denoiser(RealtimeDenoiser::new(1.0, 2));
+            }
+            for _ in 0..7 {
+                writer.write_samples(&chunk).unwrap();
+            }
+            writer.finalize().unwrap();
+
+            let info = probe_wav(output.path()).unwrap();
+            frames.push(info.data_size / u32::from(info.channels * info.bits_per_sample / 8));
+        }
+
+        assert_eq!(frames, [7_007, 7_007]);
     }
-    // Sample every 4th value for speed — RMS doesn't need every sample
-    let step = 4;
-    let mut sum = 0.0f64;
-    let mut count = 0u32;
-    let mut i = 0;
-    while i < samples.len() {
-        let s = samples[i] as f64;
-        sum += s * s;
-        count += 1;
-        i += step;
+
+    #[test]
+    fn concat_wavs_output_holds_every_input_frame() {
+        let fixture = WavFixture { sample_rate: 16_000, ..WavFixture::default() };
+        let a = WavFixture { frames: 1_600, ..fixture }.write();
+        let b = WavFixture { frames: 800, ..fixture }.write();
+        let output = TempWav::new();
+
+        concat_wavs(&[a.path().to_string(), b.path().to_string()], output.path()).unwrap();
+
+        let info = probe_wav(output.path()).unwrap();
+        let frames = info.data_size / u32::from(info.channels * info.bits_per_sample / 8);
+        assert_eq!(frames, 2_400);
+    }
+
+    #[test]
+    fn read_wav_f32_reads_back_what_the_writer_wrote() {
+        let samples: Vec<f32> = (0..960).map(|i| (i as f32 / 960.0) - 0.5).collect();
+
+        for bit_depth in [BitDepth::Float32, BitDepth::Pcm16] {
+            let output = TempWav::new();
+            let format = AudioFormat { sample_rate: 48_000, channels: 2, bits_per_sample: 32, is_float: true };
+            let mut writer = AudioWavWriter::create(output.path(), format, bit_depth, None).unwrap();
+            writer.write_samples(&samples).unwrap();
+            writer.finalize().unwrap();
+
+            let (read, info) = read_wav_f32(output.path()).unwrap();
+            assert_eq!((info.channels, info.sample_rate), (2, 48_000));
+            assert_eq!(info.is_float, bit_depth == BitDepth::Float32);
+            assert_eq!(read.len(), samples.len());
+            let tolerance = if info.is_float { 0.0 } else { 2.0 / 32_768.0 };
+            assert!(read.iter().zip(&samples).all(|(a, b)| (a - b).abs() <= tolerance));
+        }
     }
-    let rms = (sum / count as f64).sqrt() as f32;
-    rms.min(1.0)
 }
@@ -0,0 +1,209 @@
+use crate::error::AppError;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+// WAV header constants, shared by every reader and writer in `audio`
+pub const RIFF: &[u8; 4] = b"RIFF";
+pub const WAVE: &[u8; 4] = b"WAVE";
+pub const FMT_: &[u8; 4] = b"fmt ";
+pub const DATA: &[u8; 4] = b"data";
+pub const WAVE_FORMAT_PCM: u16 = 1;
+// WAVE_FORMAT_IEEE_FLOAT
+pub const WAVE_FORMAT_FLOAT: u16 = 3;
+/// `WAVEFORMATEXTENSIBLE`: the real format tag is the start of the
+/// sub-format GUID at offset 24 of the fmt chunk.
+pub const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Size of the canonical header `write_header` produces.
+pub const HEADER_SIZE: u64 = 44;
+
+/// Minimal WAV format info extracted from header.
+#[derive(Debug, Clone)]
+pub struct WavInfo {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    pub is_float: bool,
+    pub data_offset: u64,
+    pub data_size: u32,
+}
+
+impl WavInfo {
+    /// Playing time of the data chunk.
+    pub fn duration_ms(&self) -> u64 {
+        let bytes_per_second = self.sample_rate as u64
+            * self.channels as u64
+            * (self.bits_per_sample as u64 / 8);
+        if bytes_per_second == 0 {
+            return 0;
+        }
+        self.data_size as u64 * 1000 / bytes_per_second
+    }
+}
+
+/// Fields of the fmt chunk that `WavInfo` needs.
+#[derive(Clone, Copy)]
+struct FmtChunk {
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    is_float: bool,
+}
+
+impl FmtChunk {
+    /// Parse a fmt chunk of any length: plain (16 bytes), `WAVEFORMATEX`
+    /// (18+) or `WAVEFORMATEXTENSIBLE` (40).
+    fn parse(fmt: &[u8]) -> Result<Self, AppError> {
+        if fmt.len() < 16 {
+            return Err(AppError::WavDecode(format!("fmt chunk too short: {} bytes", fmt.len())));
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([fmt[i], fmt[i + 1]]);
+
+        let mut format_tag = u16_at(0);
+        if format_tag == WAVE_FORMAT_EXTENSIBLE && fmt.len() >= 26 {
+            format_tag = u16_at(24);
+        }
+
+        Ok(Self {
+            channels: u16_at(2),
+            sample_rate: u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]),
+            bits_per_sample: u16_at(14),
+            is_float: format_tag == WAVE_FORMAT_FLOAT,
+        })
+    }
+}
+
+/// Read and parse a WAV header, returning format info.
+///
+/// Walks the RIFF chunks from offset 12 by their (id, size) headers, so fmt
+/// chunks of any size and extra chunks (`LIST`, `fact`, ...) before the
+/// data are handled. Odd-sized chunks are followed by a padding byte.
+///
+/// A data size of 0 or one running past the end of the file (streamed or
+/// interrupted recordings never got their header patched) is replaced by
+/// every whole frame up to EOF; a torn last frame is dropped.
+pub fn read_header(reader: &mut (impl Read + Seek)) -> Result<WavInfo, AppError> {
+    reader.seek(SeekFrom::Start(0))
+        .map_err(|e| AppError::WavDecode(format!("Seek: {e}")))?;
+
+    let mut riff = [0u8; 12];
+    reader.read_exact(&mut riff)
+        .map_err(|e| AppError::WavDecode(format!("Read WAV header: {e}")))?;
+
+    if &riff[0..4] != RIFF || &riff[8..12] != WAVE {
+        return Err(AppError::WavDecode("Not a valid WAV file".into()));
+    }
+
+    let mut fmt: Option<FmtChunk> = None;
+    let mut offset: u64 = 12; // after RIFF + size + WAVE
+
+    loop {
+        let mut chunk_header = [0u8; 8];
+        reader.read_exact(&mut chunk_header)
+            .map_err(|e| AppError::WavDecode(format!("Read chunk header: {e}")))?;
+        offset += 8;
+
+        let chunk_size = u32::from_le_bytes([
+            chunk_header[4], chunk_header[5], chunk_header[6], chunk_header[7],
+        ]);
+
+        match &chunk_header[0..4] {
+            id if id == DATA => {
+                let fmt = fmt.ok_or_else(|| AppError::WavDecode("data chunk before fmt chunk".into()))?;
+                let file_len = reader.seek(SeekFrom::End(0))
+                    .map_err(|e| AppError::WavDecode(format!("Seek to end: {e}")))?;
+                let remaining = file_len.saturating_sub(offset);
+                let data_size = if chunk_size == 0 || chunk_size as u64 > remaining {
+                    let block_align = (fmt.channels as u64 * (fmt.bits_per_sample as u64 / 8)).max(1);
+                    let whole = remaining.min(u32::MAX as u64);
+                    (whole - whole % block_align) as u32
+                } else {
+                    chunk_size
+                };
+                return Ok(WavInfo {
+                    channels: fmt.channels,
+                    sample_rate: fmt.sample_rate,
+                    bits_per_sample: fmt.bits_per_sample,
+                    is_float: fmt.is_float,
+                    data_offset: offset,
+                    data_size,
+                });
+            }
+            id if id == FMT_ => {
+                let mut bytes = vec![0u8; chunk_size as usize];
+                reader.read_exact(&mut bytes)
+                    .map_err(|e| AppError::WavDecode(format!("Read fmt chunk: {e}")))?;
+                fmt = Some(FmtChunk::parse(&bytes)?);
+            }
+            _ => {
+                reader.seek(SeekFrom::Current(chunk_size as i64))
+                    .map_err(|e| AppError::WavDecode(format!("Skip chunk: {e}")))?;
+            }
+        }
+        offset += chunk_size as u64;
+
+        if chunk_size % 2 == 1 {
+            reader.seek(SeekFrom::Current(1))
+                .map_err(|e| AppError::WavDecode(format!("Skip chunk padding: {e}")))?;
+            offset += 1;
+        }
+    }
+}
+
+/// Write the canonical 44-byte header: RIFF, a 16-byte fmt chunk (PCM, or
+/// IEEE float when `is_float`), and the data chunk header. `data_size` can
+/// be 0 for a placeholder patched once the length is known.
+pub fn write_header(
+    w: &mut impl Write,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    is_float: bool,
+    data_size: u32,
+) -> std::io::Result<()> {
+    let format_tag = if is_float { WAVE_FORMAT_FLOAT } else { WAVE_FORMAT_PCM };
+    let block_align = channels * (bits_per_sample / 8);
+    let byte_rate = sample_rate * block_align as u32;
+    let chunk_size = 36u32.saturating_add(data_size);
+
+    let mut header = [0u8; HEADER_SIZE as usize];
+    header[0..4].copy_from_slice(RIFF);
+    header[4..8].copy_from_slice(&chunk_size.to_le_bytes());
+    header[8..12].copy_from_slice(WAVE);
+    header[12..16].copy_from_slice(FMT_);
+    header[16..20].copy_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    header[20..22].copy_from_slice(&format_tag.to_le_bytes());
+    header[22..24].copy_from_slice(&channels.to_le_bytes());
+    header[24..28].copy_from_slice(&sample_rate.to_le_bytes());
+    header[28..32].copy_from_slice(&byte_rate.to_le_bytes());
+    header[32..34].copy_from_slice(&block_align.to_le_bytes());
+    header[34..36].copy_from_slice(&bits_per_sample.to_le_bytes());
+    header[36..40].copy_from_slice(DATA);
+    header[40..44].copy_from_slice(&data_size.to_le_bytes());
+
+    w.write_all(&header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn read_header_reads_what_write_header_wrote() {
+        for (channels, sample_rate, bits, is_float) in
+            [(2, 48_000, 32, true), (1, 16_000, 16, false), (6, 44_100, 24, false)]
+        {
+            let data_size = u32::from(channels * bits / 8) * 10;
+            let mut bytes = Vec::new();
+            write_header(&mut bytes, channels, sample_rate, bits, is_float, data_size).unwrap();
+            bytes.resize(bytes.len() + data_size as usize, 0);
+
+            let info = read_header(&mut Cursor::new(bytes)).unwrap();
+            assert_eq!(
+                (info.channels, info.sample_rate, info.bits_per_sample, info.is_float),
+                (channels, sample_rate, bits, is_float)
+            );
+            assert_eq!((info.data_offset, info.data_size), (HEADER_SIZE, data_size));
+        }
+    }
+}
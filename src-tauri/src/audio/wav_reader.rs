@@ -0,0 +1,229 @@
+use crate::error::AppError;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+use super::wav_format::{read_header, WavInfo};
+
+/// Read a WAV file's format and data layout without loading the samples.
+pub fn probe_wav(path: &str) -> Result<WavInfo, AppError> {
+    let file = File::open(path)
+        .map_err(|e| AppError::WavDecode(format!("Open WAV: {e}")))?;
+    read_header(&mut BufReader::new(file))
+}
+
+/// Read all samples from a WAV file as f32. Returns (samples, info).
+///
+/// Supports 8-bit unsigned, 16-bit and 24-bit PCM and 32-bit float.
+pub fn read_wav_f32(path: &str) -> Result<(Vec<f32>, WavInfo), AppError> {
+    let file = File::open(path)
+        .map_err(|e| AppError::WavDecode(format!("Open WAV: {e}")))?;
+    read_wav_samples(&mut BufReader::new(file), None)
+}
+
+/// Like [`read_wav_f32`], but reads no more than the first `max_seconds`
+/// of audio; the rest of the file is never loaded. The returned
+/// `info.data_size` is the number of bytes actually read.
+pub fn read_wav_f32_prefix(path: &str, max_seconds: f32) -> Result<(Vec<f32>, WavInfo), AppError> {
+    let file = File::open(path)
+        .map_err(|e| AppError::WavDecode(format!("Open WAV: {e}")))?;
+    read_wav_samples(&mut BufReader::new(file), Some(max_seconds))
+}
+
+fn read_wav_samples(
+    reader: &mut (impl Read + Seek),
+    max_seconds: Option<f32>,
+) -> Result<(Vec<f32>, WavInfo), AppError> {
+    let mut info = read_header(reader)?;
+    if let Some(seconds) = max_seconds {
+        let frames = (seconds.max(0.0) * info.sample_rate as f32) as u64;
+        let block_align = u64::from(info.channels) * u64::from(info.bits_per_sample / 8);
+        let wanted = frames.saturating_mul(block_align);
+        info.data_size = info.data_size.min(wanted.try_into().unwrap_or(u32::MAX));
+    }
+
+    reader.seek(SeekFrom::Start(info.data_offset))
+        .map_err(|e| AppError::WavDecode(format!("Seek to data: {e}")))?;
+
+    let decode: fn(&[u8]) -> f32 = match (info.is_float, info.bits_per_sample) {
+        (true, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        (false, 24) => |b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0,
+        (false, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+        // 8-bit WAV is unsigned, centered on 128
+        (false, 8) => |b| (b[0] as f32 - 128.0) / 128.0,
+        _ => {
+            return Err(AppError::UnsupportedAudioFormat {
+                bits: info.bits_per_sample,
+                is_float: info.is_float,
+            })
+        }
+    };
+
+    let mut bytes = vec![0u8; info.data_size as usize];
+    reader.read_exact(&mut bytes)
+        .map_err(|e| AppError::WavDecode(format!("Read audio data: {e}")))?;
+    let samples = bytes
+        .chunks_exact(info.bits_per_sample as usize / 8)
+        .map(decode)
+        .collect();
+    Ok((samples, info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_util::WavFixture;
+    use super::super::wav_format::{DATA, FMT_, RIFF, WAVE, WAVE_FORMAT_EXTENSIBLE, WAVE_FORMAT_FLOAT};
+    use std::io::Cursor;
+
+    /// A minimal mono 8 kHz PCM WAV holding `data`.
+    fn wav_bytes(bits: u16, data: &[u8]) -> Vec<u8> {
+        let block_align = bits / 8;
+        let mut out = Vec::new();
+        out.extend_from_slice(RIFF);
+        out.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        out.extend_from_slice(WAVE);
+        out.extend_from_slice(FMT_);
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        out.extend_from_slice(&1u16.to_le_bytes()); // mono
+        out.extend_from_slice(&8000u32.to_le_bytes());
+        out.extend_from_slice(&(8000 * block_align as u32).to_le_bytes());
+        out.extend_from_slice(&block_align.to_le_bytes());
+        out.extend_from_slice(&bits.to_le_bytes());
+        out.extend_from_slice(DATA);
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn decode(bits: u16, data: &[u8]) -> Vec<f32> {
+        let (samples, info) = read_wav_samples(&mut Cursor::new(wav_bytes(bits, data)), None).unwrap();
+        assert_eq!(info.bits_per_sample, bits);
+        samples
+    }
+
+    #[test]
+    fn reads_24_bit_pcm() {
+        let samples = decode(24, &[
+            0x00, 0x00, 0x00, // 0
+            0xFF, 0xFF, 0x7F, // max positive
+            0x00, 0x00, 0x80, // min negative
+            0x00, 0x00, 0x40, // +0.5
+            0xFF, 0xFF, 0xFF, // -1 LSB
+        ]);
+        assert_eq!(samples, [0.0, 8_388_607.0 / 8_388_608.0, -1.0, 0.5, -1.0 / 8_388_608.0]);
+    }
+
+    #[test]
+    fn reads_8_bit_unsigned_pcm() {
+        let samples = decode(8, &[128, 255, 0, 192, 64]);
+        assert_eq!(samples, [0.0, 127.0 / 128.0, -1.0, 0.5, -0.5]);
+    }
+
+    #[test]
+    fn reads_16_bit_pcm() {
+        let data: Vec<u8> = [0i16, i16::MAX, i16::MIN, 16384]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let samples = decode(16, &data);
+        assert_eq!(samples, [0.0, 32767.0 / 32768.0, -1.0, 0.5]);
+    }
+
+    #[test]
+    fn reads_extensible_fmt_after_odd_sized_chunk() {
+        let samples = [0.25f32, -0.5];
+        let mut out = Vec::new();
+        out.extend_from_slice(RIFF);
+        out.extend_from_slice(&0u32.to_le_bytes()); // size isn't checked
+        out.extend_from_slice(WAVE);
+        // Odd-sized chunk, then its padding byte
+        out.extend_from_slice(b"LIST");
+        out.extend_from_slice(&3u32.to_le_bytes());
+        out.extend_from_slice(&[1, 2, 3, 0]);
+        out.extend_from_slice(FMT_);
+        out.extend_from_slice(&40u32.to_le_bytes());
+        out.extend_from_slice(&WAVE_FORMAT_EXTENSIBLE.to_le_bytes());
+        out.extend_from_slice(&2u16.to_le_bytes());
+        out.extend_from_slice(&48_000u32.to_le_bytes());
+        out.extend_from_slice(&(48_000u32 * 8).to_le_bytes());
+        out.extend_from_slice(&8u16.to_le_bytes());
+        out.extend_from_slice(&32u16.to_le_bytes());
+        out.extend_from_slice(&22u16.to_le_bytes()); // cbSize
+        out.extend_from_slice(&32u16.to_le_bytes()); // valid bits
+        out.extend_from_slice(&3u32.to_le_bytes()); // channel mask
+        out.extend_from_slice(&WAVE_FORMAT_FLOAT.to_le_bytes()); // sub-format GUID...
+        out.extend_from_slice(&[0; 14]);
+        out.extend_from_slice(DATA);
+        out.extend_from_slice(&8u32.to_le_bytes());
+        out.extend(samples.iter().flat_map(|s| s.to_le_bytes()));
+
+        let (decoded, info) = read_wav_samples(&mut Cursor::new(out), None).unwrap();
+        assert_eq!((info.channels, info.sample_rate, info.bits_per_sample), (2, 48_000, 32));
+        assert!(info.is_float);
+        assert_eq!(info.data_offset, 12 + 12 + 48 + 8);
+        assert_eq!(decoded, samples);
+    }
+
+    #[test]
+    fn recovers_samples_when_data_size_is_missing_or_too_large() {
+        let data: Vec<u8> = [1000i16, -2000, 3000]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+
+        for bogus_size in [0u32, u32::MAX] {
+            let mut bytes = wav_bytes(16, &data);
+            bytes[40..44].copy_from_slice(&bogus_size.to_le_bytes());
+
+            let (samples, info) = read_wav_samples(&mut Cursor::new(bytes), None).unwrap();
+            assert_eq!(info.data_size, 6, "data size {bogus_size:#x}");
+            assert_eq!(samples, [1000.0 / 32768.0, -2000.0 / 32768.0, 3000.0 / 32768.0]);
+        }
+    }
+
+    #[test]
+    fn reads_every_supported_depth_from_a_fixture() {
+        let depths = [(8, false, 2.0 / 128.0), (16, false, 2.0 / 32_768.0), (24, false, 2.0 / 8_388_608.0), (32, true, 0.0)];
+        for (bits, is_float, tolerance) in depths {
+            let fixture = WavFixture { bits_per_sample: bits, is_float, frames: 4_800, ..WavFixture::default() };
+            let file = fixture.write();
+
+            let (samples, info) = read_wav_f32(file.path()).unwrap();
+
+            assert_eq!((info.channels, info.sample_rate, info.bits_per_sample), (2, 48_000, bits));
+            assert_eq!(info.is_float, is_float);
+            let expected = fixture.samples();
+            assert_eq!(samples.len(), expected.len());
+            assert!(
+                samples.iter().zip(&expected).all(|(a, b)| (a - b).abs() <= tolerance),
+                "{bits}-bit samples differ by more than {tolerance}"
+            );
+        }
+    }
+
+    #[test]
+    fn prefix_reads_only_the_requested_frames() {
+        let fixture = WavFixture::default();
+        let file = fixture.write();
+
+        let (samples, info) = read_wav_f32_prefix(file.path(), 0.1).unwrap();
+        assert_eq!(samples.len(), 4_800 * 2);
+        assert_eq!(info.data_size, 4_800 * 2 * 2);
+        let expected = &fixture.samples()[..samples.len()];
+        assert!(samples.iter().zip(expected).all(|(a, b)| (a - b).abs() <= 2.0 / 32_768.0));
+
+        // Asking for more than the file holds reads all of it
+        let (samples, _) = read_wav_f32_prefix(file.path(), f32::MAX).unwrap();
+        assert_eq!(samples.len(), fixture.frames * 2);
+    }
+
+    #[test]
+    fn rejects_unsupported_bit_depth() {
+        let result = read_wav_samples(&mut Cursor::new(wav_bytes(12, &[0; 4])), None);
+        assert!(matches!(
+            result,
+            Err(AppError::UnsupportedAudioFormat { bits: 12, is_float: false })
+        ));
+    }
+}
@@ -1,160 +1,1384 @@
+use std::path::Path;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use serde::Serialize;
 use tauri::{AppHandle, Emitter, State};
 
-use crate::audio::{self, SystemAudioHandle};
+use crate::audio::{
+    self, BitDepth, CaptureConfig, CaptureInfo, DenoiseIntensity, EmptyCapture, EnhanceOptions,
+    MicMix, ProcessLoopback, RecordingFormat, RingCapture, SplitOptions, SystemAudioHandle,
+};
 use crate::error::AppError;
-use crate::transcription::MoonshineEngine;
+use crate::progress::{NoProgress, ProgressReporter};
+use crate::settings::{self, CaptureSettings};
+use crate::transcription::{
+    self, DecodeStrategy, ExecutionProvider, HallucinationFilter, ModelVariant, MoonshineEngine,
+    Segment, SubtitleFormat, Transcript, TranscriptionSession,
+};
 use crate::AudioCaptureState;
+use crate::EnhanceState;
+use crate::LiveTranscriptionState;
+use crate::MemoryCaptureState;
+use crate::ModelDownloadState;
+use crate::RingCaptureState;
+use crate::TranscriptionProviderState;
 use crate::TranscriptionState;
 
+/// `format` is `"wav"` (default), `"flac"` or `"mp3"` (only in builds with
+/// the `mp3` feature); `bit_depth` is `"float32"` (default; 24-bit in FLAC,
+/// ignored by MP3) or `"pcm16"`. `bitrate_kbps` sets the MP3 bitrate
+/// (default 192; 8–320 in LAME's steps). `denoise_intensity` (0.0–1.0,
+/// default 0.0) applies RNNoise live while recording. `spectrum` turns on
+/// `audio-spectrum` events for a frequency visualizer (off by default).
+/// `emit_interval_ms` sets how often level events fire (default ~100 ms,
+/// minimum 20 ms); lower is smoother but sends more IPC traffic.
+/// `target_sample_rate` (8000–192000) resamples the recording when the
+/// device mixes at a different rate, e.g. 44100 from a 48 kHz device.
+/// `empty_capture` decides what a recording stopped before any audio
+/// arrived becomes: `"pad"` (default, 100 ms of silence) or `"reject"`
+/// (`RECORDING_TOO_SHORT`, no file).
+/// `buffer_bytes` sizes the WAV write buffer (default 256 KB, clamped to
+/// 4 KB–64 MB). `high_priority` registers the capture thread with MMCSS
+/// "Pro Audio" to cut glitches on loaded machines (off by default; it can
+/// starve other threads, and is skipped where MMCSS is unavailable). Omitted `device_id`, `bit_depth` and `denoise_intensity`
+/// fall back to the saved capture settings. `output_path` records straight
+/// to that file (its folder must exist; the format's extension is appended
+/// if missing) instead of a temp file.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)] // each one is a separate invoke argument
 pub async fn start_system_audio_capture(
     app: AppHandle,
     state: State<'_, AudioCaptureState>,
+    device_id: Option<String>,
+    bit_depth: Option<BitDepth>,
+    denoise_intensity: Option<f32>,
+    format: Option<RecordingFormat>,
+    spectrum: Option<bool>,
+    emit_interval_ms: Option<u32>,
+    target_sample_rate: Option<u32>,
+    empty_capture: Option<EmptyCapture>,
+    buffer_bytes: Option<usize>,
+    output_path: Option<String>,
+    bitrate_kbps: Option<u32>,
+    high_priority: Option<bool>,
+) -> Result<CaptureInfo, AppError> {
+    if let Some(rate) = target_sample_rate {
+        if !(8_000..=192_000).contains(&rate) {
+            return Err(AppError::AudioCapture(format!("Unsupported sample rate: {rate} Hz")));
+        }
+    }
+    validate_bitrate(bitrate_kbps)?;
+
+    let saved = settings::load(&app);
+    let config = CaptureConfig {
+        device_id: device_id.or(saved.device_id),
+        format: format.unwrap_or_default(),
+        bit_depth: bit_depth.unwrap_or(saved.bit_depth),
+        bitrate_kbps,
+        denoise_intensity: denoise_intensity.unwrap_or(saved.denoise_intensity).clamp(0.0, 1.0),
+        spectrum: spectrum.unwrap_or(false),
+        emit_interval_ms,
+        target_sample_rate,
+        empty_capture: empty_capture.unwrap_or_default(),
+        buffer_bytes: buffer_bytes.map(|b| b.clamp(4 * 1024, 64 * 1024 * 1024)),
+        high_priority: high_priority.unwrap_or(false),
+        ..CaptureConfig::default()
+    };
+    let output_path = output_path
+        .map(|path| validate_output_path(path, config.format))
+        .transpose()?;
+    start_capture(app, &state, config, output_path).await
+}
+
+/// Record system audio and the default microphone mixed into one WAV.
+/// Gains are linear multipliers applied to each source before summing.
+/// `echo_cancel` removes the speaker audio the mic picks up (off by default).
+#[tauri::command]
+pub async fn start_combined_capture(
+    app: AppHandle,
+    state: State<'_, AudioCaptureState>,
+    mic_gain: f32,
+    system_gain: f32,
+    echo_cancel: Option<bool>,
+) -> Result<CaptureInfo, AppError> {
+    let config = CaptureConfig {
+        mic_mix: Some(MicMix {
+            mic_gain: mic_gain.max(0.0),
+            system_gain: system_gain.max(0.0),
+            echo_cancel: echo_cancel.unwrap_or(false),
+        }),
+        ..CaptureConfig::default()
+    };
+    start_capture(app, &state, config, None).await
+}
+
+/// Record only the audio of process `pid` (Windows 10 version 2004 and
+/// later). `include_tree` (default on) also records the processes it
+/// started, which browsers need since tabs play from child processes.
+#[tauri::command]
+pub async fn start_process_audio_capture(
+    app: AppHandle,
+    state: State<'_, AudioCaptureState>,
+    pid: u32,
+    include_tree: Option<bool>,
+) -> Result<CaptureInfo, AppError> {
+    let config = CaptureConfig {
+        process: Some(ProcessLoopback { pid, include_tree: include_tree.unwrap_or(true) }),
+        ..CaptureConfig::default()
+    };
+    start_capture(app, &state, config, None).await
+}
+
+/// Last saved capture preferences, or the defaults if none were saved or the
+/// file is unreadable.
+#[tauri::command]
+pub async fn load_capture_settings(app: AppHandle) -> CaptureSettings {
+    settings::load(&app)
+}
+
+/// Remember capture preferences for the next launch.
+#[tauri::command]
+pub async fn save_capture_settings(
+    app: AppHandle,
+    settings: CaptureSettings,
+) -> Result<(), AppError> {
+    settings::save(&app, &settings)
+}
+
+/// Emitted whenever a file recording starts or stops, whoever triggered it.
+#[derive(Serialize, Clone)]
+pub struct RecordingStateEvent {
+    pub recording: bool,
+    /// The finished file, when a recording just stopped.
+    pub output_path: Option<String>,
+}
+
+/// Check that an MP3 bitrate is one LAME supports.
+fn validate_bitrate(bitrate_kbps: Option<u32>) -> Result<(), AppError> {
+    match bitrate_kbps {
+        Some(kbps) if !audio::MP3_BITRATES_KBPS.contains(&kbps) => {
+            Err(AppError::AudioCapture(format!("Unsupported MP3 bitrate: {kbps} kbps")))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Give `path` the extension of `format` if it lacks it, and check that its
+/// folder exists and isn't read-only.
+fn validate_output_path(path: String, format: RecordingFormat) -> Result<String, AppError> {
+    let mut path = std::path::PathBuf::from(path);
+    let has_extension = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(format.extension()));
+    if !has_extension {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(format.extension());
+        path.set_file_name(name);
+    }
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => return Err(AppError::AudioCapture("Output path needs a folder".into())),
+    };
+    let writable = std::fs::metadata(parent)
+        .is_ok_and(|meta| meta.is_dir() && !meta.permissions().readonly());
+    if !writable {
+        return Err(AppError::AudioCapture(format!(
+            "Output folder does not exist or is not writable: {}",
+            parent.display()
+        )));
+    }
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Start a file recording. Shared by the commands and the tray menu.
+/// `output_path` overrides the timestamped temp file.
+pub(crate) async fn start_capture(
+    app: AppHandle,
+    state: &AudioCaptureState,
+    config: CaptureConfig,
+    output_path: Option<String>,
+) -> Result<CaptureInfo, AppError> {
+    let state_inner = Arc::clone(&state.0);
+    let notify = app.clone();
+
+    let info = tauri::async_runtime::spawn_blocking(move || {
+        let mut capture_lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        if capture_lock.is_some() {
+            return Err(AppError::CaptureAlreadyRunning);
+        }
+
+        let output_path = output_path.unwrap_or_else(|| {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            std::env::temp_dir()
+                .join(format!("recogning_capture_{timestamp}.{}", config.format.extension()))
+                .to_string_lossy()
+                .to_string()
+        });
+
+        let handle = SystemAudioHandle::start(output_path, config, app)?;
+        let info = handle.info().clone();
+        *capture_lock = Some(handle);
+        Ok(info)
+    })
+    .await
+    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))??;
+
+    recording_state_changed(&notify, None);
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn stop_system_audio_capture(
+    app: AppHandle,
+    state: State<'_, AudioCaptureState>,
+) -> Result<String, AppError> {
+    stop_capture(&app, &state).await
+}
+
+/// Stop the file recording and return its path. Shared by the commands
+/// and the tray menu.
+pub(crate) async fn stop_capture(
+    app: &AppHandle,
+    state: &AudioCaptureState,
 ) -> Result<String, AppError> {
     let state_inner = Arc::clone(&state.0);
 
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let mut capture_lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        match capture_lock.take() {
+            Some(mut handle) => handle.stop(),
+            None => Err(AppError::NoCaptureRunning),
+        }
+    })
+    .await
+    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?;
+
+    // A failed stop still ends the session, so the tray must update either way
+    match &result {
+        Ok(path) => recording_state_changed(app, Some(path.clone())),
+        Err(AppError::NoCaptureRunning) => {}
+        Err(_) => recording_state_changed(app, None),
+    }
+    result
+}
+
+/// Stop the file recording and delete it instead of keeping it, for
+/// scrapping a take.
+#[tauri::command]
+pub async fn abort_system_audio_capture(
+    app: AppHandle,
+    state: State<'_, AudioCaptureState>,
+) -> Result<(), AppError> {
+    let state_inner = Arc::clone(&state.0);
+
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let mut capture_lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        match capture_lock.take() {
+            Some(mut handle) => handle.abort(),
+            None => Err(AppError::NoCaptureRunning),
+        }
+    })
+    .await
+    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?;
+
+    if !matches!(result, Err(AppError::NoCaptureRunning)) {
+        recording_state_changed(&app, None);
+    }
+    result
+}
+
+/// Sync the tray with `AudioCaptureState` and tell the frontend.
+fn recording_state_changed(app: &AppHandle, output_path: Option<String>) {
+    crate::tray::refresh(app);
+    let recording = crate::tray::is_recording(app);
+    let _ = app.emit("recording-state-changed", RecordingStateEvent { recording, output_path });
+}
+
+/// Capture system audio into memory instead of a temp WAV, for short clips
+/// that go straight to transcription. Stop with `stop_memory_capture`.
+#[tauri::command]
+pub async fn start_memory_capture(
+    app: AppHandle,
+    state: State<'_, MemoryCaptureState>,
+    device_id: Option<String>,
+) -> Result<CaptureInfo, AppError> {
+    let state_inner = Arc::clone(&state.0);
+    let config = CaptureConfig {
+        device_id,
+        ..CaptureConfig::default()
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut capture_lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        if capture_lock.is_some() {
+            return Err(AppError::CaptureAlreadyRunning);
+        }
+
+        let handle = SystemAudioHandle::start_memory(config, app)?;
+        let info = handle.info().clone();
+        *capture_lock = Some(handle);
+        Ok(info)
+    })
+    .await
+    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?
+}
+
+/// Stop a memory capture and return its audio as mono 16 kHz samples,
+/// ready to pass to `transcription_transcribe`.
+#[tauri::command]
+pub async fn stop_memory_capture(
+    state: State<'_, MemoryCaptureState>,
+) -> Result<Vec<f32>, AppError> {
+    let state_inner = Arc::clone(&state.0);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut capture_lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        match capture_lock.take() {
+            Some(mut handle) => handle.stop(),
+            None => Err(AppError::NoCaptureRunning),
+        }
+    })
+    .await
+    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?
+}
+
+/// Capture system audio continuously but keep only the last `seconds`
+/// (1–300) in memory, for "clip that". Save with `save_ring_clip`; stop
+/// with `stop_ring_capture`. Live denoise follows the saved settings.
+#[tauri::command]
+pub async fn start_ring_capture(
+    app: AppHandle,
+    state: State<'_, RingCaptureState>,
+    seconds: u32,
+    device_id: Option<String>,
+) -> Result<CaptureInfo, AppError> {
+    let state_inner = Arc::clone(&state.0);
+    let saved = settings::load(&app);
+    let config = CaptureConfig {
+        device_id: device_id.or(saved.device_id),
+        denoise_intensity: saved.denoise_intensity.clamp(0.0, 1.0),
+        ..CaptureConfig::default()
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut capture_lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        if capture_lock.is_some() {
+            return Err(AppError::CaptureAlreadyRunning);
+        }
+
+        let capture = RingCapture::start(config, app, seconds)?;
+        let info = capture.info().clone();
+        *capture_lock = Some(capture);
+        Ok(info)
+    })
+    .await
+    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?
+}
+
+/// Save the ring capture's current contents as a 32-bit float WAV at
+/// `output_path` (".wav" appended if missing) and return the path. The
+/// capture keeps running.
+#[tauri::command]
+pub async fn save_ring_clip(
+    state: State<'_, RingCaptureState>,
+    output_path: String,
+) -> Result<String, AppError> {
+    let state_inner = Arc::clone(&state.0);
+    let path = validate_output_path(output_path, RecordingFormat::Wav)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let capture_lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        let capture = capture_lock.as_ref().ok_or(AppError::NoCaptureRunning)?;
+        let duration_ms = capture.save_clip(&path)?;
+        eprintln!("[capture] Saved {duration_ms} ms clip to {path}");
+        Ok(path)
+    })
+    .await
+    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?
+}
+
+/// Stop the ring capture, discarding whatever it held.
+#[tauri::command]
+pub async fn stop_ring_capture(
+    state: State<'_, RingCaptureState>,
+) -> Result<(), AppError> {
+    let state_inner = Arc::clone(&state.0);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut capture_lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        match capture_lock.take() {
+            Some(capture) => capture.stop(),
+            None => Err(AppError::NoCaptureRunning),
+        }
+    })
+    .await
+    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?
+}
+
+/// Pause the active capture. With `keep_timeline` the paused span is
+/// recorded as silence; otherwise it is left out of the file.
+#[tauri::command]
+pub async fn pause_capture(
+    state: State<'_, AudioCaptureState>,
+    keep_timeline: Option<bool>,
+) -> Result<(), AppError> {
+    let lock = state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+    match lock.as_ref() {
+        Some(handle) => handle.pause(keep_timeline.unwrap_or(false)),
+        None => Err(AppError::NoCaptureRunning),
+    }
+}
+
+#[tauri::command]
+pub async fn resume_capture(
+    state: State<'_, AudioCaptureState>,
+) -> Result<(), AppError> {
+    let lock = state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+    match lock.as_ref() {
+        Some(handle) => handle.resume(),
+        None => Err(AppError::NoCaptureRunning),
+    }
+}
+
+/// Returned by `recording_status`.
+#[derive(Serialize)]
+pub struct RecordingStatus {
+    pub recording: bool,
+    /// Time since the recording started; 0 when idle.
+    pub elapsed_ms: u64,
+    pub output_path: Option<String>,
+}
+
+/// Current file recording, so the UI can restore its state and timer
+/// after a reload.
+#[tauri::command]
+pub async fn recording_status(
+    state: State<'_, AudioCaptureState>,
+) -> Result<RecordingStatus, AppError> {
+    let lock = state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+    Ok(match lock.as_ref() {
+        Some(handle) => RecordingStatus {
+            recording: true,
+            elapsed_ms: u64::try_from(handle.elapsed().as_millis()).unwrap_or(u64::MAX),
+            output_path: handle.output_path().map(str::to_string),
+        },
+        None => RecordingStatus {
+            recording: false,
+            elapsed_ms: 0,
+            output_path: None,
+        },
+    })
+}
+
+/// File name prefixes of the recordings, enhanced copies and previews this
+/// app writes to the temp dir.
+const TEMP_RECORDING_PREFIXES: &[&str] =
+    &["recogning_capture_", "recogning_enhanced_", "recogning_preview_"];
+
+/// A recording left in the temp dir, from `list_temp_recordings`.
+#[derive(Serialize)]
+pub struct TempRecording {
+    pub path: String,
+    pub size_bytes: u64,
+    /// Last modification, Unix time in milliseconds.
+    pub modified_ms: u64,
+}
+
+fn temp_recordings() -> Result<Vec<TempRecording>, AppError> {
+    let mut recordings = Vec::new();
+    for entry in std::fs::read_dir(std::env::temp_dir())? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !TEMP_RECORDING_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else { continue };
+        if !meta.is_file() {
+            continue;
+        }
+        let modified_ms = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_millis() as u64);
+        recordings.push(TempRecording {
+            path: entry.path().to_string_lossy().to_string(),
+            size_bytes: meta.len(),
+            modified_ms,
+        });
+    }
+    Ok(recordings)
+}
+
+/// Recordings and enhanced copies this app left in the temp dir.
+#[tauri::command]
+pub async fn list_temp_recordings() -> Result<Vec<TempRecording>, AppError> {
+    tauri::async_runtime::spawn_blocking(temp_recordings)
+        .await
+        .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?
+}
+
+/// Delete temp recordings last modified more than `older_than_ms` ago,
+/// never the file the running capture is writing. Returns the deleted paths.
+#[tauri::command]
+pub async fn cleanup_temp_recordings(
+    state: State<'_, AudioCaptureState>,
+    older_than_ms: u64,
+) -> Result<Vec<String>, AppError> {
+    let active = state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?
+        .as_ref()
+        .and_then(|handle| handle.output_path().map(str::to_string));
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+
+        let mut deleted = Vec::new();
+        for recording in temp_recordings()? {
+            if Some(&recording.path) == active.as_ref()
+                || now_ms.saturating_sub(recording.modified_ms) < older_than_ms
+            {
+                continue;
+            }
+            match std::fs::remove_file(&recording.path) {
+                Ok(()) => deleted.push(recording.path),
+                // Possibly open elsewhere; try again next time
+                Err(e) => eprintln!("[cleanup] Could not delete {}: {e}", recording.path),
+            }
+        }
+        Ok(deleted)
+    })
+    .await
+    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?
+}
+
+/// Rebind the global recording toggle (default `Ctrl+Shift+R`) to an
+/// accelerator such as `"Alt+F9"`. Invalid or already-taken accelerators
+/// are rejected and the previous binding stays active.
+#[tauri::command]
+pub async fn set_recording_hotkey(app: AppHandle, accelerator: String) -> Result<(), AppError> {
+    crate::hotkey::set(&app, &accelerator)
+}
+
+/// Payload of `operation-progress`, shared by model downloads, enhancement
+/// and transcription.
+#[derive(Serialize, Clone)]
+pub struct OperationProgressEvent {
+    /// `"model-download"`, `"enhance"` or `"transcribe"`.
+    pub operation: &'static str,
+    /// Step within the operation, e.g. the file being downloaded.
+    pub stage: String,
+    pub done: u64,
+    /// 0 when unknown.
+    pub total: u64,
+}
+
+/// Forwards an operation's progress to the frontend as `operation-progress`.
+struct EventProgress<'a> {
+    app: &'a AppHandle,
+    operation: &'static str,
+}
+
+impl ProgressReporter for EventProgress<'_> {
+    fn report(&self, stage: &str, done: u64, total: u64) {
+        let _ = self.app.emit("operation-progress", OperationProgressEvent {
+            operation: self.operation,
+            stage: stage.to_string(),
+            done,
+            total,
+        });
+    }
+}
+
+/// Returned by `enhance_audio`.
+#[derive(Serialize)]
+pub struct EnhanceResult {
+    pub output_path: String,
+    /// Intensity actually applied, after resolving a preset or clamping.
+    pub intensity: f32,
+}
+
+/// Denoise `input_path` into a new temp WAV, emitting `operation-progress`
+/// (operation `"enhance"`) along the way. Fields missing from `options` take their defaults
+/// (e.g. a 50 ms cosine fade). `options.method` picks RNNoise (default) or
+/// `{ "type": "spectralgate" }` for music and ambient audio;
+/// `options.intensity` is `"light"`, `"medium"`, `"strong"` or a number
+/// from 0.0 to 1.0. Stopped early by `cancel_enhance`.
+#[tauri::command]
+pub async fn enhance_audio(
+    app: AppHandle,
+    state: State<'_, EnhanceState>,
+    input_path: String,
+    options: EnhanceOptions,
+) -> Result<EnhanceResult, AppError> {
+    let cancel = Arc::clone(&state.0);
+    cancel.store(false, Ordering::Relaxed);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let temp_dir = std::env::temp_dir();
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let output_path = temp_dir
+            .join(format!("recogning_enhanced_{timestamp}.wav"))
+            .to_string_lossy()
+            .to_string();
+
+        let progress = EventProgress { app: &app, operation: "enhance" };
+        let result = audio::denoise_wav(&input_path, &output_path, &options, &progress, Some(&cancel));
+        if matches!(result, Err(AppError::EnhanceCancelled)) {
+            // Don't leave a partial WAV behind
+            let _ = std::fs::remove_file(&output_path);
+        }
+        result.map(|output_path| EnhanceResult { output_path, intensity: options.intensity.value() })
+    })
+    .await
+    .map_err(|e| AppError::AudioEnhance(format!("Task join: {e}")))?
+}
+
+/// Enhance only the first `seconds` of `input_path` (default 10, at most
+/// 60) into a short temp WAV, to audition `options` before processing a
+/// long recording with `enhance_audio`. Emits `operation-progress` with
+/// operation `"enhance"`.
+#[tauri::command]
+pub async fn enhance_preview(
+    app: AppHandle,
+    input_path: String,
+    options: EnhanceOptions,
+    seconds: Option<f32>,
+) -> Result<EnhanceResult, AppError> {
+    let seconds = seconds.unwrap_or(10.0).clamp(0.1, 60.0);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let output_path = std::env::temp_dir()
+            .join(format!("recogning_preview_{timestamp}.wav"))
+            .to_string_lossy()
+            .to_string();
+
+        let progress = EventProgress { app: &app, operation: "enhance" };
+        audio::denoise_preview(&input_path, &output_path, &options, seconds, &progress)
+            .map(|output_path| EnhanceResult { output_path, intensity: options.intensity.value() })
+    })
+    .await
+    .map_err(|e| AppError::AudioEnhance(format!("Task join: {e}")))?
+}
+
+/// Split a long recording at silent gaps into `output_dir/segment_NNN.wav`
+/// files, returning their paths (none for a silent input). Defaults: gaps of
+/// 700 ms below -45 dBFS, 150 ms of padding around each segment.
+#[tauri::command]
+pub async fn split_on_silence(
+    input_path: String,
+    output_dir: String,
+    min_silence_ms: Option<u32>,
+    threshold_db: Option<f32>,
+    padding_ms: Option<u32>,
+) -> Result<Vec<String>, AppError> {
+    let defaults = SplitOptions::default();
+    let options = SplitOptions {
+        min_silence_ms: min_silence_ms.unwrap_or(defaults.min_silence_ms),
+        threshold_db: threshold_db.unwrap_or(defaults.threshold_db),
+        padding_ms: padding_ms.unwrap_or(defaults.padding_ms),
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        audio::split_on_silence(&input_path, &output_dir, &options)
+    })
+    .await
+    .map_err(|e| AppError::AudioEnhance(format!("Task join: {e}")))?
+}
+
+/// Returned by `probe_audio_file`.
+#[derive(Serialize)]
+pub struct AudioFileInfo {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    pub is_float: bool,
+    pub duration_ms: u64,
+}
+
+impl From<audio::WavInfo> for AudioFileInfo {
+    fn from(info: audio::WavInfo) -> Self {
+        Self {
+            channels: info.channels,
+            sample_rate: info.sample_rate,
+            bits_per_sample: info.bits_per_sample,
+            is_float: info.is_float,
+            duration_ms: info.duration_ms(),
+        }
+    }
+}
+
+/// Format and duration of a WAV file, read from its header only.
+#[tauri::command]
+pub async fn probe_audio_file(path: String) -> Result<AudioFileInfo, AppError> {
+    tauri::async_runtime::spawn_blocking(move || audio::probe_wav(&path).map(AudioFileInfo::from))
+        .await
+        .map_err(|e| AppError::WavDecode(format!("Task join: {e}")))?
+}
+
+/// Join WAV files (e.g. pause/resume chunks) into one at `output_path`.
+/// The inputs must share a channel count and sample rate.
+#[tauri::command]
+pub async fn concat_audio_files(
+    inputs: Vec<String>,
+    output_path: String,
+) -> Result<String, AppError> {
+    tauri::async_runtime::spawn_blocking(move || audio::concat_wavs(&inputs, &output_path))
+        .await
+        .map_err(|e| AppError::WavEncode(format!("Task join: {e}")))?
+}
+
+/// Returned by `transcode_audio`.
+#[derive(Serialize)]
+pub struct TranscodeResult {
+    pub output_path: String,
+    pub size_bytes: u64,
+}
+
+/// Re-encode a WAV recording as `codec` (`"wav"`, `"flac"` or `"mp3"`) at
+/// `output_path`, keeping its channel count and sample rate. The codec's
+/// extension is appended if missing. `bitrate_kbps` applies to MP3
+/// (default 192), which needs a build with the `mp3` feature.
+#[tauri::command]
+pub async fn transcode_audio(
+    input_path: String,
+    output_path: String,
+    codec: RecordingFormat,
+    bitrate_kbps: Option<u32>,
+) -> Result<TranscodeResult, AppError> {
+    validate_bitrate(bitrate_kbps)?;
+    let output_path = validate_output_path(output_path, codec)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let size_bytes = audio::transcode(&input_path, &output_path, codec, bitrate_kbps)?;
+        Ok(TranscodeResult { output_path, size_bytes })
+    })
+    .await
+    .map_err(|e| AppError::WavEncode(format!("Task join: {e}")))?
+}
+
+/// Make a recording louder or quieter by `gain_db` without normalizing it.
+/// Peaks pushed past full scale are soft-clipped. Returns `output_path`.
+#[tauri::command]
+pub async fn adjust_gain(
+    input_path: String,
+    output_path: String,
+    gain_db: f32,
+) -> Result<String, AppError> {
+    if !gain_db.is_finite() {
+        return Err(AppError::AudioEnhance(format!("Invalid gain: {gain_db} dB")));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || audio::apply_gain(&input_path, &output_path, gain_db))
+        .await
+        .map_err(|e| AppError::AudioEnhance(format!("Task join: {e}")))?
+}
+
+/// Downmix a recording to a single channel, e.g. to halve a stereo voice
+/// recording. Returns `output_path`.
+#[tauri::command]
+pub async fn convert_to_mono(input_path: String, output_path: String) -> Result<String, AppError> {
+    tauri::async_runtime::spawn_blocking(move || audio::to_mono(&input_path, &output_path))
+        .await
+        .map_err(|e| AppError::AudioEnhance(format!("Task join: {e}")))?
+}
+
+/// Ask the running `enhance_audio` job to stop.
+#[tauri::command]
+pub async fn cancel_enhance(state: State<'_, EnhanceState>) -> Result<(), AppError> {
+    state.0.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn is_system_audio_available() -> bool {
+    tauri::async_runtime::spawn_blocking(audio::check_system_audio_available)
+        .await
+        .unwrap_or(false)
+}
+
+/// Availability plus the default output device's name and mix format, for
+/// a setup check. Opens no stream, so it is safe to call at any time.
+#[tauri::command]
+pub async fn system_audio_report() -> Result<audio::SystemAudioReport, AppError> {
+    tauri::async_runtime::spawn_blocking(audio::system_audio_report)
+        .await
+        .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))
+}
+
+#[derive(Serialize)]
+pub struct AudioOutputDevice {
+    pub id: String,
+    pub name: String,
+}
+
+#[tauri::command]
+pub async fn list_audio_output_devices() -> Result<Vec<AudioOutputDevice>, AppError> {
+    let devices = tauri::async_runtime::spawn_blocking(audio::list_output_devices)
+        .await
+        .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))??;
+
+    Ok(devices
+        .into_iter()
+        .map(|(id, name)| AudioOutputDevice { id, name })
+        .collect())
+}
+
+// ── Transcription commands ──────────────────────────────────────────
+
+#[derive(Serialize)]
+pub struct TranscriptionModelInfo {
+    pub loaded: bool,
+    pub cached: bool,
+    /// Variant currently loaded, if any.
+    pub variant: Option<ModelVariant>,
+    /// Provider the loaded model runs on, if any.
+    pub execution_provider: Option<ExecutionProvider>,
+    /// Folder of the loaded model's files; with no model loaded, the
+    /// download cache the default variant would load from.
+    pub model_dir: String,
+}
+
+/// `warmup` primes a freshly loaded model so the first transcription isn't
+/// slow; it adds a moment to the load.
+#[tauri::command]
+pub async fn transcription_load_model(
+    app: AppHandle,
+    state: State<'_, TranscriptionState>,
+    provider_state: State<'_, TranscriptionProviderState>,
+    download_state: State<'_, ModelDownloadState>,
+    warmup: Option<bool>,
+) -> Result<TranscriptionModelInfo, AppError> {
+    let warmup = warmup.unwrap_or(false);
+    let variant = ModelVariant::default();
+    load_model(app, &state, &provider_state, &download_state, variant, warmup).await
+}
+
+/// Load `"tiny"` or `"base"`, replacing a different variant if one is loaded.
+#[tauri::command]
+pub async fn transcription_load_model_variant(
+    app: AppHandle,
+    state: State<'_, TranscriptionState>,
+    provider_state: State<'_, TranscriptionProviderState>,
+    download_state: State<'_, ModelDownloadState>,
+    variant: String,
+    warmup: Option<bool>,
+) -> Result<TranscriptionModelInfo, AppError> {
+    let variant: ModelVariant = variant.parse()?;
+    let warmup = warmup.unwrap_or(false);
+    load_model(app, &state, &provider_state, &download_state, variant, warmup).await
+}
+
+/// Ask the download behind a running model load to stop. Files that
+/// finished downloading stay cached for the next load.
+#[tauri::command]
+pub async fn transcription_cancel_download(
+    state: State<'_, ModelDownloadState>,
+) -> Result<(), AppError> {
+    state.0.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Prime the loaded model so the next transcription starts fast; call it
+/// while the app is idle. Returns the warm-up time in ms.
+#[tauri::command]
+pub async fn transcription_warmup(state: State<'_, TranscriptionState>) -> Result<u64, AppError> {
+    let state_inner = Arc::clone(&state.0);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        match lock.as_mut() {
+            Some(engine) => Ok(engine.warmup()?.as_millis() as u64),
+            None => Err(AppError::ModelNotLoaded),
+        }
+    })
+    .await
+    .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
+}
+
+/// Load model files the user copied to `dir` instead of downloading them,
+/// replacing any loaded model.
+#[tauri::command]
+pub async fn transcription_load_local_model(
+    state: State<'_, TranscriptionState>,
+    provider_state: State<'_, TranscriptionProviderState>,
+    dir: String,
+) -> Result<TranscriptionModelInfo, AppError> {
+    let state_inner = Arc::clone(&state.0);
+    let provider = *provider_state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        // Free the current sessions before loading the new ones
+        *lock = None;
+
+        let engine = lock.insert(MoonshineEngine::load_from_dir(Path::new(&dir), provider)?);
+        Ok(TranscriptionModelInfo {
+            loaded: true,
+            cached: true,
+            variant: Some(engine.variant()),
+            execution_provider: Some(engine.provider()),
+            model_dir: engine.model_dir().to_string_lossy().to_string(),
+        })
+    })
+    .await
+    .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
+}
+
+/// Choose `"auto"`, `"cpu"`, `"directml"` or `"cuda"` for subsequent loads.
+/// A loaded model is unloaded when the choice changes, so the next
+/// `transcription_load_model` picks it up.
+#[tauri::command]
+pub async fn transcription_set_provider(
+    state: State<'_, TranscriptionState>,
+    provider_state: State<'_, TranscriptionProviderState>,
+    provider: String,
+) -> Result<(), AppError> {
+    let provider: ExecutionProvider = provider.parse()?;
+
+    let mut preferred = provider_state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+    if *preferred == provider {
+        return Ok(());
+    }
+    *preferred = provider;
+
+    let mut lock = state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+    *lock = None;
+    Ok(())
+}
+
+/// Parse an optional `"tiny"` / `"base"` argument, defaulting to base.
+fn parse_variant(variant: Option<&str>) -> Result<ModelVariant, AppError> {
+    variant.map_or(Ok(ModelVariant::default()), str::parse)
+}
+
+async fn load_model(
+    app: AppHandle,
+    state: &TranscriptionState,
+    provider_state: &TranscriptionProviderState,
+    download_state: &ModelDownloadState,
+    variant: ModelVariant,
+    warmup: bool,
+) -> Result<TranscriptionModelInfo, AppError> {
+    let state_inner = Arc::clone(&state.0);
+    let cancel = Arc::clone(&download_state.0);
+    cancel.store(false, Ordering::Relaxed);
+    let provider = *provider_state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        let loaded = |engine: &MoonshineEngine| TranscriptionModelInfo {
+            loaded: true,
+            cached: true,
+            variant: Some(variant),
+            execution_provider: Some(engine.provider()),
+            model_dir: engine.model_dir().to_string_lossy().to_string(),
+        };
+
+        // Already loaded
+        if let Some(engine) = lock.as_ref().filter(|engine| engine.variant() == variant) {
+            return Ok(loaded(engine));
+        }
+
+        // Free the other variant's sessions before loading this one
+        *lock = None;
+
+        let progress = EventProgress { app: &app, operation: "model-download" };
+        let engine = MoonshineEngine::download_and_load(variant, provider, warmup, &cancel, &progress)?;
+
+        Ok(loaded(lock.insert(engine)))
+    })
+    .await
+    .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
+}
+
+/// `strategy` defaults to greedy; `{ "type": "beam", "width": 4 }` enables beam search.
+/// `repetition_penalty` defaults to 1.1; 1.0 disables it.
+/// `hallucination` defaults to dropping repetitive output. `channels` marks
+/// `audio` as interleaved multi-channel so it is downmixed to mono first
+/// (default 1).
+#[tauri::command]
+#[allow(clippy::too_many_arguments)] // each one is a separate invoke argument
+pub async fn transcription_transcribe(
+    app: AppHandle,
+    state: State<'_, TranscriptionState>,
+    audio: Vec<f32>,
+    language: String,
+    vad_threshold: Option<f32>,
+    repetition_penalty: Option<f32>,
+    strategy: Option<DecodeStrategy>,
+    hallucination: Option<HallucinationFilter>,
+    channels: Option<u16>,
+) -> Result<Transcript, AppError> {
+    let state_inner = Arc::clone(&state.0);
+    let audio = match channels {
+        // Same rate in and out, so this only averages the channels
+        Some(ch) if ch > 1 => audio::resample_for_asr(&audio, 16_000, ch),
+        _ => audio,
+    };
+
     tauri::async_runtime::spawn_blocking(move || {
-        let mut capture_lock = state_inner
+        let mut lock = state_inner
             .lock()
             .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
 
-        if capture_lock.is_some() {
-            return Err(AppError::CaptureAlreadyRunning);
+        match lock.as_mut() {
+            Some(engine) => engine.transcribe_filtered(
+                &audio,
+                &language,
+                vad_threshold,
+                repetition_penalty,
+                strategy.unwrap_or_default(),
+                &hallucination.unwrap_or_default(),
+                &EventProgress { app: &app, operation: "transcribe" },
+            ),
+            None => Err(AppError::ModelNotLoaded),
         }
-
-        let temp_dir = std::env::temp_dir();
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-        let output_path = temp_dir
-            .join(format!("recogning_capture_{timestamp}.wav"))
-            .to_string_lossy()
-            .to_string();
-
-        let handle = SystemAudioHandle::start(output_path, app)?;
-        *capture_lock = Some(handle);
-        Ok("System audio capture started".to_string())
     })
     .await
-    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?
+    .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
 }
 
+/// Like `transcription_transcribe`, seeding the decoder with `prompt` (names,
+/// jargon, earlier context) to bias it toward that vocabulary. The prompt
+/// isn't part of the returned text; a very long one keeps its end.
 #[tauri::command]
-pub async fn stop_system_audio_capture(
-    state: State<'_, AudioCaptureState>,
-) -> Result<String, AppError> {
+#[allow(clippy::too_many_arguments)] // each one is a separate invoke argument
+pub async fn transcription_transcribe_prompted(
+    app: AppHandle,
+    state: State<'_, TranscriptionState>,
+    audio: Vec<f32>,
+    language: String,
+    prompt: String,
+    vad_threshold: Option<f32>,
+    repetition_penalty: Option<f32>,
+    strategy: Option<DecodeStrategy>,
+    hallucination: Option<HallucinationFilter>,
+) -> Result<Transcript, AppError> {
     let state_inner = Arc::clone(&state.0);
 
     tauri::async_runtime::spawn_blocking(move || {
-        let mut capture_lock = state_inner
+        let mut lock = state_inner
             .lock()
             .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
 
-        match capture_lock.take() {
-            Some(mut handle) => handle.stop(),
-            None => Err(AppError::NoCaptureRunning),
+        match lock.as_mut() {
+            Some(engine) => engine.transcribe_prompted(
+                &audio,
+                &language,
+                vad_threshold,
+                repetition_penalty,
+                strategy.unwrap_or_default(),
+                &hallucination.unwrap_or_default(),
+                Some(&prompt),
+                &EventProgress { app: &app, operation: "transcribe" },
+            ),
+            None => Err(AppError::ModelNotLoaded),
         }
     })
     .await
-    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?
+    .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
 }
 
+/// Transcribe a recorded WAV in one call: the file is read, downmixed and
+/// resampled to 16 kHz here instead of in the frontend.
 #[tauri::command]
-pub async fn enhance_audio(
-    input_path: String,
-    intensity: f32,
-    normalize: bool,
+pub async fn transcribe_wav_file(
+    app: AppHandle,
+    state: State<'_, TranscriptionState>,
+    path: String,
 ) -> Result<String, AppError> {
+    // Moonshine is English-only
+    transcribe_file(app, &state, path, "en".into()).await.map(|t| t.text)
+}
+
+/// Like `transcription_transcribe`, but loads the WAV on the Rust side so
+/// long recordings never cross the IPC bridge as float arrays.
+#[tauri::command]
+pub async fn transcription_transcribe_file(
+    app: AppHandle,
+    state: State<'_, TranscriptionState>,
+    path: String,
+    language: String,
+) -> Result<Transcript, AppError> {
+    transcribe_file(app, &state, path, language).await
+}
+
+async fn transcribe_file(
+    app: AppHandle,
+    state: &TranscriptionState,
+    path: String,
+    language: String,
+) -> Result<Transcript, AppError> {
+    let state_inner = Arc::clone(&state.0);
+
     tauri::async_runtime::spawn_blocking(move || {
-        let temp_dir = std::env::temp_dir();
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-        let output_path = temp_dir
-            .join(format!("recogning_enhanced_{timestamp}.wav"))
-            .to_string_lossy()
-            .to_string();
+        let (samples, info) = audio::read_wav_f32(&path)?;
+        let audio = audio::resample_for_asr(&samples, info.sample_rate, info.channels);
+
+        let mut lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
 
-        let intensity = intensity.clamp(0.0, 1.0);
-        audio::denoise_wav(&input_path, &output_path, intensity, normalize)
+        match lock.as_mut() {
+            Some(engine) => {
+                let progress = EventProgress { app: &app, operation: "transcribe" };
+                engine.transcribe(&audio, &language, None, None, &progress)
+            }
+            None => Err(AppError::ModelNotLoaded),
+        }
     })
     .await
-    .map_err(|e| AppError::AudioEnhance(format!("Task join: {e}")))?
+    .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
 }
 
-#[tauri::command]
-pub async fn is_system_audio_available() -> bool {
-    tauri::async_runtime::spawn_blocking(audio::check_system_audio_available)
-        .await
-        .unwrap_or(false)
+/// Longest recording `record_and_transcribe` makes.
+const MAX_RECORD_AND_TRANSCRIBE_MS: u64 = 10 * 60 * 1000;
+
+/// Returned by `record_and_transcribe`.
+#[derive(Serialize)]
+pub struct RecordedTranscript {
+    pub text: String,
+    /// The WAV that was transcribed: the denoised copy when denoising was
+    /// asked for, otherwise the recording itself.
+    pub wav_path: String,
 }
 
-// ── Transcription commands ──────────────────────────────────────────
+/// Record system audio for `duration_ms` (at most 10 minutes), denoise it
+/// when `denoise_intensity` is given (`"light"`, `"medium"`, `"strong"` or
+/// 0.0–1.0), and transcribe it, all in one call. The recording emits
+/// `audio-level` events and shows in the tray like any other; stopping it
+/// early transcribes what was recorded, aborting it fails the call. Fails
+/// before recording if no model is loaded.
+#[tauri::command]
+pub async fn record_and_transcribe(
+    app: AppHandle,
+    state: State<'_, AudioCaptureState>,
+    transcription: State<'_, TranscriptionState>,
+    duration_ms: u64,
+    denoise_intensity: Option<DenoiseIntensity>,
+) -> Result<RecordedTranscript, AppError> {
+    if !(1..=MAX_RECORD_AND_TRANSCRIBE_MS).contains(&duration_ms) {
+        return Err(AppError::AudioCapture(format!(
+            "Recording length must be 1–{MAX_RECORD_AND_TRANSCRIBE_MS} ms, got {duration_ms}"
+        )));
+    }
+    let loaded = transcription.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?
+        .is_some();
+    if !loaded {
+        return Err(AppError::ModelNotLoaded);
+    }
 
-#[derive(Serialize, Clone)]
-pub struct ModelDownloadProgress {
-    pub file_index: usize,
-    pub total_files: usize,
-    pub bytes_downloaded: u64,
-    pub total_bytes: u64,
+    // Denoising happens afterwards on the whole file, not live
+    let saved = settings::load(&app);
+    let config = CaptureConfig {
+        device_id: saved.device_id,
+        bit_depth: saved.bit_depth,
+        max_duration_ms: Some(duration_ms),
+        ..CaptureConfig::default()
+    };
+    let recording = start_capture(app.clone(), &state, config, None)
+        .await?
+        .output_path
+        .unwrap_or_default();
+
+    let state_inner = Arc::clone(&state.0);
+    let ours = recording.clone();
+    let stopped_here = tauri::async_runtime::spawn_blocking(move || {
+        let is_ours = |handle: &SystemAudioHandle| handle.output_path() == Some(ours.as_str());
+        let finished = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?
+            .as_ref()
+            .filter(|handle| is_ours(handle))
+            .map(SystemAudioHandle::finished);
+        if let Some(finished) = finished {
+            finished.wait();
+        }
+
+        // Stopped early or aborted by the user, who may have started
+        // another recording since: leave whatever is in the state alone
+        let mut capture_lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+        match capture_lock.take_if(|handle| is_ours(handle)) {
+            Some(mut handle) => handle.stop().map(Some),
+            None => Ok(None),
+        }
+    })
+    .await
+    .map_err(|e| AppError::AudioCapture(format!("Task join: {e}")))?;
+
+    match stopped_here {
+        Ok(Some(_)) => recording_state_changed(&app, Some(recording.clone())),
+        Ok(None) if Path::new(&recording).exists() => {}
+        Ok(None) => return Err(AppError::AudioCapture("The recording was aborted".into())),
+        Err(e) => {
+            recording_state_changed(&app, None);
+            return Err(e);
+        }
+    }
+
+    let wav_path = match denoise_intensity {
+        Some(intensity) => {
+            let app = app.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let path = Path::new(&recording);
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let output_path = path
+                    .with_file_name(format!("{stem}_enhanced.wav"))
+                    .to_string_lossy()
+                    .to_string();
+                let options = EnhanceOptions { intensity, ..EnhanceOptions::default() };
+                let progress = EventProgress { app: &app, operation: "enhance" };
+                audio::denoise_wav(&recording, &output_path, &options, &progress, None)
+            })
+            .await
+            .map_err(|e| AppError::AudioEnhance(format!("Task join: {e}")))??
+        }
+        None => recording,
+    };
+
+    // Moonshine is English-only
+    let transcript = transcribe_file(app, &transcription, wav_path.clone(), "en".into()).await?;
+    Ok(RecordedTranscript { text: transcript.text, wav_path })
 }
 
-#[derive(Serialize)]
-pub struct TranscriptionModelInfo {
-    pub loaded: bool,
-    pub cached: bool,
+/// Result for one file of `transcription_transcribe_batch`, also sent as the
+/// `batch-progress` event.
+#[derive(Clone, Serialize)]
+pub struct BatchTranscription {
+    /// Position of the file in the batch, from 0.
+    pub index: usize,
+    pub total: usize,
+    pub path: String,
+    /// Empty when the file failed.
+    pub text: String,
+    /// Why the file failed, if it did; the batch carries on regardless.
+    pub error: Option<String>,
 }
 
+/// Transcribe many WAV files with the loaded model, emitting
+/// `batch-progress` after each one. A file that fails is recorded with its
+/// error instead of stopping the batch.
 #[tauri::command]
-pub async fn transcription_load_model(
+pub async fn transcription_transcribe_batch(
     app: AppHandle,
     state: State<'_, TranscriptionState>,
-) -> Result<TranscriptionModelInfo, AppError> {
+    paths: Vec<String>,
+    language: String,
+) -> Result<Vec<BatchTranscription>, AppError> {
     let state_inner = Arc::clone(&state.0);
 
     tauri::async_runtime::spawn_blocking(move || {
         let mut lock = state_inner
             .lock()
             .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+        let engine = lock.as_mut().ok_or(AppError::ModelNotLoaded)?;
 
-        // Already loaded
-        if lock.is_some() {
-            return Ok(TranscriptionModelInfo {
-                loaded: true,
-                cached: true,
-            });
-        }
-
-        let engine = MoonshineEngine::download_and_load(|file_idx, total, downloaded, total_bytes| {
-            let _ = app.emit("model-download-progress", ModelDownloadProgress {
-                file_index: file_idx,
-                total_files: total,
-                bytes_downloaded: downloaded,
-                total_bytes,
-            });
-        })?;
+        let total = paths.len();
+        let results = paths
+            .into_iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let transcript = audio::read_wav_f32(&path).and_then(|(samples, info)| {
+                    let audio = audio::resample_for_asr(&samples, info.sample_rate, info.channels);
+                    engine.transcribe(&audio, &language, None, None, &NoProgress)
+                });
+                let (text, error) = match transcript {
+                    Ok(transcript) => (transcript.text, None),
+                    Err(e) => (String::new(), Some(e.to_string())),
+                };
 
-        *lock = Some(engine);
-
-        Ok(TranscriptionModelInfo {
-            loaded: true,
-            cached: true,
-        })
+                let result = BatchTranscription { index, total, path, text, error };
+                let _ = app.emit("batch-progress", result.clone());
+                result
+            })
+            .collect();
+        Ok(results)
     })
     .await
     .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
 }
 
+/// Transcribe in ~30 s windows, emitting `transcription-segment` as each
+/// one is decoded. Returns all segments once done.
 #[tauri::command]
-pub async fn transcription_transcribe(
+pub async fn transcription_transcribe_streaming(
+    app: AppHandle,
     state: State<'_, TranscriptionState>,
     audio: Vec<f32>,
     language: String,
-) -> Result<String, AppError> {
+) -> Result<Vec<Segment>, AppError> {
     let state_inner = Arc::clone(&state.0);
 
     tauri::async_runtime::spawn_blocking(move || {
@@ -163,7 +1387,9 @@ pub async fn transcription_transcribe(
             .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
 
         match lock.as_mut() {
-            Some(engine) => engine.transcribe(&audio, &language),
+            Some(engine) => engine.transcribe_streaming(&audio, &language, |segment| {
+                let _ = app.emit("transcription-segment", segment);
+            }),
             None => Err(AppError::ModelNotLoaded),
         }
     })
@@ -171,6 +1397,243 @@ pub async fn transcription_transcribe(
     .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
 }
 
+/// Open a live captioning session for `language`, replacing any open one.
+/// Feed it 16 kHz audio with `live_transcription_feed`.
+#[tauri::command]
+pub async fn live_transcription_start(
+    state: State<'_, TranscriptionState>,
+    live: State<'_, LiveTranscriptionState>,
+    language: String,
+) -> Result<(), AppError> {
+    let state_inner = Arc::clone(&state.0);
+    let live_inner = Arc::clone(&live.0);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut session = live_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+        let lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        let engine = lock.as_ref().ok_or(AppError::ModelNotLoaded)?;
+        *session = Some(TranscriptionSession::new(engine, &language)?);
+        Ok(())
+    })
+    .await
+    .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
+}
+
+/// Add 16 kHz audio to the live session. Returns the newly finalized text
+/// once about 5 s has accumulated, `None` until then. `channels` marks
+/// `samples` as interleaved multi-channel to downmix first (default 1).
+#[tauri::command]
+pub async fn live_transcription_feed(
+    state: State<'_, TranscriptionState>,
+    live: State<'_, LiveTranscriptionState>,
+    samples: Vec<f32>,
+    channels: Option<u16>,
+) -> Result<Option<String>, AppError> {
+    let state_inner = Arc::clone(&state.0);
+    let live_inner = Arc::clone(&live.0);
+    let samples = match channels {
+        Some(ch) if ch > 1 => audio::resample_for_asr(&samples, 16_000, ch),
+        _ => samples,
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut session = live_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+        let mut lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        let session = session.as_mut().ok_or(AppError::LiveTranscriptionNotRunning)?;
+        let engine = lock.as_mut().ok_or(AppError::ModelNotLoaded)?;
+        session.feed(engine, &samples)
+    })
+    .await
+    .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
+}
+
+/// Close the live session, returning the text of the audio still buffered.
+#[tauri::command]
+pub async fn live_transcription_stop(
+    state: State<'_, TranscriptionState>,
+    live: State<'_, LiveTranscriptionState>,
+) -> Result<Option<String>, AppError> {
+    let state_inner = Arc::clone(&state.0);
+    let live_inner = Arc::clone(&live.0);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let session = live_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?
+            .take()
+            .ok_or(AppError::LiveTranscriptionNotRunning)?;
+        let mut lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        let engine = lock.as_mut().ok_or(AppError::ModelNotLoaded)?;
+        session.finish(engine)
+    })
+    .await
+    .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
+}
+
+/// Re-hash the cached files of `variant` (default `"base"`). A corrupt
+/// file is deleted and reported so the next load downloads it again.
+#[tauri::command]
+pub async fn transcription_verify_cache(variant: Option<String>) -> Result<(), AppError> {
+    let variant = parse_variant(variant.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        transcription::ModelManager::new(variant)?.verify_cache()
+    })
+    .await
+    .map_err(|e| AppError::ModelDownload(format!("Task join: {e}")))?
+}
+
+/// Directory where the files of `variant` (default `"base"`) are cached.
+#[tauri::command]
+pub async fn transcription_cache_dir(variant: Option<String>) -> Result<String, AppError> {
+    let variant = parse_variant(variant.as_deref())?;
+    let manager = transcription::ModelManager::new(variant)?;
+    Ok(manager.cache_dir().to_string_lossy().to_string())
+}
+
+/// Bytes still to download before `variant` (default `"base"`) can load.
+#[tauri::command]
+pub async fn transcription_download_size(variant: Option<String>) -> Result<u64, AppError> {
+    let variant = parse_variant(variant.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        transcription::ModelManager::new(variant)?.estimated_download_size()
+    })
+    .await
+    .map_err(|e| AppError::ModelDownload(format!("Task join: {e}")))?
+}
+
+/// Pre-flight check that the download for `variant` (default `"base"`)
+/// fits on the models drive.
+#[tauri::command]
+pub async fn transcription_check_disk_space(
+    variant: Option<String>,
+) -> Result<transcription::DiskSpace, AppError> {
+    let variant = parse_variant(variant.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        transcription::ModelManager::new(variant)?.check_disk_space()
+    })
+    .await
+    .map_err(|e| AppError::ModelDownload(format!("Task join: {e}")))?
+}
+
+/// Delete the cached files of `variant` (default `"base"`) and the ONNX
+/// Runtime DLL. Refused while a model is loaded. Returns bytes freed.
+#[tauri::command]
+pub async fn transcription_clear_cache(
+    state: State<'_, TranscriptionState>,
+    variant: Option<String>,
+) -> Result<u64, AppError> {
+    let variant = parse_variant(variant.as_deref())?;
+    let state_inner = Arc::clone(&state.0);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        // Hold the lock so a load can't start while files are being removed
+        let lock = state_inner
+            .lock()
+            .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+        if lock.is_some() {
+            return Err(AppError::ModelInUse);
+        }
+
+        transcription::ModelManager::new(variant)?.clear_cache()
+    })
+    .await
+    .map_err(|e| AppError::ModelDownload(format!("Task join: {e}")))?
+}
+
+/// Set the RMS voice-activity threshold used by later transcriptions.
+/// `0.0` disables VAD so quiet audio is always transcribed.
+#[tauri::command]
+pub async fn transcription_set_vad_threshold(
+    state: State<'_, TranscriptionState>,
+    threshold: f32,
+) -> Result<(), AppError> {
+    let mut lock = state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+    match lock.as_mut() {
+        Some(engine) => {
+            engine.set_vad_threshold(threshold);
+            Ok(())
+        }
+        None => Err(AppError::ModelNotLoaded),
+    }
+}
+
+/// Have VAD measure `window_ms` windows and skip audio unless at least
+/// `min_voiced_fraction` (0–1) of them reach the threshold, so isolated
+/// clicks don't trigger a decode. `0.0` accepts any voiced window.
+#[tauri::command]
+pub async fn transcription_set_vad_window(
+    state: State<'_, TranscriptionState>,
+    window_ms: u32,
+    min_voiced_fraction: f32,
+) -> Result<(), AppError> {
+    let mut lock = state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+    match lock.as_mut() {
+        Some(engine) => {
+            engine.set_vad_window(window_ms, min_voiced_fraction);
+            Ok(())
+        }
+        None => Err(AppError::ModelNotLoaded),
+    }
+}
+
+/// Zero quiet passages below this RMS before later transcriptions encode
+/// them, to reduce hallucinations; `0.0` (the default) turns the gate off.
+#[tauri::command]
+pub async fn transcription_set_noise_gate(
+    state: State<'_, TranscriptionState>,
+    threshold: f32,
+) -> Result<(), AppError> {
+    let mut lock = state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
+
+    match lock.as_mut() {
+        Some(engine) => {
+            engine.set_noise_gate(threshold);
+            Ok(())
+        }
+        None => Err(AppError::ModelNotLoaded),
+    }
+}
+
+/// Write `segments` to `output_path` as `"srt"` or `"vtt"` and return the path.
+#[tauri::command]
+pub async fn export_subtitles(
+    segments: Vec<Segment>,
+    format: SubtitleFormat,
+    output_path: String,
+) -> Result<String, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        transcription::write_subtitles(&segments, format, &output_path)?;
+        Ok(output_path)
+    })
+    .await
+    .map_err(|e| AppError::Transcription(format!("Task join: {e}")))?
+}
+
 #[tauri::command]
 pub async fn transcription_unload_model(
     state: State<'_, TranscriptionState>,
@@ -191,11 +1654,22 @@ pub async fn transcription_model_status(
         .lock()
         .map_err(|e| AppError::LockPoisoned(e.to_string()))?;
 
-    let loaded = lock.is_some();
+    let variant = lock.as_ref().map(MoonshineEngine::variant);
 
-    let cached = crate::transcription::ModelManager::new()
-        .map(|m| m.is_cached())
-        .unwrap_or(false);
+    let manager = transcription::ModelManager::new(variant.unwrap_or_default()).ok();
+    let cached = manager.as_ref().is_some_and(|m| m.is_cached());
+    let model_dir = match (lock.as_ref(), &manager) {
+        (Some(engine), _) => engine.model_dir().to_string_lossy().to_string(),
+        (None, Some(manager)) => manager.cache_dir().to_string_lossy().to_string(),
+        (None, None) => String::new(),
+    };
 
-    Ok(TranscriptionModelInfo { loaded, cached })
+    let execution_provider = lock.as_ref().map(MoonshineEngine::provider);
+    Ok(TranscriptionModelInfo {
+        loaded: variant.is_some(),
+        cached,
+        variant,
+        execution_provider,
+        model_dir,
+    })
 }
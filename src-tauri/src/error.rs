@@ -15,9 +15,18 @@ pub enum AppError {
     #[error("Capture already stopped")]
     CaptureAlreadyStopped,
 
+    #[error("Capture is already paused")]
+    CaptureAlreadyPaused,
+
+    #[error("Capture is not paused")]
+    CaptureNotPaused,
+
     #[error("Audio capture thread panicked")]
     CaptureThreadPanicked,
 
+    #[error("Recording too short: no audio was captured")]
+    RecordingTooShort,
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -27,9 +36,27 @@ pub enum AppError {
     #[error("WAV encoding error: {0}")]
     WavEncode(String),
 
+    #[error("WAV decoding error: {0}")]
+    WavDecode(String),
+
+    #[error("FLAC encoding error: {0}")]
+    FlacEncode(String),
+
+    #[error("MP3 encoding error: {0}")]
+    Mp3Encode(String),
+
+    #[error("Unsupported audio format: {bits}-bit {}", if *is_float { "float" } else { "PCM" })]
+    UnsupportedAudioFormat { bits: u16, is_float: bool },
+
+    #[error("Audio data is not frame-aligned: {data_size} bytes with {block_align}-byte frames")]
+    MisalignedAudioData { data_size: u32, block_align: u16 },
+
     #[error("Audio enhancement error: {0}")]
     AudioEnhance(String),
 
+    #[error("Audio enhancement was cancelled")]
+    EnhanceCancelled,
+
     #[error("Transcription error: {0}")]
     Transcription(String),
 
@@ -38,6 +65,27 @@ pub enum AppError {
 
     #[error("Model not loaded")]
     ModelNotLoaded,
+
+    #[error("Model not loaded: {0}")]
+    ModelFilesMissing(String),
+
+    #[error("ONNX Runtime is unavailable: {0}")]
+    OrtRuntimeUnavailable(String),
+
+    #[error("Model files are damaged: {0}")]
+    ModelCorrupt(String),
+
+    #[error("A model is loaded; unload it first")]
+    ModelInUse,
+
+    #[error("No live transcription is running")]
+    LiveTranscriptionNotRunning,
+
+    #[error("Invalid hotkey: {0}")]
+    InvalidHotkey(String),
+
+    #[error("Settings error: {0}")]
+    Settings(String),
 }
 
 impl AppError {
@@ -48,14 +96,43 @@ impl AppError {
             Self::CaptureAlreadyRunning => "CAPTURE_ALREADY_RUNNING",
             Self::NoCaptureRunning => "NO_CAPTURE_RUNNING",
             Self::CaptureAlreadyStopped => "CAPTURE_ALREADY_STOPPED",
+            Self::CaptureAlreadyPaused => "CAPTURE_ALREADY_PAUSED",
+            Self::CaptureNotPaused => "CAPTURE_NOT_PAUSED",
             Self::CaptureThreadPanicked => "CAPTURE_THREAD_PANICKED",
+            Self::RecordingTooShort => "RECORDING_TOO_SHORT",
             Self::Io(_) => "IO_ERROR",
             Self::AudioCapture(_) => "AUDIO_CAPTURE_ERROR",
             Self::WavEncode(_) => "WAV_ENCODE_ERROR",
+            Self::WavDecode(_) => "WAV_DECODE_ERROR",
+            Self::FlacEncode(_) => "FLAC_ENCODE_ERROR",
+            Self::Mp3Encode(_) => "MP3_ENCODE_ERROR",
+            Self::UnsupportedAudioFormat { .. } => "UNSUPPORTED_AUDIO_FORMAT",
+            Self::MisalignedAudioData { .. } => "MISALIGNED_AUDIO_DATA",
             Self::AudioEnhance(_) => "AUDIO_ENHANCE_ERROR",
+            Self::EnhanceCancelled => "ENHANCE_CANCELLED",
             Self::Transcription(_) => "TRANSCRIPTION_ERROR",
             Self::ModelDownload(_) => "MODEL_DOWNLOAD_ERROR",
             Self::ModelNotLoaded => "MODEL_NOT_LOADED",
+            Self::ModelFilesMissing(_) => "MODEL_FILES_MISSING",
+            Self::OrtRuntimeUnavailable(_) => "ORT_RUNTIME_UNAVAILABLE",
+            Self::ModelCorrupt(_) => "MODEL_CORRUPT",
+            Self::ModelInUse => "MODEL_IN_USE",
+            Self::LiveTranscriptionNotRunning => "LIVE_TRANSCRIPTION_NOT_RUNNING",
+            Self::InvalidHotkey(_) => "INVALID_HOTKEY",
+            Self::Settings(_) => "SETTINGS_ERROR",
+        }
+    }
+
+    /// What the user can do about the error, for the codes that have a fix.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::OrtRuntimeUnavailable(_) => {
+                Some("Load the model again to re-download ONNX Runtime; for a local model, put onnxruntime.dll next to its files.")
+            }
+            Self::ModelCorrupt(_) => {
+                Some("Clear the model cache, then load the model again to re-download it.")
+            }
+            _ => None,
         }
     }
 }
@@ -66,9 +143,10 @@ impl Serialize for AppError {
         S: serde::ser::Serializer,
     {
         use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("AppError", 2)?;
+        let mut state = serializer.serialize_struct("AppError", 3)?;
         state.serialize_field("code", self.code())?;
         state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("hint", &self.hint())?;
         state.end()
     }
 }
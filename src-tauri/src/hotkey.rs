@@ -0,0 +1,99 @@
+use serde::Serialize;
+use tauri::{App, AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::error::AppError;
+use crate::{tray, HotkeyState};
+
+/// Toggles recording until rebound with `set_recording_hotkey`.
+pub const DEFAULT_HOTKEY: &str = "Ctrl+Shift+R";
+
+/// The registered recording toggle.
+pub struct Binding {
+    pub shortcut: Shortcut,
+    /// As the user wrote it, for events and messages.
+    pub accelerator: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct HotkeyTriggeredEvent {
+    pub accelerator: String,
+    /// `"start"` or `"stop"`.
+    pub action: &'static str,
+}
+
+/// Install the global-shortcut plugin and bind the default toggle. A
+/// default already taken by another app is logged, not fatal: recording
+/// still works from the UI and the tray.
+pub fn setup(app: &App) -> Result<(), Box<dyn std::error::Error>> {
+    app.handle().plugin(
+        tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(on_shortcut)
+            .build(),
+    )?;
+
+    if let Err(e) = set(app.handle(), DEFAULT_HOTKEY) {
+        eprintln!("[hotkey] {e}");
+    }
+    Ok(())
+}
+
+fn on_shortcut(app: &AppHandle, _shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+
+    let action = if tray::is_recording(app) {
+        tray::stop_recording(app);
+        "stop"
+    } else {
+        tray::start_recording(app);
+        "start"
+    };
+
+    let accelerator = app
+        .state::<HotkeyState>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|binding| binding.as_ref().map(|b| b.accelerator.clone()))
+        .unwrap_or_default();
+    let _ = app.emit("hotkey-triggered", HotkeyTriggeredEvent { accelerator, action });
+}
+
+/// Bind the recording toggle to `accelerator` (e.g. `"Ctrl+Shift+R"`),
+/// replacing the previous binding. If the accelerator is invalid or can't
+/// be registered, the previous binding stays active.
+pub fn set(app: &AppHandle, accelerator: &str) -> Result<(), AppError> {
+    let shortcut: Shortcut = accelerator
+        .parse()
+        .map_err(|e| AppError::InvalidHotkey(format!("{accelerator}: {e}")))?;
+
+    let state = app.state::<HotkeyState>();
+    let current = state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))?
+        .as_ref()
+        .map(|binding| binding.shortcut);
+    if current == Some(shortcut) {
+        return Ok(());
+    }
+
+    // Not holding the lock here: registering may wait on the event loop,
+    // which runs `on_shortcut`
+    let shortcuts = app.global_shortcut();
+    shortcuts.register(shortcut).map_err(|e| {
+        AppError::InvalidHotkey(format!("Could not register {accelerator}: {e}"))
+    })?;
+    if let Some(old) = current {
+        let _ = shortcuts.unregister(old);
+    }
+
+    *state.0
+        .lock()
+        .map_err(|e| AppError::LockPoisoned(e.to_string()))? = Some(Binding {
+        shortcut,
+        accelerator: accelerator.to_string(),
+    });
+    Ok(())
+}
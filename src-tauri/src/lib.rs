@@ -1,35 +1,109 @@
 mod audio;
 mod commands;
 mod error;
+mod hotkey;
+mod progress;
+mod settings;
 mod transcription;
 mod tray;
 
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
 pub struct AudioCaptureState(pub Arc<Mutex<Option<audio::SystemAudioHandle>>>);
+pub struct MemoryCaptureState(pub Arc<Mutex<Option<audio::SystemAudioHandle<Vec<f32>>>>>);
+/// "Record the last N seconds" capture, saved on demand with `save_ring_clip`.
+pub struct RingCaptureState(pub Arc<Mutex<Option<audio::RingCapture>>>);
 pub struct TranscriptionState(pub Arc<Mutex<Option<transcription::MoonshineEngine>>>);
+/// Open live captioning session; it transcribes with the loaded model.
+pub struct LiveTranscriptionState(pub Arc<Mutex<Option<transcription::TranscriptionSession>>>);
+/// Execution provider used the next time a model is loaded.
+pub struct TranscriptionProviderState(pub Arc<Mutex<transcription::ExecutionProvider>>);
+/// Global shortcut that toggles file recording, once registered.
+pub struct HotkeyState(pub Arc<Mutex<Option<hotkey::Binding>>>);
+/// Cancel flag for the running `enhance_audio` job.
+pub struct EnhanceState(pub Arc<AtomicBool>);
+/// Cancel flag for the model download behind a running model load.
+pub struct ModelDownloadState(pub Arc<AtomicBool>);
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
             tray::setup(app)?;
+            hotkey::setup(app)?;
             Ok(())
         })
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
         .manage(AudioCaptureState(Arc::new(Mutex::new(None))))
+        .manage(MemoryCaptureState(Arc::new(Mutex::new(None))))
+        .manage(RingCaptureState(Arc::new(Mutex::new(None))))
         .manage(TranscriptionState(Arc::new(Mutex::new(None))))
+        .manage(LiveTranscriptionState(Arc::new(Mutex::new(None))))
+        .manage(TranscriptionProviderState(Arc::new(Mutex::new(Default::default()))))
+        .manage(EnhanceState(Arc::new(AtomicBool::new(false))))
+        .manage(ModelDownloadState(Arc::new(AtomicBool::new(false))))
+        .manage(HotkeyState(Arc::new(Mutex::new(None))))
         .invoke_handler(tauri::generate_handler![
             commands::start_system_audio_capture,
+            commands::start_combined_capture,
+            commands::start_process_audio_capture,
             commands::stop_system_audio_capture,
+            commands::abort_system_audio_capture,
+            commands::start_memory_capture,
+            commands::stop_memory_capture,
+            commands::start_ring_capture,
+            commands::save_ring_clip,
+            commands::stop_ring_capture,
+            commands::pause_capture,
+            commands::resume_capture,
+            commands::recording_status,
+            commands::set_recording_hotkey,
             commands::is_system_audio_available,
+            commands::system_audio_report,
+            commands::list_temp_recordings,
+            commands::cleanup_temp_recordings,
+            commands::load_capture_settings,
+            commands::save_capture_settings,
+            commands::list_audio_output_devices,
             commands::enhance_audio,
+            commands::enhance_preview,
+            commands::probe_audio_file,
+            commands::split_on_silence,
+            commands::concat_audio_files,
+            commands::transcode_audio,
+            commands::adjust_gain,
+            commands::convert_to_mono,
+            commands::cancel_enhance,
             commands::transcription_load_model,
+            commands::transcription_load_model_variant,
+            commands::transcription_cancel_download,
+            commands::transcription_load_local_model,
+            commands::transcription_set_provider,
             commands::transcription_transcribe,
+            commands::transcription_transcribe_prompted,
+            commands::transcription_warmup,
+            commands::transcription_transcribe_batch,
+            commands::transcribe_wav_file,
+            commands::transcription_transcribe_file,
+            commands::transcription_transcribe_streaming,
+            commands::record_and_transcribe,
+            commands::live_transcription_start,
+            commands::live_transcription_feed,
+            commands::live_transcription_stop,
+            commands::export_subtitles,
+            commands::transcription_set_vad_threshold,
+            commands::transcription_set_vad_window,
+            commands::transcription_set_noise_gate,
             commands::transcription_unload_model,
             commands::transcription_model_status,
+            commands::transcription_verify_cache,
+            commands::transcription_clear_cache,
+            commands::transcription_download_size,
+            commands::transcription_check_disk_space,
+            commands::transcription_cache_dir,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,21 @@
+/// Receives progress from a long-running operation (model download,
+/// enhancement, transcription). `Sync` because downloads report from
+/// several worker threads at once.
+pub trait ProgressReporter: Sync {
+    /// `done` of `total` units of `stage` are complete. Units are whatever
+    /// the stage counts (bytes, frames, windows); `total` is 0 when unknown.
+    fn report(&self, stage: &str, done: u64, total: u64);
+}
+
+/// Discards every report, for callers that don't show progress.
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {
+    fn report(&self, _stage: &str, _done: u64, _total: u64) {}
+}
+
+impl<F: Fn(&str, u64, u64) + Sync> ProgressReporter for F {
+    fn report(&self, stage: &str, done: u64, total: u64) {
+        self(stage, done, total)
+    }
+}
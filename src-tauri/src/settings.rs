@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::audio::BitDepth;
+use crate::error::AppError;
+
+const SETTINGS_FILE: &str = "capture_settings.json";
+
+/// Capture preferences remembered between launches. Missing fields take
+/// their defaults, so older files keep loading as fields are added.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureSettings {
+    /// Render endpoint last recorded from; `None` is the default device.
+    pub device_id: Option<String>,
+    pub bit_depth: BitDepth,
+    /// Live RNNoise strength, 0.0–1.0.
+    pub denoise_intensity: f32,
+    /// Normalize when enhancing a finished recording.
+    pub normalize: bool,
+}
+
+fn settings_path(app: &AppHandle) -> Result<std::path::PathBuf, AppError> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::Settings(format!("App data directory: {e}")))?;
+    Ok(dir.join(SETTINGS_FILE))
+}
+
+/// Saved settings, or the built-in defaults when the file is missing or
+/// unreadable (a corrupt file is logged and ignored).
+pub fn load(app: &AppHandle) -> CaptureSettings {
+    let Ok(path) = settings_path(app) else {
+        return CaptureSettings::default();
+    };
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return CaptureSettings::default();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        eprintln!("[settings] Ignoring corrupt {}: {e}", path.display());
+        CaptureSettings::default()
+    })
+}
+
+/// Write `settings` via a temp file and rename, so a crash mid-write never
+/// leaves a truncated file behind.
+pub fn save(app: &AppHandle, settings: &CaptureSettings) -> Result<(), AppError> {
+    let path = settings_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| AppError::Settings(format!("Serialize settings: {e}")))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
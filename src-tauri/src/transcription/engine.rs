@@ -1,10 +1,17 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
 
+use ort::ep::{self, ExecutionProvider as _, ExecutionProviderDispatch};
+use ort::ErrorCode;
+use ort::session::builder::SessionBuilder;
 use ort::session::Session;
 use ort::value::Value;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 use crate::error::AppError;
-use super::model_manager::{ModelManager, ModelPaths};
+use crate::progress::ProgressReporter;
+use super::model_manager::{ModelManager, ModelPaths, ModelVariant};
 
 /// Moonshine model config extracted from config.json.
 struct MoonshineConfig {
@@ -17,6 +24,15 @@ struct MoonshineConfig {
 }
 
 impl MoonshineConfig {
+    /// Moonshine tiny has a 288-wide hidden state, base 416.
+    fn variant(&self) -> ModelVariant {
+        if self.hidden_size < 416 {
+            ModelVariant::Tiny
+        } else {
+            ModelVariant::Base
+        }
+    }
+
     fn dim_kv(&self) -> usize {
         self.hidden_size / self.decoder_num_key_value_heads
     }
@@ -50,7 +66,285 @@ impl MoonshineConfig {
     }
 }
 
+/// Moonshine expects 16 kHz mono input.
+pub(super) const SAMPLE_RATE: usize = 16_000;
+
+/// Longest audio `transcribe` hands the encoder at once; longer input is
+/// decoded in back-to-back windows of this length.
+const MAX_WINDOW_SECS: usize = 30;
+
+/// Most prompt tokens fed to the decoder; longer prompts keep their end.
+const MAX_PROMPT_TOKENS: usize = 224;
+
+/// Window length for `transcribe_streaming`. Moonshine degrades on very long
+/// inputs, and shorter windows give the UI text sooner.
+const STREAM_WINDOW_SECS: usize = 30;
+
+/// Overlap between consecutive windows so words cut at a boundary are heard
+/// whole in at least one of them.
+const STREAM_OVERLAP_SECS: usize = 2;
+
+/// Default divisor for the logits of already generated tokens, which keeps
+/// the decoder from looping on a phrase.
+pub const DEFAULT_REPETITION_PENALTY: f32 = 1.1;
+
+/// Default RMS level below which audio is treated as silence and skipped.
+pub const DEFAULT_VAD_THRESHOLD: f32 = 0.015;
+
+/// Default length of the windows VAD measures separately.
+pub const DEFAULT_VAD_WINDOW_MS: u32 = 100;
+
+/// Default share of VAD windows that must reach the threshold, so a single
+/// click or knock in otherwise silent audio isn't decoded.
+pub const DEFAULT_VAD_VOICED_FRACTION: f32 = 0.02;
+
+/// Model files smaller than this can't hold a Moonshine graph: they're
+/// truncated downloads or saved error pages.
+const MIN_MODEL_BYTES: u64 = 64 * 1024;
+
+/// A transcribed span of audio. Offsets are seconds from the start of the input.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Segment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// Text of a transcription and the language it was decoded as.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Transcript {
+    pub text: String,
+    /// ISO 639-1 code, e.g. `"en"`.
+    pub language: String,
+    /// Set under `HallucinationPolicy::Flag` when the text looks like a
+    /// repetition loop rather than speech.
+    pub suspected_hallucination: bool,
+    /// Why `text` is empty, if it is.
+    pub status: TranscriptStatus,
+}
+
+/// Outcome of a transcription, so an empty text can be explained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptStatus {
+    /// Speech was decoded (the text may still be empty).
+    Ok,
+    /// VAD found no speech, so nothing was decoded.
+    Silence,
+    /// Everything decoded was dropped as a hallucination.
+    HallucinationFiltered,
+}
+
+/// What to do with output that looks hallucinated (repetitive phrases).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HallucinationPolicy {
+    /// Discard it, returning empty text for that window.
+    #[default]
+    Drop,
+    /// Keep it, but set `suspected_hallucination` on the result.
+    Flag,
+    /// Keep it without checking.
+    Keep,
+}
+
+/// Hallucination filter settings. Deserialized from the frontend
+/// (camelCase); missing fields take their defaults.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HallucinationFilter {
+    pub policy: HallucinationPolicy,
+    /// Text whose distinct-word share is below this is suspect.
+    pub min_unique_ratio: f64,
+    /// Text repeating any 3-word phrase this many times is suspect.
+    pub max_ngram_repeats: u32,
+}
+
+impl Default for HallucinationFilter {
+    fn default() -> Self {
+        Self {
+            policy: HallucinationPolicy::Drop,
+            min_unique_ratio: 0.25,
+            max_ngram_repeats: 3,
+        }
+    }
+}
+
+impl HallucinationFilter {
+    fn is_hallucination(&self, text: &str) -> bool {
+        self.policy != HallucinationPolicy::Keep
+            && is_hallucination(text, self.min_unique_ratio, self.max_ngram_repeats)
+    }
+}
+
+/// ONNX Runtime execution provider to run the model on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionProvider {
+    /// First GPU provider that initializes, otherwise CPU.
+    #[default]
+    Auto,
+    Cpu,
+    /// DirectX 12 GPUs on Windows.
+    DirectMl,
+    /// NVIDIA GPUs; needs a CUDA-enabled ONNX Runtime.
+    Cuda,
+}
+
+impl ExecutionProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Cpu => "cpu",
+            Self::DirectMl => "directml",
+            Self::Cuda => "cuda",
+        }
+    }
+
+    /// GPU providers to try, in order, before falling back to CPU.
+    fn candidates(self) -> &'static [Self] {
+        match self {
+            Self::Auto => &[Self::DirectMl, Self::Cuda],
+            Self::Cpu => &[],
+            Self::DirectMl => &[Self::DirectMl],
+            Self::Cuda => &[Self::Cuda],
+        }
+    }
+
+    /// ORT dispatch for this provider, or `None` if the loaded ONNX Runtime
+    /// wasn't built with it.
+    fn dispatch(self) -> Option<ExecutionProviderDispatch> {
+        let (available, dispatch) = match self {
+            Self::DirectMl => {
+                let ep = ep::DirectML::default();
+                (ep.is_available(), ep.build())
+            }
+            Self::Cuda => {
+                let ep = ep::CUDA::default();
+                (ep.is_available(), ep.build())
+            }
+            Self::Auto | Self::Cpu => return None,
+        };
+        available.unwrap_or(false).then(|| dispatch.error_on_failure())
+    }
+}
+
+impl FromStr for ExecutionProvider {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "cpu" => Ok(Self::Cpu),
+            "directml" => Ok(Self::DirectMl),
+            "cuda" => Ok(Self::Cuda),
+            _ => Err(AppError::Transcription(format!("Unknown execution provider: {s}"))),
+        }
+    }
+}
+
+fn session_builder() -> Result<SessionBuilder, AppError> {
+    Session::builder()
+        .map_err(|e| AppError::Transcription(format!("ORT session builder error: {e}")))?
+        .with_intra_threads(4)
+        .map_err(|e| AppError::Transcription(format!("ORT thread config error: {e}")))
+}
+
+/// Create a session for the model at `path` on the first of `provider`'s
+/// candidates that registers, falling back to CPU. Returns the provider used.
+fn build_session(
+    path: &Path,
+    provider: ExecutionProvider,
+    what: &str,
+) -> Result<(Session, ExecutionProvider), AppError> {
+    for &candidate in provider.candidates() {
+        let Some(dispatch) = candidate.dispatch() else {
+            eprintln!("[transcription] {} not available in this ONNX Runtime", candidate.as_str());
+            continue;
+        };
+
+        let mut builder = session_builder()?;
+        if candidate == ExecutionProvider::DirectMl {
+            // DirectML doesn't support memory patterns or parallel execution
+            builder = builder
+                .with_memory_pattern(false)
+                .and_then(|b| b.with_parallel_execution(false))
+                .map_err(|e| AppError::Transcription(format!("ORT DirectML config error: {e}")))?;
+        }
+
+        match builder
+            .with_execution_providers([dispatch])
+            .and_then(|b| b.commit_from_file(path))
+        {
+            Ok(session) => return Ok((session, candidate)),
+            Err(e) => eprintln!("[transcription] {} failed for {what}, trying next: {e}", candidate.as_str()),
+        }
+    }
+
+    let session = session_builder()?.commit_from_file(path).map_err(|e| {
+        let file_len = std::fs::metadata(path).map(|m| m.len()).ok();
+        let detail = format!("Failed to load {what} from {}: {e}", path.display());
+        load_error(e.code(), e.message(), file_len, detail)
+    })?;
+    Ok((session, ExecutionProvider::Cpu))
+}
+
+/// Pick the error for a model that ORT refused, so the user is told whether
+/// to fetch the runtime or the model again. `file_len` is `None` when the
+/// file can't be read at all.
+fn load_error(code: ErrorCode, message: &str, file_len: Option<u64>, detail: String) -> AppError {
+    let message = message.to_ascii_lowercase();
+    if code == ErrorCode::NoSuchFile || file_len.is_none() {
+        AppError::ModelFilesMissing(detail)
+    } else if file_len < Some(MIN_MODEL_BYTES)
+        || code == ErrorCode::InvalidProtobuf
+        || message.contains("protobuf")
+    {
+        AppError::ModelCorrupt(detail)
+    } else if message.contains("opset") || message.contains("ir version") {
+        // The model needs operators newer than the runtime that was loaded
+        AppError::OrtRuntimeUnavailable(detail)
+    } else {
+        AppError::Transcription(detail)
+    }
+}
+
+/// Load the ONNX Runtime DLL at `path` up front, so a missing or
+/// incompatible DLL is an error here instead of a panic on first use.
+/// Only the first successful load counts for the life of the process.
+#[cfg(target_os = "windows")]
+fn load_runtime(path: &Path) -> Result<(), AppError> {
+    ort::init_from(path)
+        .map(|_environment| ())
+        .map_err(|e| AppError::OrtRuntimeUnavailable(e.to_string()))
+}
+
+/// How the decoder chooses tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DecodeStrategy {
+    /// Most likely token at every step — fastest.
+    #[default]
+    Greedy,
+    /// Beam search over `width` hypotheses; slower, but steadier on hard audio.
+    Beam { width: usize },
+}
+
+/// Encoder hidden states: shape + flat data.
+struct EncoderOutput {
+    shape: Vec<i64>,
+    data: Vec<f32>,
+}
+
+/// A partial transcript kept by beam search, with its own KV cache.
+struct Hypothesis {
+    tokens: Vec<i64>,
+    log_prob: f32,
+    kv_cache: Vec<KvEntry>,
+}
+
 /// Named KV cache entry: shape + flat data.
+#[derive(Clone)]
 struct KvEntry {
     name: String,
     shape: Vec<i64>,
@@ -62,26 +356,38 @@ pub struct MoonshineEngine {
     decoder_session: Session,
     tokenizer: tokenizers::Tokenizer,
     config: MoonshineConfig,
+    variant: ModelVariant,
+    /// Provider the sessions run on.
+    provider: ExecutionProvider,
+    /// Folder the model files were loaded from.
+    model_dir: PathBuf,
+    /// RMS voice-activity threshold; `0.0` disables VAD.
+    vad_threshold: f32,
+    /// Length of the windows VAD measures separately.
+    vad_window_ms: u32,
+    /// Share of VAD windows (0–1) that must reach `vad_threshold`.
+    vad_voiced_fraction: f32,
+    /// RMS below which 10 ms blocks are zeroed before encoding; `0.0`
+    /// (the default) disables the gate.
+    noise_gate: f32,
 }
 
 impl MoonshineEngine {
     /// Load the Moonshine model from cached ONNX files.
-    pub fn load(paths: &ModelPaths) -> Result<Self, AppError> {
+    ///
+    /// Both sessions try `provider` first and fall back to CPU if it can't
+    /// be registered; [`provider`](Self::provider) reports what was used.
+    pub fn load(
+        paths: &ModelPaths,
+        variant: ModelVariant,
+        provider: ExecutionProvider,
+    ) -> Result<Self, AppError> {
         let config = MoonshineConfig::from_json(&paths.config)?;
 
-        let encoder_session = Session::builder()
-            .map_err(|e| AppError::Transcription(format!("ORT session builder error: {e}")))?
-            .with_intra_threads(4)
-            .map_err(|e| AppError::Transcription(format!("ORT thread config error: {e}")))?
-            .commit_from_file(&paths.encoder)
-            .map_err(|e| AppError::Transcription(format!("Failed to load encoder: {e}")))?;
-
-        let decoder_session = Session::builder()
-            .map_err(|e| AppError::Transcription(format!("ORT session builder error: {e}")))?
-            .with_intra_threads(4)
-            .map_err(|e| AppError::Transcription(format!("ORT thread config error: {e}")))?
-            .commit_from_file(&paths.decoder)
-            .map_err(|e| AppError::Transcription(format!("Failed to load decoder: {e}")))?;
+        let (encoder_session, encoder_provider) = build_session(&paths.encoder, provider, "encoder")?;
+        // The decoder goes wherever the encoder ended up, so `Auto` doesn't probe twice
+        let (decoder_session, provider) = build_session(&paths.decoder, encoder_provider, "decoder")?;
+        eprintln!("[transcription] {variant:?} model running on {}", provider.as_str());
 
         let tokenizer = tokenizers::Tokenizer::from_file(&paths.tokenizer)
             .map_err(|e| AppError::Transcription(format!("Failed to load tokenizer: {e}")))?;
@@ -91,46 +397,382 @@ impl MoonshineEngine {
             decoder_session,
             tokenizer,
             config,
+            variant,
+            provider,
+            model_dir: paths.dir.clone(),
+            vad_threshold: DEFAULT_VAD_THRESHOLD,
+            vad_window_ms: DEFAULT_VAD_WINDOW_MS,
+            vad_voiced_fraction: DEFAULT_VAD_VOICED_FRACTION,
+            noise_gate: 0.0,
         })
     }
 
-    /// Download model if needed and load it.
-    pub fn download_and_load<F>(on_progress: F) -> Result<Self, AppError>
-    where
-        F: Fn(usize, usize, u64, u64),
-    {
-        let manager = ModelManager::new()?;
+    /// Download `variant` if needed and load it, then [`warmup`](Self::warmup)
+    /// when asked. A failed warm-up is logged; the model is still returned.
+    /// Setting `cancel` aborts the download; `progress` follows it as
+    /// [`ModelManager::download`] describes.
+    pub fn download_and_load(
+        variant: ModelVariant,
+        provider: ExecutionProvider,
+        warmup: bool,
+        cancel: &AtomicBool,
+        progress: &dyn ProgressReporter,
+    ) -> Result<Self, AppError> {
+        let manager = ModelManager::new(variant)?;
 
-        // Ensure ONNX Runtime DLL is available (load-dynamic requires it at runtime)
-        #[cfg(all(target_os = "windows", target_arch = "x86_64"))]
-        {
-            let dll_path = manager.ensure_onnx_runtime_dll()?;
-            std::env::set_var("ORT_DYLIB_PATH", &dll_path);
+        // Also fetches the ONNX Runtime DLL, which load-dynamic needs at runtime
+        let paths = manager.download(cancel, progress)?;
+
+        #[cfg(target_os = "windows")]
+        if let Err(e) = load_runtime(&manager.ort_dll_path()) {
+            // `download` only fetches a DLL that's missing, so drop the one
+            // that won't load and the next attempt gets a fresh copy
+            let _ = std::fs::remove_file(manager.ort_dll_path());
+            return Err(e);
+        }
+
+        let mut engine = Self::load(&paths, variant, provider)?;
+        if warmup {
+            if let Err(e) = engine.warmup() {
+                eprintln!("[transcription] Warm-up failed: {e}");
+            }
         }
+        Ok(engine)
+    }
+
+    /// Load a model the user placed in `dir` (same layout as the
+    /// HuggingFace repo), without touching the network. On Windows the
+    /// ONNX Runtime DLL must be in `dir` too. The variant is read from
+    /// the model's config.
+    pub fn load_from_dir(dir: &Path, provider: ExecutionProvider) -> Result<Self, AppError> {
+        let paths = ModelPaths::from_local_dir(dir)?;
+
+        #[cfg(target_os = "windows")]
+        load_runtime(&ModelPaths::local_ort_dll(dir))?;
+
+        let variant = MoonshineConfig::from_json(&paths.config)?.variant();
+        Self::load(&paths, variant, provider)
+    }
+
+    pub fn variant(&self) -> ModelVariant {
+        self.variant
+    }
+
+    /// Provider the model actually initialized on (never `Auto`).
+    pub fn provider(&self) -> ExecutionProvider {
+        self.provider
+    }
+
+    /// Folder the model files were loaded from: the download cache, or the
+    /// folder given to [`load_from_dir`](Self::load_from_dir).
+    pub fn model_dir(&self) -> &Path {
+        &self.model_dir
+    }
+
+    /// Run one encode + decode over a second of a faint tone so ONNX Runtime
+    /// allocates its buffers now rather than on the first real request.
+    /// Returns how long it took.
+    pub fn warmup(&mut self) -> Result<std::time::Duration, AppError> {
+        const WARMUP_SECS: usize = 1;
+
+        // 440 Hz at -60 dBFS: non-silent input of the right shape; VAD is bypassed
+        let tone: Vec<f32> = (0..WARMUP_SECS * SAMPLE_RATE)
+            .map(|i| 0.001 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect();
+
+        let started = std::time::Instant::now();
+        self.decode_window(&tone, DecodeStrategy::Greedy, DEFAULT_REPETITION_PENALTY, &[])?;
+        let elapsed = started.elapsed();
+        eprintln!("[transcription] Warm-up took {} ms", elapsed.as_millis());
+        Ok(elapsed)
+    }
+
+    pub fn vad_threshold(&self) -> f32 {
+        self.vad_threshold
+    }
+
+    /// Set the RMS level below which audio is skipped as silence.
+    /// `0.0` disables VAD so even very quiet audio is transcribed.
+    pub fn set_vad_threshold(&mut self, threshold: f32) {
+        self.vad_threshold = threshold.max(0.0);
+    }
+
+    /// VAD window length in milliseconds and the share of windows that must
+    /// be voiced.
+    pub fn vad_window(&self) -> (u32, f32) {
+        (self.vad_window_ms, self.vad_voiced_fraction)
+    }
+
+    /// Measure VAD over `window_ms` windows (at least 10 ms) and require
+    /// `min_voiced_fraction` (0–1) of them to reach the threshold. Audio with
+    /// any voiced window passes when the fraction is `0.0`.
+    pub fn set_vad_window(&mut self, window_ms: u32, min_voiced_fraction: f32) {
+        self.vad_window_ms = window_ms.max(10);
+        self.vad_voiced_fraction = min_voiced_fraction.clamp(0.0, 1.0);
+    }
+
+    /// Zero quiet passages (10 ms blocks with RMS below `threshold`) before
+    /// encoding, which keeps the decoder from hallucinating on near-silence.
+    /// `0.0` disables the gate.
+    pub fn set_noise_gate(&mut self, threshold: f32) {
+        self.noise_gate = threshold.max(0.0);
+    }
+
+    /// Transcribe raw PCM audio (f32, 16kHz, mono) with greedy decoding.
+    ///
+    /// `language` is an ISO 639-1 code (a region suffix like `en-US` is
+    /// ignored); `""` or `"auto"` picks the model's default. Languages the
+    /// model can't transcribe are rejected rather than decoded as English.
+    ///
+    /// Audio longer than 30 s is split into consecutive windows whose texts
+    /// are joined, so memory and decoder length stay bounded.
+    ///
+    /// `vad_threshold` overrides the engine's threshold for this call only
+    /// (`Some(0.0)` disables VAD). `repetition_penalty` divides the logits of
+    /// tokens already generated (default [`DEFAULT_REPETITION_PENALTY`];
+    /// `Some(1.0)` disables it).
+    ///
+    /// `progress` follows stage `"transcribe"`, counting decoded windows.
+    pub fn transcribe(
+        &mut self,
+        audio: &[f32],
+        language: &str,
+        vad_threshold: Option<f32>,
+        repetition_penalty: Option<f32>,
+        progress: &dyn ProgressReporter,
+    ) -> Result<Transcript, AppError> {
+        let strategy = DecodeStrategy::Greedy;
+        self.transcribe_with(audio, language, vad_threshold, repetition_penalty, strategy, progress)
+    }
 
-        let paths = if manager.is_cached() {
-            manager.get_paths()?
+    /// Like [`transcribe`](Self::transcribe), choosing how tokens are decoded.
+    pub fn transcribe_with(
+        &mut self,
+        audio: &[f32],
+        language: &str,
+        vad_threshold: Option<f32>,
+        repetition_penalty: Option<f32>,
+        strategy: DecodeStrategy,
+        progress: &dyn ProgressReporter,
+    ) -> Result<Transcript, AppError> {
+        let filter = HallucinationFilter::default();
+        self.transcribe_filtered(audio, language, vad_threshold, repetition_penalty, strategy, &filter, progress)
+    }
+
+    /// Like [`transcribe_with`](Self::transcribe_with), choosing how
+    /// repetitive, likely hallucinated output is handled.
+    #[allow(clippy::too_many_arguments)]
+    pub fn transcribe_filtered(
+        &mut self,
+        audio: &[f32],
+        language: &str,
+        vad_threshold: Option<f32>,
+        repetition_penalty: Option<f32>,
+        strategy: DecodeStrategy,
+        filter: &HallucinationFilter,
+        progress: &dyn ProgressReporter,
+    ) -> Result<Transcript, AppError> {
+        self.transcribe_prompted(
+            audio,
+            language,
+            vad_threshold,
+            repetition_penalty,
+            strategy,
+            filter,
+            None,
+            progress,
+        )
+    }
+
+    /// Like [`transcribe_filtered`](Self::transcribe_filtered), seeding the
+    /// decoder with `prompt` (e.g. names and jargon the audio contains) to
+    /// bias it toward that vocabulary. The prompt never appears in the
+    /// output; one longer than [`MAX_PROMPT_TOKENS`] or the model's context
+    /// keeps its end.
+    #[allow(clippy::too_many_arguments)]
+    pub fn transcribe_prompted(
+        &mut self,
+        audio: &[f32],
+        language: &str,
+        vad_threshold: Option<f32>,
+        repetition_penalty: Option<f32>,
+        strategy: DecodeStrategy,
+        filter: &HallucinationFilter,
+        prompt: Option<&str>,
+        progress: &dyn ProgressReporter,
+    ) -> Result<Transcript, AppError> {
+        let language = resolve_language(self.variant, language)?.to_string();
+        let prompt = match prompt.map(str::trim).filter(|p| !p.is_empty()) {
+            Some(prompt) => self.prompt_tokens(prompt)?,
+            None => Vec::new(),
+        };
+
+        let threshold = vad_threshold.map_or(self.vad_threshold, |t| t.max(0.0));
+        let penalty = repetition_penalty.map_or(DEFAULT_REPETITION_PENALTY, |p| p.max(1.0));
+        let gated;
+        let audio = if self.noise_gate > 0.0 {
+            gated = noise_gate(audio, self.noise_gate);
+            &gated
         } else {
-            manager.download(on_progress)?
+            audio
         };
 
-        Self::load(&paths)
+        let mut texts = Vec::new();
+        let mut suspected_hallucination = false;
+        let mut voiced = false;
+        let mut dropped = false;
+        let window_len = MAX_WINDOW_SECS * SAMPLE_RATE;
+        let total_windows = audio.len().div_ceil(window_len) as u64;
+        // Silent windows are skipped, so silent input yields an empty text
+        for (i, window) in audio.chunks(window_len).enumerate() {
+            progress.report("transcribe", i as u64, total_windows);
+            if !self.has_voice_activity(window, threshold) {
+                continue;
+            }
+            voiced = true;
+            let text = self.decode_window(window, strategy, penalty, &prompt)?;
+            if filter.is_hallucination(&text) {
+                if filter.policy == HallucinationPolicy::Drop {
+                    dropped = true;
+                    continue;
+                }
+                suspected_hallucination = true;
+            }
+            if !text.is_empty() {
+                texts.push(text);
+            }
+        }
+
+        progress.report("transcribe", total_windows, total_windows);
+
+        let status = match (voiced, texts.is_empty() && dropped) {
+            (false, _) => TranscriptStatus::Silence,
+            (true, true) => TranscriptStatus::HallucinationFiltered,
+            (true, false) => TranscriptStatus::Ok,
+        };
+        Ok(Transcript { text: texts.join(" "), language, suspected_hallucination, status })
     }
 
-    /// Transcribe raw PCM audio (f32, 16kHz, mono).
-    pub fn transcribe(&mut self, audio: &[f32], _language: &str) -> Result<String, AppError> {
-        if audio.is_empty() {
-            return Ok(String::new());
+    /// Transcribe long audio (f32, 16kHz, mono) in overlapping windows,
+    /// calling `on_segment` as soon as each window is decoded.
+    ///
+    /// Windows without voice activity are skipped. Words repeated across
+    /// the overlap are dropped from the later segment.
+    pub fn transcribe_streaming<F>(
+        &mut self,
+        audio: &[f32],
+        language: &str,
+        mut on_segment: F,
+    ) -> Result<Vec<Segment>, AppError>
+    where
+        F: FnMut(&Segment),
+    {
+        resolve_language(self.variant, language)?;
+
+        let window = STREAM_WINDOW_SECS * SAMPLE_RATE;
+        let step = window - STREAM_OVERLAP_SECS * SAMPLE_RATE;
+
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut start = 0;
+
+        while start < audio.len() {
+            let end = (start + window).min(audio.len());
+            let chunk = &audio[start..end];
+
+            let text = self.caption_window(chunk)?;
+            let text = match segments.last() {
+                Some(prev) => strip_overlap(&prev.text, &text),
+                None => text,
+            };
+
+            if !text.is_empty() {
+                let segment = Segment {
+                    start: start as f64 / SAMPLE_RATE as f64,
+                    end: end as f64 / SAMPLE_RATE as f64,
+                    text,
+                };
+                on_segment(&segment);
+                segments.push(segment);
+            }
+
+            if end == audio.len() {
+                break;
+            }
+            start += step;
         }
 
-        if !has_voice_activity(audio) {
+        Ok(segments)
+    }
+
+    /// Token IDs of `prompt`, at most [`MAX_PROMPT_TOKENS`] from its end.
+    fn prompt_tokens(&self, prompt: &str) -> Result<Vec<i64>, AppError> {
+        let encoding = self.tokenizer
+            .encode(prompt, false)
+            .map_err(|e| AppError::Transcription(format!("Tokenizer encode error: {e}")))?;
+        let ids = encoding.get_ids();
+        let keep = &ids[ids.len().saturating_sub(MAX_PROMPT_TOKENS)..];
+        Ok(keep.iter().map(|&id| id as i64).collect())
+    }
+
+    /// Greedy-decode one window of live or streamed audio. Silent windows
+    /// and likely hallucinations come back empty.
+    pub(super) fn caption_window(&mut self, audio: &[f32]) -> Result<String, AppError> {
+        if !self.has_voice_activity(audio, self.vad_threshold) {
             return Ok(String::new());
         }
+        let text = self.decode_window(audio, DecodeStrategy::Greedy, DEFAULT_REPETITION_PENALTY, &[])?;
+        if HallucinationFilter::default().is_hallucination(&text) {
+            return Ok(String::new());
+        }
+        Ok(text)
+    }
+
+    /// [`has_voice_activity`] with the engine's VAD windowing.
+    fn has_voice_activity(&self, audio: &[f32], threshold: f32) -> bool {
+        has_voice_activity(audio, threshold, self.vad_window_ms, self.vad_voiced_fraction)
+    }
+
+    /// Run the encoder and decoder over one window of audio, after feeding
+    /// the decoder `prompt`. The text is returned unfiltered.
+    fn decode_window(
+        &mut self,
+        audio: &[f32],
+        strategy: DecodeStrategy,
+        repetition_penalty: f32,
+        prompt: &[i64],
+    ) -> Result<String, AppError> {
+        let encoded = self.encode(audio)?;
+
+        let audio_seconds = audio.len() as f64 / SAMPLE_RATE as f64;
+        let max_len = ((audio_seconds * 6.0) as usize)
+            .min(self.config.max_position_embeddings)
+            .max(1);
+        // Start token + prompt + output must fit the decoder's positions
+        let room = self.config.max_position_embeddings.saturating_sub(max_len + 1);
+        let prompt = &prompt[prompt.len().saturating_sub(room)..];
+
+        let token_ids = match strategy {
+            DecodeStrategy::Greedy => self.decode_greedy(&encoded, max_len, repetition_penalty, prompt)?,
+            DecodeStrategy::Beam { width } => {
+                self.decode_beam(&encoded, max_len, width.max(1), repetition_penalty, prompt)?
+            }
+        };
+
+        let token_ids: Vec<u32> = token_ids.iter().map(|&t| t as u32).collect();
+        let text = self.tokenizer
+            .decode(&token_ids, true)
+            .map_err(|e| AppError::Transcription(format!("Tokenizer decode error: {e}")))?;
 
+        Ok(text.trim().to_string())
+    }
+
+    /// Run the encoder over `audio`. Its hidden states are shared by every
+    /// decoder step and never mutated.
+    fn encode(&mut self, audio: &[f32]) -> Result<EncoderOutput, AppError> {
         let normalized = normalize_audio(audio);
         let audio_len = normalized.len();
 
-        // 1. Run encoder: input shape [1, audio_len]
+        // Input shape [1, audio_len]
         let encoder_input = Value::from_array(([1, audio_len as i64], normalized))
             .map_err(|e| AppError::Transcription(format!("Encoder input error: {e}")))?;
 
@@ -138,28 +780,24 @@ impl MoonshineEngine {
             .run(ort::inputs!["input_values" => encoder_input])
             .map_err(|e| AppError::Transcription(format!("Encoder run error: {e}")))?;
 
-        // Extract encoder hidden states — shared across all decoder steps (never mutated)
         let (enc_shape, enc_data) = encoder_outputs[0]
             .try_extract_tensor::<f32>()
             .map_err(|e| AppError::Transcription(format!("Encoder output extract error: {e}")))?;
-        let enc_shape_vec: Vec<i64> = enc_shape.iter().copied().collect();
-        let enc_data_vec: Vec<f32> = enc_data.to_vec();
 
-        // 2. Prepare KV cache
+        Ok(EncoderOutput {
+            shape: enc_shape.iter().copied().collect(),
+            data: enc_data.to_vec(),
+        })
+    }
+
+    /// KV cache for the first decoder step, with placeholder shape
+    /// [1, num_heads, 1, dim_kv]. ONNX Runtime requires all dimensions >= 1;
+    /// on step 0 the model uses use_cache_branch=false, so the values are ignored.
+    fn empty_kv_cache(&self) -> Vec<KvEntry> {
         let num_layers = self.config.decoder_num_hidden_layers;
         let num_heads = self.config.decoder_num_key_value_heads;
         let dim_kv = self.config.dim_kv();
 
-        let audio_seconds = audio_len as f64 / 16000.0;
-        let max_len = ((audio_seconds * 6.0) as usize)
-            .min(self.config.max_position_embeddings)
-            .max(1);
-
-        let mut generated_tokens: Vec<i64> = vec![self.config.decoder_start_token_id];
-
-        // Initialize KV cache with placeholder shape [1, num_heads, 1, dim_kv].
-        // ONNX Runtime requires all dimensions >= 1. On step 0 the model uses
-        // use_cache_branch=false, so these placeholder values are ignored.
         let mut kv_cache: Vec<KvEntry> = Vec::new();
         for layer in 0..num_layers {
             for module in &["decoder", "encoder"] {
@@ -172,108 +810,339 @@ impl MoonshineEngine {
                 }
             }
         }
+        kv_cache
+    }
 
-        // 3. Autoregressive decoding
-        for step in 0..max_len {
-            let use_cache = step > 0;
-            let last_token = *generated_tokens.last().unwrap();
-
-            // Build inputs as Vec<(name, Value)>
-            let input_ids_val = Value::from_array(([1i64, 1], vec![last_token]))
-                .map_err(|e| AppError::Transcription(format!("Input IDs error: {e}")))?;
-
-            // Re-wrap the same data without cloning the full tensor — ort requires
-            // owned Vec, so we must clone, but we pre-allocated enc_data_vec once.
-            // Future: if ort adds Value::from_slice this clone can be removed entirely.
-            let enc_hs_val = Value::from_array((enc_shape_vec.as_slice(), enc_data_vec.clone()))
-                .map_err(|e| AppError::Transcription(format!("Encoder HS error: {e}")))?;
-
-            let cache_flag_val = Value::from_array(([1i64], vec![use_cache]))
-                .map_err(|e| AppError::Transcription(format!("Cache flag error: {e}")))?;
-
-            let mut inputs: Vec<(String, ort::value::DynValue)> = vec![
-                ("input_ids".into(), input_ids_val.into_dyn()),
-                ("encoder_hidden_states".into(), enc_hs_val.into_dyn()),
-                ("use_cache_branch".into(), cache_flag_val.into_dyn()),
-            ];
-
-            for entry in &kv_cache {
-                let val = Value::from_array((entry.shape.as_slice(), entry.data.clone()))
-                    .map_err(|e| AppError::Transcription(format!("KV cache error for {}: {e}", entry.name)))?;
-                inputs.push((entry.name.clone(), val.into_dyn()));
+    /// Feed `token` to the decoder at position `step`, updating `kv_cache`
+    /// in place, and return the logits for the next token.
+    fn decoder_step(
+        &mut self,
+        encoded: &EncoderOutput,
+        token: i64,
+        step: usize,
+        kv_cache: &mut [KvEntry],
+    ) -> Result<Vec<f32>, AppError> {
+        let use_cache = step > 0;
+
+        // Build inputs as Vec<(name, Value)>
+        let input_ids_val = Value::from_array(([1i64, 1], vec![token]))
+            .map_err(|e| AppError::Transcription(format!("Input IDs error: {e}")))?;
+
+        // Re-wrap the same data without cloning the full tensor — ort requires
+        // owned Vec, so we must clone, but the encoder output is extracted once.
+        // Future: if ort adds Value::from_slice this clone can be removed entirely.
+        let enc_hs_val = Value::from_array((encoded.shape.as_slice(), encoded.data.clone()))
+            .map_err(|e| AppError::Transcription(format!("Encoder HS error: {e}")))?;
+
+        let cache_flag_val = Value::from_array(([1i64], vec![use_cache]))
+            .map_err(|e| AppError::Transcription(format!("Cache flag error: {e}")))?;
+
+        let mut inputs: Vec<(String, ort::value::DynValue)> = vec![
+            ("input_ids".into(), input_ids_val.into_dyn()),
+            ("encoder_hidden_states".into(), enc_hs_val.into_dyn()),
+            ("use_cache_branch".into(), cache_flag_val.into_dyn()),
+        ];
+
+        for entry in kv_cache.iter() {
+            let val = Value::from_array((entry.shape.as_slice(), entry.data.clone()))
+                .map_err(|e| AppError::Transcription(format!("KV cache error for {}: {e}", entry.name)))?;
+            inputs.push((entry.name.clone(), val.into_dyn()));
+        }
+
+        let decoder_outputs = self.decoder_session
+            .run(inputs)
+            .map_err(|e| AppError::Transcription(format!("Decoder run error at step {step}: {e}")))?;
+
+        // Extract logits
+        let (logits_shape, logits_data) = decoder_outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(|e| AppError::Transcription(format!("Logits extract error: {e}")))?;
+
+        let vocab_size: usize = *logits_shape.last().unwrap_or(&1) as usize;
+        let offset: usize = logits_data.len().saturating_sub(vocab_size);
+        let logits = logits_data[offset..].to_vec();
+
+        // Update KV cache
+        for (j, entry) in kv_cache.iter_mut().enumerate() {
+            let output_idx = j + 1;
+            if output_idx < decoder_outputs.len() {
+                // For encoder KV: only update on first step
+                // For decoder KV: always update
+                if !use_cache || entry.name.contains("decoder") {
+                    let (shape, data) = decoder_outputs[output_idx]
+                        .try_extract_tensor::<f32>()
+                        .map_err(|e| AppError::Transcription(format!("KV output error: {e}")))?;
+                    entry.shape = shape.iter().copied().collect::<Vec<i64>>();
+                    entry.data = data.to_vec();
+                }
             }
+        }
 
-            let decoder_outputs = self.decoder_session
-                .run(inputs)
-                .map_err(|e| AppError::Transcription(format!("Decoder run error at step {step}: {e}")))?;
+        Ok(logits)
+    }
 
-            // Extract logits
-            let (logits_shape, logits_data) = decoder_outputs[0]
-                .try_extract_tensor::<f32>()
-                .map_err(|e| AppError::Transcription(format!("Logits extract error: {e}")))?;
+    /// Feed the start token and all but the last `prompt` token, so
+    /// `kv_cache` holds the prompt as context. Returns the token and step
+    /// decoding continues from.
+    fn prime(
+        &mut self,
+        encoded: &EncoderOutput,
+        prompt: &[i64],
+        kv_cache: &mut [KvEntry],
+    ) -> Result<(i64, usize), AppError> {
+        let mut token = self.config.decoder_start_token_id;
+        for (step, &next) in prompt.iter().enumerate() {
+            self.decoder_step(encoded, token, step, kv_cache)?;
+            token = next;
+        }
+        Ok((token, prompt.len()))
+    }
 
-            let vocab_size: usize = *logits_shape.last().unwrap_or(&1) as usize;
-            let offset: usize = logits_data.len().saturating_sub(vocab_size);
-            let next_token: i64 = logits_data[offset..]
-                .iter()
-                .enumerate()
-                .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-                .map_or(self.config.eos_token_id, |(i, _)| i as i64);
+    /// Take the most likely token at every step. Returns the generated
+    /// tokens without the start token or prompt.
+    fn decode_greedy(
+        &mut self,
+        encoded: &EncoderOutput,
+        max_len: usize,
+        repetition_penalty: f32,
+        prompt: &[i64],
+    ) -> Result<Vec<i64>, AppError> {
+        let eos = self.config.eos_token_id;
+        let mut kv_cache = self.empty_kv_cache();
+        let (start, first_step) = self.prime(encoded, prompt, &mut kv_cache)?;
 
-            if next_token == self.config.eos_token_id {
-                break;
+        greedy_decode(start, first_step, eos, max_len, repetition_penalty, |token, step| {
+            self.decoder_step(encoded, token, step, &mut kv_cache)
+        })
+    }
+
+    /// Keep the `width` most likely hypotheses by cumulative log-probability,
+    /// expanding each by its `width` best tokens per step. Returns the tokens
+    /// of the best finished hypothesis (by log-probability per token, so
+    /// short hypotheses aren't favoured), without the start token or prompt.
+    fn decode_beam(
+        &mut self,
+        encoded: &EncoderOutput,
+        max_len: usize,
+        width: usize,
+        repetition_penalty: f32,
+        prompt: &[i64],
+    ) -> Result<Vec<i64>, AppError> {
+        let eos = self.config.eos_token_id;
+        let mut kv_cache = self.empty_kv_cache();
+        let (start, first_step) = self.prime(encoded, prompt, &mut kv_cache)?;
+        let mut beams = vec![Hypothesis { tokens: Vec::new(), log_prob: 0.0, kv_cache }];
+        let mut finished: Vec<(Vec<i64>, f32)> = Vec::new();
+
+        for step in 0..max_len {
+            // (beam index, token, cumulative log-prob)
+            let mut candidates: Vec<(usize, i64, f32)> = Vec::with_capacity(beams.len() * width);
+            for (i, beam) in beams.iter_mut().enumerate() {
+                let last_token = beam.tokens.last().copied().unwrap_or(start);
+                let mut logits =
+                    self.decoder_step(encoded, last_token, first_step + step, &mut beam.kv_cache)?;
+                apply_repetition_penalty(&mut logits, &beam.tokens, repetition_penalty);
+                let log_probs = log_softmax(&logits);
+                for token in top_k(&log_probs, width) {
+                    candidates.push((i, token as i64, beam.log_prob + log_probs[token]));
+                }
             }
+            candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
 
-            generated_tokens.push(next_token);
-
-            // Update KV cache
-            for (j, entry) in kv_cache.iter_mut().enumerate() {
-                let output_idx = j + 1;
-                if output_idx < decoder_outputs.len() {
-                    // For encoder KV: only update on first step
-                    // For decoder KV: always update
-                    if !use_cache || entry.name.contains("decoder") {
-                        let (shape, data) = decoder_outputs[output_idx]
-                            .try_extract_tensor::<f32>()
-                            .map_err(|e| AppError::Transcription(format!("KV output error: {e}")))?;
-                        entry.shape = shape.iter().copied().collect::<Vec<i64>>();
-                        entry.data = data.to_vec();
-                    }
+            let mut next: Vec<Hypothesis> = Vec::with_capacity(width);
+            for (i, token, log_prob) in candidates {
+                if next.len() == width {
+                    break;
+                }
+                let parent = &beams[i];
+                if token == eos {
+                    finished.push((parent.tokens.clone(), log_prob));
+                } else {
+                    let mut tokens = parent.tokens.clone();
+                    tokens.push(token);
+                    next.push(Hypothesis { tokens, log_prob, kv_cache: parent.kv_cache.clone() });
                 }
             }
+            beams = next;
+
+            if beams.is_empty() || finished.len() >= width {
+                break;
+            }
         }
 
-        // 4. Decode tokens
-        let token_ids: Vec<u32> = generated_tokens.iter()
-            .skip(1)
-            .map(|&t| t as u32)
-            .collect();
+        // Hypotheses cut off by `max_len` compete only if none finished
+        if finished.is_empty() {
+            finished = beams.into_iter().map(|b| (b.tokens, b.log_prob)).collect();
+        }
 
-        let text = self.tokenizer
-            .decode(&token_ids, true)
-            .map_err(|e| AppError::Transcription(format!("Tokenizer decode error: {e}")))?;
+        let per_token = |(tokens, log_prob): &(Vec<i64>, f32)| log_prob / (tokens.len() + 1) as f32;
+        Ok(finished
+            .into_iter()
+            .max_by(|a, b| per_token(a).total_cmp(&per_token(b)))
+            .map(|(tokens, _)| tokens)
+            .unwrap_or_default())
+    }
+}
 
-        let trimmed = text.trim().to_string();
+/// Greedy decoding loop. `next_logits(token, step)` feeds `token` to the
+/// decoder and returns the logits for the following one; `start` is fed at
+/// `first_step`. Returns the generated tokens without `start`.
+fn greedy_decode<F>(
+    start: i64,
+    first_step: usize,
+    eos: i64,
+    max_len: usize,
+    repetition_penalty: f32,
+    mut next_logits: F,
+) -> Result<Vec<i64>, AppError>
+where
+    F: FnMut(i64, usize) -> Result<Vec<f32>, AppError>,
+{
+    let mut tokens: Vec<i64> = Vec::new();
+    let mut last_token = start;
 
-        if is_hallucination(&trimmed) {
-            return Ok(String::new());
+    for step in first_step..first_step + max_len {
+        let mut logits = next_logits(last_token, step)?;
+        apply_repetition_penalty(&mut logits, &tokens, repetition_penalty);
+        let next_token = argmax(&logits).map_or(eos, |i| i as i64);
+
+        if next_token == eos {
+            break;
         }
 
-        Ok(trimmed)
+        tokens.push(next_token);
+        last_token = next_token;
+    }
+
+    Ok(tokens)
+}
+
+/// Make tokens already in `generated` less likely: positive logits are
+/// divided by `penalty`, negative ones multiplied, so both move down.
+/// Each token is penalized once however often it occurred.
+fn apply_repetition_penalty(logits: &mut [f32], generated: &[i64], penalty: f32) {
+    if penalty <= 1.0 {
+        return;
+    }
+    let mut seen = std::collections::HashSet::new();
+    for &token in generated {
+        let Some(logit) = usize::try_from(token).ok().and_then(|t| logits.get_mut(t)) else {
+            continue;
+        };
+        if seen.insert(token) {
+            *logit = if *logit > 0.0 { *logit / penalty } else { *logit * penalty };
+        }
+    }
+}
+
+/// Index of the largest logit.
+fn argmax(logits: &[f32]) -> Option<usize> {
+    logits
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(i, _)| i)
+}
+
+/// Numerically stable log-softmax.
+fn log_softmax(logits: &[f32]) -> Vec<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let log_sum = logits.iter().map(|&l| (l - max).exp()).sum::<f32>().ln() + max;
+    logits.iter().map(|&l| l - log_sum).collect()
+}
+
+/// Indices of the `k` largest values, in no particular order.
+fn top_k(values: &[f32], k: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..values.len()).collect();
+    if k < indices.len() {
+        indices.select_nth_unstable_by(k, |&a, &b| values[b].total_cmp(&values[a]));
+        indices.truncate(k);
     }
+    indices
 }
 
-/// Simple RMS voice activity detection.
-fn has_voice_activity(audio: &[f32]) -> bool {
-    const VAD_RMS_THRESHOLD: f32 = 0.015;
+/// Windowed RMS voice activity detection: `audio` passes when at least
+/// `min_voiced_fraction` of its `window_ms` windows, and never fewer than
+/// one, reach `threshold`. A threshold of `0.0` always passes.
+fn has_voice_activity(audio: &[f32], threshold: f32, window_ms: u32, min_voiced_fraction: f32) -> bool {
     const STEP: usize = 4;
 
-    let (sum_sq, count) = audio.iter().step_by(STEP).fold(
-        (0.0f64, 0usize),
-        |(sum, cnt), &s| (sum + (s as f64) * (s as f64), cnt + 1),
-    );
-    let rms = (sum_sq / count.max(1) as f64).sqrt() as f32;
-    rms >= VAD_RMS_THRESHOLD
+    if threshold <= 0.0 {
+        return true;
+    }
+
+    let rms = |window: &[f32]| {
+        let (sum_sq, count) = window.iter().step_by(STEP).fold(
+            (0.0f64, 0usize),
+            |(sum, cnt), &s| (sum + (s as f64) * (s as f64), cnt + 1),
+        );
+        (sum_sq / count.max(1) as f64).sqrt() as f32
+    };
+
+    let window = (window_ms as usize * SAMPLE_RATE / 1000).max(STEP);
+    let windows = audio.len().div_ceil(window);
+    let required = ((windows as f32 * min_voiced_fraction).ceil() as usize).max(1);
+    audio.chunks(window).filter(|w| rms(w) >= threshold).take(required).count() == required
+}
+
+/// Drop the words at the start of `text` that repeat the end of `prev`
+/// (the overlap between two streaming windows).
+pub(super) fn strip_overlap(prev: &str, text: &str) -> String {
+    const MAX_OVERLAP_WORDS: usize = 12;
+
+    fn key(word: &str) -> String {
+        word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+    }
+
+    let prev_words: Vec<String> = prev.split_whitespace().map(key).collect();
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let max = MAX_OVERLAP_WORDS.min(prev_words.len()).min(words.len());
+
+    let overlap = (1..=max)
+        .rev()
+        .find(|&k| {
+            prev_words[prev_words.len() - k..]
+                .iter()
+                .zip(&words[..k])
+                .all(|(a, b)| *a == key(b))
+        })
+        .unwrap_or(0);
+
+    words[overlap..].join(" ")
+}
+
+/// Copy of `audio` with every 10 ms block whose RMS is below `threshold`
+/// set to zero.
+fn noise_gate(audio: &[f32], threshold: f32) -> Vec<f32> {
+    const BLOCK: usize = SAMPLE_RATE / 100;
+
+    let mut gated = audio.to_vec();
+    for block in gated.chunks_mut(BLOCK) {
+        let rms = (block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32).sqrt();
+        if rms < threshold {
+            block.fill(0.0);
+        }
+    }
+    gated
+}
+
+/// Map a requested language to one `variant` supports: `""`/`"auto"` give
+/// its default, and region suffixes (`en-US`, `en_GB`) are dropped.
+pub(super) fn resolve_language(variant: ModelVariant, language: &str) -> Result<&'static str, AppError> {
+    let supported = variant.languages();
+    let requested = language.trim();
+    if requested.is_empty() || requested.eq_ignore_ascii_case("auto") {
+        return Ok(supported[0]);
+    }
+
+    let base = requested.split(['-', '_']).next().unwrap_or(requested);
+    supported
+        .iter()
+        .find(|code| code.eq_ignore_ascii_case(base))
+        .copied()
+        .ok_or_else(|| {
+            AppError::Transcription(format!("language {requested} not supported by this model"))
+        })
 }
 
 /// Normalize audio to target peak.
@@ -289,8 +1158,9 @@ fn normalize_audio(audio: &[f32]) -> Vec<f32> {
     audio.iter().map(|s| s * scale).collect()
 }
 
-/// Detect hallucinated ASR output (repetitive phrases).
-fn is_hallucination(text: &str) -> bool {
+/// Detect hallucinated ASR output (repetitive phrases): too few distinct
+/// words, or a 3-word phrase repeated `max_ngram_repeats` times.
+fn is_hallucination(text: &str, min_unique_ratio: f64, max_ngram_repeats: u32) -> bool {
     if text.len() < 20 {
         return false;
     }
@@ -307,7 +1177,7 @@ fn is_hallucination(text: &str) -> bool {
 
     // Low unique word ratio
     let unique: std::collections::HashSet<&str> = words.iter().copied().collect();
-    if (unique.len() as f64 / words.len() as f64) < 0.25 {
+    if (unique.len() as f64 / words.len() as f64) < min_unique_ratio {
         return true;
     }
 
@@ -317,10 +1187,117 @@ fn is_hallucination(text: &str) -> bool {
     for window in words.windows(3) {
         let count = ngrams.entry((window[0], window[1], window[2])).or_insert(0);
         *count += 1;
-        if *count >= 3 {
+        if *count >= max_ngram_repeats {
             return true;
         }
     }
 
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: i64 = 3;
+    const EOS: i64 = 0;
+
+    #[test]
+    fn repetition_penalty_breaks_greedy_loop() {
+        // Token 1 always narrowly beats EOS, so plain greedy decoding
+        // repeats it until `max_len`
+        let looping_logits = |_token: i64, _step: usize| Ok(vec![2.0, 2.1, 0.5, -1.0]);
+
+        let looped = greedy_decode(START, 0, EOS, 20, 1.0, looping_logits).unwrap();
+        assert_eq!(looped, vec![1; 20]);
+
+        let penalized =
+            greedy_decode(START, 0, EOS, 20, DEFAULT_REPETITION_PENALTY, looping_logits).unwrap();
+        assert_eq!(penalized, vec![1]);
+    }
+
+    #[test]
+    fn greedy_decode_continues_after_the_prompt() {
+        let mut seen = Vec::new();
+        let tokens = greedy_decode(7, 3, EOS, 2, 1.0, |token, step| {
+            seen.push((token, step));
+            Ok(vec![0.0, 1.0, 0.5, -1.0])
+        })
+        .unwrap();
+
+        // The last prompt token is fed at the step after the primed ones
+        assert_eq!(seen, vec![(7, 3), (1, 4)]);
+        assert_eq!(tokens, vec![1, 1]);
+    }
+
+    #[test]
+    fn hallucination_thresholds_are_tunable() {
+        let looped = "thank you for watching thank you for watching thank you for watching";
+        assert!(is_hallucination(looped, 0.25, 3));
+        assert!(!is_hallucination(looped, 0.25, 4));
+
+        let filter = HallucinationFilter { policy: HallucinationPolicy::Keep, ..Default::default() };
+        assert!(!filter.is_hallucination(looped));
+    }
+
+    #[test]
+    fn windowed_vad_ignores_a_lone_transient() {
+        // 50 ms knock in 30 s of silence: loud enough to lift the clip's
+        // overall RMS above the threshold, but only one window is voiced
+        let mut knock = vec![0.0f32; 30 * SAMPLE_RATE];
+        knock[SAMPLE_RATE..SAMPLE_RATE + SAMPLE_RATE / 20].fill(0.8);
+        let vad = |audio: &[f32], fraction| {
+            has_voice_activity(audio, DEFAULT_VAD_THRESHOLD, DEFAULT_VAD_WINDOW_MS, fraction)
+        };
+        assert!(!vad(&knock, DEFAULT_VAD_VOICED_FRACTION));
+        assert!(vad(&knock, 0.0));
+
+        // Two seconds of quiet speech-level tone are enough
+        let mut speech = vec![0.0f32; 30 * SAMPLE_RATE];
+        for (i, s) in speech[..2 * SAMPLE_RATE].iter_mut().enumerate() {
+            *s = 0.1 * (i as f32 * 0.05).sin();
+        }
+        assert!(vad(&speech, DEFAULT_VAD_VOICED_FRACTION));
+        assert!(!vad(&[], DEFAULT_VAD_VOICED_FRACTION));
+    }
+
+    #[test]
+    fn noise_gate_zeroes_only_quiet_blocks() {
+        let block = SAMPLE_RATE / 100;
+        let mut audio = vec![0.001f32; block];
+        audio.extend(std::iter::repeat_n(0.2f32, block));
+
+        let gated = noise_gate(&audio, 0.01);
+        assert!(gated[..block].iter().all(|&s| s == 0.0));
+        assert_eq!(&gated[block..], &audio[block..]);
+    }
+
+    #[test]
+    fn resolve_language_accepts_supported_codes_and_rejects_others() {
+        assert_eq!(resolve_language(ModelVariant::Base, "auto").unwrap(), "en");
+        assert_eq!(resolve_language(ModelVariant::Base, "").unwrap(), "en");
+        assert_eq!(resolve_language(ModelVariant::Base, "EN-us").unwrap(), "en");
+
+        let err = resolve_language(ModelVariant::Tiny, "es").unwrap_err();
+        assert_eq!(err.to_string(), "Transcription error: language es not supported by this model");
+    }
+
+    #[test]
+    fn load_errors_point_at_the_runtime_or_the_model() {
+        let classify = |code, message, file_len| load_error(code, message, file_len, String::new()).code();
+        let big = Some(30 * 1024 * 1024);
+
+        assert_eq!(classify(ErrorCode::NoSuchFile, "", None), "MODEL_FILES_MISSING");
+        assert_eq!(classify(ErrorCode::GenericFailure, "", Some(512)), "MODEL_CORRUPT");
+        assert_eq!(classify(ErrorCode::InvalidProtobuf, "", big), "MODEL_CORRUPT");
+        assert_eq!(
+            classify(ErrorCode::GenericFailure, "Load model failed: Protobuf parsing failed.", big),
+            "MODEL_CORRUPT"
+        );
+        assert_eq!(
+            classify(ErrorCode::GenericFailure, "Opset 23 is under development", big),
+            "ORT_RUNTIME_UNAVAILABLE"
+        );
+        assert_eq!(classify(ErrorCode::RuntimeException, "out of memory", big), "TRANSCRIPTION_ERROR");
+    }
+}
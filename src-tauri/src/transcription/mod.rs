@@ -1,5 +1,11 @@
 mod engine;
 mod model_manager;
+mod session;
+mod subtitles;
 
-pub use engine::MoonshineEngine;
-pub use model_manager::ModelManager;
+pub use engine::{
+    DecodeStrategy, ExecutionProvider, HallucinationFilter, MoonshineEngine, Segment, Transcript,
+};
+pub use model_manager::{DiskSpace, ModelManager, ModelVariant};
+pub use session::TranscriptionSession;
+pub use subtitles::{write_subtitles, SubtitleFormat};
@@ -0,0 +1,117 @@
+use crate::error::AppError;
+use super::engine::{resolve_language, strip_overlap, SAMPLE_RATE};
+use super::MoonshineEngine;
+
+/// New audio a session waits for before decoding again.
+const STEP_SECS: usize = 5;
+
+/// Already decoded audio repeated at the start of the next decode, so a word
+/// cut at the boundary is heard whole once.
+const OVERLAP_SECS: usize = 2;
+
+/// Rolling transcription of audio that arrives in pieces, for live captions.
+///
+/// [`feed`](Self::feed) buffers 16 kHz mono audio and, each time
+/// `STEP_SECS` of it is new, decodes it together with the last
+/// `OVERLAP_SECS` of the previous decode. Words the previous decode already
+/// returned are dropped, so every result is only newly finalized text.
+///
+/// The engine is passed to each call rather than owned, so it stays
+/// available to other transcriptions while a session is open.
+pub struct TranscriptionSession {
+    /// Overlap kept from the last decode, followed by audio not decoded yet.
+    buffer: Vec<f32>,
+    /// Samples at the start of `buffer` that were already decoded.
+    overlap: usize,
+    /// Text of the last decode, matched against the start of the next.
+    last_text: String,
+}
+
+impl TranscriptionSession {
+    /// Start a session; fails if `engine`'s model can't transcribe `language`.
+    pub fn new(engine: &MoonshineEngine, language: &str) -> Result<Self, AppError> {
+        resolve_language(engine.variant(), language)?;
+        Ok(Self { buffer: Vec::new(), overlap: 0, last_text: String::new() })
+    }
+
+    /// Append `samples` and return the text finalized by the decodes they
+    /// completed, or `None` while audio is still accumulating (or silent).
+    pub fn feed(
+        &mut self,
+        engine: &mut MoonshineEngine,
+        samples: &[f32],
+    ) -> Result<Option<String>, AppError> {
+        self.buffer.extend_from_slice(samples);
+        self.advance(|window| engine.caption_window(window), false)
+    }
+
+    /// Decode the audio still buffered, however short, and end the session.
+    pub fn finish(mut self, engine: &mut MoonshineEngine) -> Result<Option<String>, AppError> {
+        self.advance(|window| engine.caption_window(window), true)
+    }
+
+    /// Decode every full step in `buffer`, then what's left if `flush`.
+    fn advance<F>(&mut self, mut decode: F, flush: bool) -> Result<Option<String>, AppError>
+    where
+        F: FnMut(&[f32]) -> Result<String, AppError>,
+    {
+        let step = STEP_SECS * SAMPLE_RATE;
+        let mut texts = Vec::new();
+
+        loop {
+            let fresh = self.buffer.len() - self.overlap;
+            if fresh == 0 || (fresh < step && !flush) {
+                break;
+            }
+
+            let end = self.overlap + fresh.min(step);
+            let decoded = decode(&self.buffer[..end])?;
+            let text = strip_overlap(&self.last_text, &decoded);
+            if !text.is_empty() {
+                texts.push(text);
+            }
+            self.last_text = decoded;
+
+            let keep = (OVERLAP_SECS * SAMPLE_RATE).min(end);
+            self.buffer.drain(..end - keep);
+            self.overlap = keep;
+        }
+
+        Ok((!texts.is_empty()).then(|| texts.join(" ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Audio whose every sample in second `n` is `n`, and a decoder that
+    /// "hears" one word per second of it.
+    fn seconds(range: std::ops::Range<usize>) -> Vec<f32> {
+        range.flat_map(|n| vec![n as f32; SAMPLE_RATE]).collect()
+    }
+
+    fn decode(window: &[f32]) -> String {
+        let words: Vec<String> = window.chunks(SAMPLE_RATE).map(|s| format!("s{}", s[0])).collect();
+        words.join(" ")
+    }
+
+    #[test]
+    fn each_second_is_returned_once_across_overlapping_decodes() {
+        let mut session = TranscriptionSession { buffer: Vec::new(), overlap: 0, last_text: String::new() };
+        let advance = |session: &mut TranscriptionSession, flush| session.advance(|w| Ok(decode(w)), flush);
+
+        session.buffer.extend(seconds(0..3));
+        assert_eq!(advance(&mut session, false).unwrap(), None);
+
+        // Two steps' worth at once: 0–5 s, then 3–10 s with 3–5 s stripped
+        session.buffer.extend(seconds(3..12));
+        assert_eq!(
+            advance(&mut session, false).unwrap().as_deref(),
+            Some("s0 s1 s2 s3 s4 s5 s6 s7 s8 s9")
+        );
+
+        assert_eq!(advance(&mut session, true).unwrap().as_deref(), Some("s10 s11"));
+        assert_eq!(advance(&mut session, true).unwrap(), None);
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt::Write as _;
+
+use crate::error::AppError;
+use super::Segment;
+
+/// Subtitle file format for `write_subtitles`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+}
+
+/// Serialize `segments` as SRT or WebVTT and write them to `path`.
+///
+/// An empty segment list still produces a valid (cue-less) file.
+pub fn write_subtitles(
+    segments: &[Segment],
+    format: SubtitleFormat,
+    path: &str,
+) -> Result<(), AppError> {
+    std::fs::write(path, render(segments, format))?;
+    Ok(())
+}
+
+fn render(segments: &[Segment], format: SubtitleFormat) -> String {
+    let mut out = String::new();
+    if format == SubtitleFormat::Vtt {
+        out.push_str("WEBVTT\n\n");
+    }
+
+    let cues = segments.iter().filter(|s| !s.text.trim().is_empty());
+    for (i, segment) in cues.enumerate() {
+        let start = to_millis(segment.start);
+        let end = to_millis(segment.end).max(start);
+
+        // Writing to a String cannot fail
+        let _ = match format {
+            SubtitleFormat::Srt => writeln!(
+                out,
+                "{}\n{} --> {}\n{}\n",
+                i + 1,
+                timestamp(start, ','),
+                timestamp(end, ','),
+                escape_srt(&segment.text),
+            ),
+            SubtitleFormat::Vtt => writeln!(
+                out,
+                "{} --> {}\n{}\n",
+                timestamp(start, '.'),
+                timestamp(end, '.'),
+                escape_vtt(&segment.text),
+            ),
+        };
+    }
+
+    out
+}
+
+fn to_millis(seconds: f64) -> u64 {
+    (seconds.max(0.0) * 1000.0).round() as u64
+}
+
+/// `HH:MM:SS<sep>mmm` — SRT uses `,`, WebVTT uses `.`.
+fn timestamp(ms: u64, sep: char) -> String {
+    let (h, rest) = (ms / 3_600_000, ms % 3_600_000);
+    let (m, rest) = (rest / 60_000, rest % 60_000);
+    let (s, ms) = (rest / 1000, rest % 1000);
+    format!("{h:02}:{m:02}:{s:02}{sep}{ms:03}")
+}
+
+/// A blank line ends a cue in both formats, so cue text is kept on one line.
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape_srt(text: &str) -> String {
+    // `-->` inside text is parsed as a timing line by some players
+    single_line(text).replace("-->", "->")
+}
+
+fn escape_vtt(text: &str) -> String {
+    single_line(text)
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f64, end: f64, text: &str) -> Segment {
+        Segment { start, end, text: text.into() }
+    }
+
+    #[test]
+    fn srt_numbers_cues_from_one_with_comma_timestamps() {
+        // The blank segment is skipped without leaving a gap in the numbering
+        let segments = [segment(0.0, 1.5, "Hello"), segment(1.5, 2.0, "  "), segment(2.25, 4.0, "world")];
+        assert_eq!(
+            render(&segments, SubtitleFormat::Srt),
+            "1\n00:00:00,000 --> 00:00:01,500\nHello\n\n2\n00:00:02,250 --> 00:00:04,000\nworld\n\n",
+        );
+    }
+
+    #[test]
+    fn vtt_starts_with_the_header_and_uses_dot_timestamps() {
+        let segments = [segment(0.0, 1.5, "Hello"), segment(2.25, 4.0, "world")];
+        assert_eq!(
+            render(&segments, SubtitleFormat::Vtt),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:01.500\nHello\n\n00:00:02.250 --> 00:00:04.000\nworld\n\n",
+        );
+    }
+
+    #[test]
+    fn timestamps_round_to_the_millisecond_and_count_hours() {
+        assert_eq!(timestamp(to_millis(3_723.456_7), ','), "01:02:03,457");
+        assert_eq!(timestamp(to_millis(36_000.0), '.'), "10:00:00.000");
+        // Rounding carries into the next second and minute
+        assert_eq!(timestamp(to_millis(59.999_6), ','), "00:01:00,000");
+        assert_eq!(to_millis(-0.5), 0);
+    }
+
+    #[test]
+    fn an_end_before_the_start_is_clamped_to_it() {
+        let srt = render(&[segment(5.0, 4.0, "late")], SubtitleFormat::Srt);
+        assert!(srt.contains("00:00:05,000 --> 00:00:05,000"), "{srt}");
+    }
+
+    #[test]
+    fn an_empty_segment_list_renders_a_cueless_file() {
+        assert_eq!(render(&[], SubtitleFormat::Srt), "");
+        assert_eq!(render(&[], SubtitleFormat::Vtt), "WEBVTT\n\n");
+    }
+
+    #[test]
+    fn cue_text_is_one_line_without_arrows() {
+        let segments = [segment(0.0, 1.0, "a --> b\n\nc <i> & d")];
+        assert_eq!(
+            render(&segments, SubtitleFormat::Srt),
+            "1\n00:00:00,000 --> 00:00:01,000\na -> b c <i> & d\n\n",
+        );
+        assert_eq!(
+            render(&segments, SubtitleFormat::Vtt),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:01.000\na --&gt; b c &lt;i&gt; &amp; d\n\n",
+        );
+    }
+}
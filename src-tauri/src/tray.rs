@@ -1,18 +1,25 @@
 use tauri::{
+    image::Image,
     menu::{Menu, MenuItem, PredefinedMenuItem},
     tray::TrayIconBuilder,
-    App, Manager,
+    App, AppHandle, Manager, Wry,
 };
 
-/// Set up the system tray with Show / Hide / Quit menu items.
+use crate::audio::CaptureConfig;
+use crate::commands;
+use crate::AudioCaptureState;
+
+const TRAY_ID: &str = "main-tray";
+
+/// App icon with a red dot, shown while recording.
+const RECORDING_ICON: Image<'static> = tauri::include_image!("./icons/tray-recording.png");
+
+/// Set up the system tray with Show / Hide, Start / Stop Recording and
+/// Quit menu items.
 pub fn setup(app: &App) -> Result<(), Box<dyn std::error::Error>> {
-    let show_item = MenuItem::with_id(app, "show", "Show Window", true, None::<&str>)?;
-    let hide_item = MenuItem::with_id(app, "hide", "Hide Window", true, None::<&str>)?;
-    let separator = PredefinedMenuItem::separator(app)?;
-    let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-    let menu = Menu::with_items(app, &[&show_item, &hide_item, &separator, &quit_item])?;
+    let menu = build_menu(app.handle(), false)?;
 
-    TrayIconBuilder::with_id("main-tray")
+    TrayIconBuilder::with_id(TRAY_ID)
         .icon(app.default_window_icon().unwrap().clone())
         .tooltip("Recogning")
         .menu(&menu)
@@ -29,6 +36,8 @@ pub fn setup(app: &App) -> Result<(), Box<dyn std::error::Error>> {
                     let _ = window.hide();
                 }
             }
+            "start_recording" => start_recording(app),
+            "stop_recording" => stop_recording(app),
             "quit" => app.exit(0),
             _ => {}
         })
@@ -36,3 +45,86 @@ pub fn setup(app: &App) -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Only the recording item that applies is enabled.
+fn build_menu(app: &AppHandle, recording: bool) -> tauri::Result<Menu<Wry>> {
+    let show_item = MenuItem::with_id(app, "show", "Show Window", true, None::<&str>)?;
+    let hide_item = MenuItem::with_id(app, "hide", "Hide Window", true, None::<&str>)?;
+    let start_label = if recording { "Recording…" } else { "Start Recording" };
+    let start_item = MenuItem::with_id(app, "start_recording", start_label, !recording, None::<&str>)?;
+    let stop_item = MenuItem::with_id(app, "stop_recording", "Stop Recording", recording, None::<&str>)?;
+    let window_separator = PredefinedMenuItem::separator(app)?;
+    let recording_separator = PredefinedMenuItem::separator(app)?;
+    let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    Menu::with_items(
+        app,
+        &[
+            &show_item,
+            &hide_item,
+            &window_separator,
+            &start_item,
+            &stop_item,
+            &recording_separator,
+            &quit_item,
+        ],
+    )
+}
+
+/// Whether a file recording is running.
+pub fn is_recording(app: &AppHandle) -> bool {
+    app.state::<AudioCaptureState>()
+        .0
+        .lock()
+        .is_ok_and(|capture| capture.is_some())
+}
+
+/// Rebuild the tray menu and swap the icon to match the current recording
+/// state. Must not be called while holding the `AudioCaptureState` lock.
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let recording = is_recording(app);
+
+    match build_menu(app, recording) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => eprintln!("[tray] Failed to rebuild menu: {e}"),
+    }
+
+    let icon = if recording {
+        Some(RECORDING_ICON)
+    } else {
+        app.default_window_icon().cloned()
+    };
+    // Keep whatever icon is showing rather than clearing it
+    if let Some(icon) = icon {
+        if let Err(e) = tray.set_icon(Some(icon)) {
+            eprintln!("[tray] Failed to set icon: {e}");
+        }
+    }
+    let _ = tray.set_tooltip(Some(if recording { "Recogning — recording" } else { "Recogning" }));
+}
+
+/// Start a default file recording in the background.
+pub fn start_recording(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AudioCaptureState>();
+        if let Err(e) = commands::start_capture(app.clone(), &state, CaptureConfig::default(), None).await {
+            eprintln!("[tray] Start recording failed: {e}");
+        }
+    });
+}
+
+/// Stop the file recording in the background.
+pub fn stop_recording(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AudioCaptureState>();
+        if let Err(e) = commands::stop_capture(&app, &state).await {
+            eprintln!("[tray] Stop recording failed: {e}");
+        }
+    });
+}
@@ -13,6 +13,6 @@ export async function transcribeNativeChunk(
   language: string,
 ): Promise<string> {
   const audioArray = Array.from(merged);
-  const raw = await nativeTranscriptionTranscribe(audioArray, language);
-  return toTranscriptionText(raw);
+  const { text } = await nativeTranscriptionTranscribe(audioArray, language);
+  return toTranscriptionText(text);
 }
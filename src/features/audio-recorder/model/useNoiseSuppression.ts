@@ -63,7 +63,7 @@ export function useNoiseSuppression(): NoiseSuppressionState & NoiseSuppressionA
         if (IS_TAURI && nativeWavPath) {
           // Desktop path: call Rust enhance_audio command
           setProgress(30);
-          const enhancedPath = await enhanceAudio(
+          const { output_path: enhancedPath } = await enhanceAudio(
             nativeWavPath,
             intensity / 100, // UI is 0-100, Rust expects 0.0-1.0
             normalize,
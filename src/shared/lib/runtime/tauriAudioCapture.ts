@@ -8,11 +8,160 @@ function assertTauri(feature: string): void {
 
 export interface AudioLevelEvent {
   level: number;
+  /** RMS of each channel in file order (L, R, …), up to 8 channels. */
+  channel_levels: number[];
+  /** Largest absolute sample since the last event; near 1 means clipping. */
+  peak: number;
+  system_level: number | null;
+  mic_level: number | null;
 }
 
-export async function startNativeSystemAudioCapture(): Promise<string> {
+export interface AudioSpectrumEvent {
+  /** 32 log-spaced bands, lowest first, each 0 (−80 dBFS) to 1 (0 dBFS). */
+  bands: number[];
+}
+
+export interface ClippingEvent {
+  /** Samples at or above full scale since the previous event. */
+  clipped_samples: number;
+  timestamp_ms: number;
+}
+
+export interface CaptureStatsEvent {
+  frames_written: number;
+  elapsed_ms: number;
+  glitch_count: number;
+  current_file_bytes: number;
+}
+
+export interface DeviceChangedEvent {
+  device_id: string;
+  device_name: string;
+  sample_rate: number;
+}
+
+/** Sent once per stall when ~3 s pass with no audio arriving (usually nothing is playing). */
+export interface CaptureTimeoutEvent {
+  consecutive_timeouts: number;
+}
+
+/** The device's mix format changed mid-recording; the file keeps its format. */
+export interface FormatChangedEvent {
+  sample_rate: number;
+  channels: number;
+  bits_per_sample: number;
+  is_float: boolean;
+}
+
+/** Sent when a file recording starts or stops, including from the tray. */
+export interface RecordingStateEvent {
+  recording: boolean;
+  /** The finished file, when a recording just stopped. */
+  output_path: string | null;
+}
+
+export interface RecordingStatus {
+  recording: boolean;
+  elapsed_ms: number;
+  output_path: string | null;
+}
+
+export interface HotkeyTriggeredEvent {
+  accelerator: string;
+  action: "start" | "stop";
+}
+
+export interface CaptureInfo {
+  device_id: string;
+  device_name: string;
+  mic_device_name: string | null;
+  /** Mix format the device opened with; kept unless `targetSampleRate` resamples. */
+  sample_rate: number;
+  channels: number;
+  is_float: boolean;
+  /** File being recorded to; null for memory captures. */
+  output_path: string | null;
+}
+
+export interface AudioOutputDevice {
+  id: string;
+  name: string;
+}
+
+export type BitDepth = "float32" | "pcm16";
+
+/** `"mp3"` needs a build with the `mp3` feature; otherwise starting fails with `MP3_ENCODE_ERROR`. */
+export type RecordingFormat = "wav" | "flac" | "mp3";
+
+/** What a recording stopped before any audio arrived becomes. */
+export type EmptyCapture = "pad" | "reject";
+
+export async function startNativeSystemAudioCapture(
+  deviceId?: string,
+  bitDepth?: BitDepth,
+  denoiseIntensity?: number,
+  format?: RecordingFormat,
+  spectrum?: boolean,
+  emitIntervalMs?: number,
+  targetSampleRate?: number,
+  emptyCapture?: EmptyCapture,
+  bufferBytes?: number,
+  outputPath?: string,
+  /** MP3 only: 8–320 in LAME's steps (default 192). */
+  bitrateKbps?: number,
+  /** MMCSS "Pro Audio" scheduling for the capture thread; may starve other threads. */
+  highPriority?: boolean,
+): Promise<CaptureInfo> {
   assertTauri("Native system audio capture");
-  return invoke<string>("start_system_audio_capture");
+  return invoke<CaptureInfo>("start_system_audio_capture", {
+    deviceId,
+    bitDepth,
+    denoiseIntensity,
+    format,
+    spectrum,
+    emitIntervalMs,
+    targetSampleRate,
+    emptyCapture,
+    bufferBytes,
+    outputPath,
+    bitrateKbps,
+    highPriority,
+  });
+}
+
+/** Capture preferences remembered between launches. */
+export interface CaptureSettings {
+  device_id: string | null;
+  bit_depth: BitDepth;
+  denoise_intensity: number;
+  normalize: boolean;
+}
+
+/// Saved settings, or the defaults when none were saved or the file is corrupt.
+export async function loadCaptureSettings(): Promise<CaptureSettings> {
+  assertTauri("Capture settings");
+  return invoke<CaptureSettings>("load_capture_settings");
+}
+
+export async function saveCaptureSettings(settings: CaptureSettings): Promise<void> {
+  assertTauri("Capture settings");
+  return invoke<void>("save_capture_settings", { settings });
+}
+
+export async function startNativeCombinedCapture(
+  micGain: number,
+  systemGain: number,
+  echoCancel = false,
+): Promise<CaptureInfo> {
+  assertTauri("Native combined audio capture");
+  return invoke<CaptureInfo>("start_combined_capture", { micGain, systemGain, echoCancel });
+}
+
+/// Record only one application's audio (Windows 10 2004+). With `includeTree`
+/// (default) its child processes are recorded too, as browsers need.
+export async function startNativeProcessAudioCapture(pid: number, includeTree = true): Promise<CaptureInfo> {
+  assertTauri("Native process audio capture");
+  return invoke<CaptureInfo>("start_process_audio_capture", { pid, includeTree });
 }
 
 export async function stopNativeSystemAudioCapture(): Promise<string> {
@@ -20,18 +169,280 @@ export async function stopNativeSystemAudioCapture(): Promise<string> {
   return invoke<string>("stop_system_audio_capture");
 }
 
+/// Stop the recording and delete its file ("scrap this take").
+export async function abortNativeSystemAudioCapture(): Promise<void> {
+  assertTauri("Native system audio capture");
+  return invoke<void>("abort_system_audio_capture");
+}
+
+/// Capture system audio into memory (no temp file) for short clips.
+export async function startNativeMemoryCapture(deviceId?: string): Promise<CaptureInfo> {
+  assertTauri("Native system audio capture");
+  return invoke<CaptureInfo>("start_memory_capture", { deviceId });
+}
+
+/// Stop a memory capture; resolves to mono 16 kHz samples that can be passed
+/// straight to `nativeTranscriptionTranscribe`.
+export async function stopNativeMemoryCapture(): Promise<number[]> {
+  assertTauri("Native system audio capture");
+  return invoke<number[]>("stop_memory_capture");
+}
+
+/// Capture continuously, keeping only the last `seconds` (1–300) for
+/// `saveNativeRingClip`.
+export async function startNativeRingCapture(seconds: number, deviceId?: string): Promise<CaptureInfo> {
+  assertTauri("Native system audio capture");
+  return invoke<CaptureInfo>("start_ring_capture", { seconds, deviceId });
+}
+
+/// Save what the ring capture holds right now as a WAV; resolves to its path.
+export async function saveNativeRingClip(outputPath: string): Promise<string> {
+  assertTauri("Native system audio capture");
+  return invoke<string>("save_ring_clip", { outputPath });
+}
+
+export async function stopNativeRingCapture(): Promise<void> {
+  assertTauri("Native system audio capture");
+  return invoke<void>("stop_ring_capture");
+}
+
+export async function pauseNativeCapture(keepTimeline = false): Promise<void> {
+  assertTauri("Native system audio capture");
+  return invoke<void>("pause_capture", { keepTimeline });
+}
+
+export async function resumeNativeCapture(): Promise<void> {
+  assertTauri("Native system audio capture");
+  return invoke<void>("resume_capture");
+}
+
+export async function getRecordingStatus(): Promise<RecordingStatus> {
+  assertTauri("Native system audio capture");
+  return invoke<RecordingStatus>("recording_status");
+}
+
+export interface TempRecording {
+  path: string;
+  size_bytes: number;
+  /** Last modification, Unix time in ms. */
+  modified_ms: number;
+}
+
+/// Recordings and enhanced copies left in the temp dir.
+export async function listTempRecordings(): Promise<TempRecording[]> {
+  assertTauri("Temp recording cleanup");
+  return invoke<TempRecording[]>("list_temp_recordings");
+}
+
+/// Delete temp recordings older than `olderThanMs`, skipping the active capture; resolves to the deleted paths.
+export async function cleanupTempRecordings(olderThanMs: number): Promise<string[]> {
+  assertTauri("Temp recording cleanup");
+  return invoke<string[]>("cleanup_temp_recordings", { olderThanMs });
+}
+
 export async function isNativeSystemAudioAvailable(): Promise<boolean> {
   if (!isTauriRuntime()) return false;
   try { return await invoke<boolean>("is_system_audio_available"); } catch { return false; }
 }
 
+/** Default output device details for a setup check; fields are null when unavailable. */
+export interface SystemAudioReport {
+  available: boolean;
+  default_device_name: string | null;
+  sample_rate: number | null;
+  channels: number | null;
+  is_float: boolean | null;
+}
+
+export async function getSystemAudioReport(): Promise<SystemAudioReport> {
+  assertTauri("System audio report");
+  return invoke<SystemAudioReport>("system_audio_report");
+}
+
+export async function listAudioOutputDevices(): Promise<AudioOutputDevice[]> {
+  assertTauri("Audio output device listing");
+  return invoke<AudioOutputDevice[]>("list_audio_output_devices");
+}
+
 export function convertFilePathToUrl(filePath: string): string {
   return convertFileSrc(filePath);
 }
 
-export async function enhanceAudio(inputPath: string, intensity: number, normalize: boolean): Promise<string> {
+/// Suggested cutoff for removing low-frequency rumble before denoising.
+export const DEFAULT_HIGH_PASS_HZ = 80;
+
+export type NormalizeMode =
+  | { mode: "off" }
+  | { mode: "peak" }
+  | { mode: "lufs"; target: number };
+
+export type FadeShape = "linear" | "cosine";
+
+/// Where the spectral gate learns the noise: the quietest 500 ms, or a marked region.
+export type NoiseProfile =
+  | { type: "quietest" }
+  | { type: "region"; startMs: number; endMs: number };
+
+/// RNNoise suits speech; the spectral gate suits music and ambient recordings.
+export type DenoiseMethod =
+  | { type: "rnnoise" }
+  | { type: "spectralgate"; profile?: NoiseProfile };
+
+/// Named strengths: light = 0.3, medium = 0.6, strong = 0.9.
+export type DenoisePreset = "light" | "medium" | "strong";
+
+export interface EnhanceResult {
+  output_path: string;
+  /** Intensity actually applied (0–1), after resolving a preset or clamping. */
+  intensity: number;
+}
+
+export interface EnhanceAudioOptions {
+  /// Noise suppressor (default RNNoise).
+  method?: DenoiseMethod;
+  /// Remove per-channel DC offset first (default true).
+  removeDc?: boolean;
+  /// High-pass cutoff in Hz; omit to skip the stage.
+  highPassHz?: number;
+  /// Fade in/out length in ms (default 50, 0 disables).
+  fadeMs?: number;
+  fadeShape?: FadeShape;
+  /// Soft-limit the final output so nothing clips.
+  limiter?: boolean;
+  /// Keep the original stereo image instead of duplicating the denoised mono.
+  preserveStereo?: boolean;
+}
+
+/// `intensity` is a preset or a raw 0–1 value. `normalize` accepts a boolean for
+/// peak-or-nothing (the original behaviour) or an explicit mode.
+export async function enhanceAudio(
+  inputPath: string,
+  intensity: number | DenoisePreset,
+  normalize: boolean | NormalizeMode,
+  options: EnhanceAudioOptions = {},
+): Promise<EnhanceResult> {
+  assertTauri("Audio enhancement");
+  const normalizeMode: NormalizeMode =
+    typeof normalize === "boolean" ? { mode: normalize ? "peak" : "off" } : normalize;
+  return invoke<EnhanceResult>("enhance_audio", {
+    inputPath,
+    options: {
+      intensity: typeof intensity === "number" ? Math.max(0, Math.min(1, intensity)) : intensity,
+      normalizeMode,
+      ...options,
+    },
+  });
+}
+
+/// Same settings as `enhanceAudio`, applied to only the first `seconds` (default 10, max 60).
+export async function enhancePreview(
+  inputPath: string,
+  intensity: number | DenoisePreset,
+  normalize: boolean | NormalizeMode,
+  seconds?: number,
+  options: EnhanceAudioOptions = {},
+): Promise<EnhanceResult> {
   assertTauri("Audio enhancement");
-  return invoke<string>("enhance_audio", { inputPath, intensity: Math.max(0, Math.min(1, intensity)), normalize });
+  const normalizeMode: NormalizeMode =
+    typeof normalize === "boolean" ? { mode: normalize ? "peak" : "off" } : normalize;
+  return invoke<EnhanceResult>("enhance_preview", {
+    inputPath,
+    seconds,
+    options: {
+      intensity: typeof intensity === "number" ? Math.max(0, Math.min(1, intensity)) : intensity,
+      normalizeMode,
+      ...options,
+    },
+  });
+}
+
+export async function cancelEnhance(): Promise<void> {
+  return invoke("cancel_enhance");
+}
+
+/** Split a WAV at silent gaps; returns the written segment paths in order. */
+export async function splitOnSilence(
+  inputPath: string,
+  outputDir: string,
+  options: { minSilenceMs?: number; thresholdDb?: number; paddingMs?: number } = {},
+): Promise<string[]> {
+  assertTauri("Audio splitting");
+  return invoke<string[]>("split_on_silence", { inputPath, outputDir, ...options });
+}
+
+export interface AudioFileInfo {
+  channels: number;
+  sample_rate: number;
+  bits_per_sample: number;
+  is_float: boolean;
+  duration_ms: number;
+}
+
+/** Format and duration of a WAV file, read from its header. */
+export async function probeAudioFile(path: string): Promise<AudioFileInfo> {
+  assertTauri("Audio file inspection");
+  return invoke<AudioFileInfo>("probe_audio_file", { path });
+}
+
+/** Join WAV files with the same channels and sample rate into `outputPath`. */
+export async function concatAudioFiles(inputs: string[], outputPath: string): Promise<string> {
+  assertTauri("Audio concatenation");
+  return invoke<string>("concat_audio_files", { inputs, outputPath });
+}
+
+export interface TranscodeResult {
+  output_path: string;
+  size_bytes: number;
+}
+
+/** Re-encode a WAV as FLAC/MP3/WAV, keeping channels and sample rate; `bitrateKbps` is MP3-only. */
+export async function transcodeAudio(
+  inputPath: string,
+  outputPath: string,
+  codec: RecordingFormat,
+  bitrateKbps?: number,
+): Promise<TranscodeResult> {
+  assertTauri("Audio transcoding");
+  return invoke<TranscodeResult>("transcode_audio", { inputPath, outputPath, codec, bitrateKbps });
+}
+
+/** Write a copy of `inputPath` scaled by `gainDb`; peaks past full scale are soft-clipped. */
+export async function adjustGain(inputPath: string, outputPath: string, gainDb: number): Promise<string> {
+  assertTauri("Audio gain adjustment");
+  return invoke<string>("adjust_gain", { inputPath, outputPath, gainDb });
+}
+
+/** Write a single-channel copy of `inputPath` (channels averaged). */
+export async function convertToMono(inputPath: string, outputPath: string): Promise<string> {
+  assertTauri("Mono conversion");
+  return invoke<string>("convert_to_mono", { inputPath, outputPath });
+}
+
+/** Progress of a model download, enhancement or transcription. */
+export interface OperationProgressEvent {
+  operation: "model-download" | "enhance" | "transcribe";
+  /** Step within the operation, e.g. the file being downloaded. */
+  stage: string;
+  done: number;
+  /** 0 when unknown. */
+  total: number;
+}
+
+export async function listenToOperationProgress(
+  callback: (progress: OperationProgressEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<OperationProgressEvent>("operation-progress", (event) => {
+    callback(event.payload);
+  });
+}
+
+/// Enhancement progress as a 0–1 fraction.
+export async function listenToEnhanceProgress(
+  callback: (progress: number) => void,
+): Promise<UnlistenFn> {
+  return listenToOperationProgress(({ operation, done, total }) => {
+    if (operation === "enhance" && total > 0) callback(done / total);
+  });
 }
 
 /// Subscribe to real-time audio level events from the Rust capture thread.
@@ -44,28 +455,345 @@ export async function listenToAudioLevels(
   });
 }
 
+/// Per-channel levels from the same events, for a multichannel meter.
+export async function listenToChannelLevels(
+  callback: (channelLevels: number[]) => void,
+): Promise<UnlistenFn> {
+  return listen<AudioLevelEvent>("audio-level", (event) => {
+    callback(event.payload.channel_levels);
+  });
+}
+
+export async function listenToAudioSpectrum(
+  callback: (bands: number[]) => void,
+): Promise<UnlistenFn> {
+  return listen<AudioSpectrumEvent>("audio-spectrum", (event) => {
+    callback(event.payload.bands);
+  });
+}
+
+export async function listenToCaptureTimeout(
+  callback: (event: CaptureTimeoutEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<CaptureTimeoutEvent>("capture-timeout", (event) => {
+    callback(event.payload);
+  });
+}
+
+export async function listenToClipping(
+  callback: (event: ClippingEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<ClippingEvent>("clipping-detected", (event) => {
+    callback(event.payload);
+  });
+}
+
+export async function listenToCaptureStats(
+  callback: (stats: CaptureStatsEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<CaptureStatsEvent>("capture-stats", (event) => {
+    callback(event.payload);
+  });
+}
+
+export async function listenToDeviceChanged(
+  callback: (event: DeviceChangedEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<DeviceChangedEvent>("device-changed", (event) => {
+    callback(event.payload);
+  });
+}
+
+export async function listenToFormatChanged(
+  callback: (event: FormatChangedEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<FormatChangedEvent>("format-changed", (event) => {
+    callback(event.payload);
+  });
+}
+
+export async function listenToRecordingState(
+  callback: (event: RecordingStateEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<RecordingStateEvent>("recording-state-changed", (event) => {
+    callback(event.payload);
+  });
+}
+
+export async function setRecordingHotkey(accelerator: string): Promise<void> {
+  assertTauri("Global recording hotkey");
+  return invoke<void>("set_recording_hotkey", { accelerator });
+}
+
+export async function listenToHotkeyTriggered(
+  callback: (event: HotkeyTriggeredEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<HotkeyTriggeredEvent>("hotkey-triggered", (event) => {
+    callback(event.payload);
+  });
+}
+
 // ── Native Transcription (Moonshine ONNX via Rust/ort) ──
 
+export type ModelVariant = "tiny" | "base";
+
+export type ExecutionProvider = "auto" | "cpu" | "directml" | "cuda";
+
 export interface TranscriptionModelInfo {
   loaded: boolean;
   cached: boolean;
+  variant: ModelVariant | null;
+  /** Provider the loaded model actually runs on. */
+  execution_provider: ExecutionProvider | null;
+  /** Folder of the loaded model (or, when none is loaded, the default variant's cache). */
+  model_dir: string;
 }
 
-export interface ModelDownloadProgress {
-  file_index: number;
-  total_files: number;
-  bytes_downloaded: number;
-  total_bytes: number;
+/**
+ * `stage` is the file being fetched; `done`/`total` count bytes across every file of
+ * the download, ONNX Runtime included — use for a single 0–100% bar.
+ */
+export type ModelDownloadProgress = OperationProgressEvent;
+
+/// `warmup` primes the model after loading so the first transcription is fast.
+export async function nativeTranscriptionLoadModel(warmup = false): Promise<TranscriptionModelInfo> {
+  assertTauri("Native transcription");
+  return invoke<TranscriptionModelInfo>("transcription_load_model", { warmup });
 }
 
-export async function nativeTranscriptionLoadModel(): Promise<TranscriptionModelInfo> {
+export async function nativeTranscriptionLoadModelVariant(
+  variant: ModelVariant,
+  warmup = false,
+): Promise<TranscriptionModelInfo> {
   assertTauri("Native transcription");
-  return invoke<TranscriptionModelInfo>("transcription_load_model");
+  return invoke<TranscriptionModelInfo>("transcription_load_model_variant", { variant, warmup });
 }
 
-export async function nativeTranscriptionTranscribe(audio: number[], language: string): Promise<string> {
+/** Stop the download behind a running model load; finished files stay cached. */
+export async function nativeTranscriptionCancelDownload(): Promise<void> {
   assertTauri("Native transcription");
-  return invoke<string>("transcription_transcribe", { audio, language });
+  return invoke("transcription_cancel_download");
+}
+
+/// Prime the loaded model during an idle moment; resolves to the time taken in ms.
+export async function nativeTranscriptionWarmup(): Promise<number> {
+  assertTauri("Native transcription");
+  return invoke<number>("transcription_warmup");
+}
+
+/// Load model files already copied to `dir` (no download), e.g. on air-gapped machines.
+export async function nativeTranscriptionLoadLocalModel(dir: string): Promise<TranscriptionModelInfo> {
+  assertTauri("Native transcription");
+  return invoke<TranscriptionModelInfo>("transcription_load_local_model", { dir });
+}
+
+/// Choose the provider for the next load; unloads the model if it changes.
+export async function nativeTranscriptionSetProvider(provider: ExecutionProvider): Promise<void> {
+  assertTauri("Native transcription");
+  return invoke<void>("transcription_set_provider", { provider });
+}
+
+/// Read, resample and transcribe a recorded WAV entirely on the Rust side.
+export async function nativeTranscribeWavFile(path: string): Promise<string> {
+  assertTauri("Native transcription");
+  return invoke<string>("transcribe_wav_file", { path });
+}
+
+export interface RecordedTranscript {
+  text: string;
+  /** The WAV that was transcribed (the denoised copy, if denoising was requested). */
+  wav_path: string;
+}
+
+/// Record system audio for `durationMs`, optionally denoise it, and transcribe it in one call.
+export async function recordAndTranscribe(
+  durationMs: number,
+  denoiseIntensity?: number | DenoisePreset,
+): Promise<RecordedTranscript> {
+  assertTauri("Native transcription");
+  return invoke<RecordedTranscript>("record_and_transcribe", { durationMs, denoiseIntensity });
+}
+
+/** Transcribed text and the language it was decoded as (ISO 639-1). */
+export interface Transcript {
+  text: string;
+  language: string;
+  /** Set in "flag" mode when the text looks like a repetition loop. */
+  suspected_hallucination: boolean;
+  /** "silence": no speech detected; "hallucination_filtered": output was dropped. */
+  status: TranscriptStatus;
+}
+
+export type TranscriptStatus = "ok" | "silence" | "hallucination_filtered";
+
+/** Drop (default) discards repetitive output, flag marks it, keep never checks. */
+export type HallucinationPolicy = "drop" | "flag" | "keep";
+
+export interface HallucinationFilter {
+  policy?: HallucinationPolicy;
+  /** Distinct-word share below which text is suspect (default 0.25). */
+  minUniqueRatio?: number;
+  /** Repeats of a 3-word phrase that make text suspect (default 3). */
+  maxNgramRepeats?: number;
+}
+
+/// Transcribe a WAV file without sending its samples over IPC.
+/// `language` may be "auto"; unsupported languages are rejected.
+export async function nativeTranscriptionTranscribeFile(
+  path: string,
+  language: string,
+): Promise<Transcript> {
+  assertTauri("Native transcription");
+  return invoke<Transcript>("transcription_transcribe_file", { path, language });
+}
+
+/** Greedy is fastest; beam search is steadier on hard audio. */
+export type DecodeStrategy = { type: "greedy" } | { type: "beam"; width: number };
+
+/// `vadThreshold` overrides the engine's VAD threshold for this call; 0 disables VAD.
+export async function nativeTranscriptionTranscribe(
+  audio: number[],
+  language: string,
+  vadThreshold?: number,
+  strategy?: DecodeStrategy,
+  repetitionPenalty?: number,
+  hallucination?: HallucinationFilter,
+  /** Interleaved channel count of `audio`; multi-channel input is downmixed. */
+  channels?: number,
+): Promise<Transcript> {
+  assertTauri("Native transcription");
+  return invoke<Transcript>("transcription_transcribe", {
+    audio,
+    language,
+    vadThreshold,
+    repetitionPenalty,
+    strategy,
+    hallucination,
+    channels,
+  });
+}
+
+/// Bias the decoder toward the vocabulary in `prompt` (names, jargon); the prompt
+/// itself is not part of the returned text.
+export async function nativeTranscriptionTranscribePrompted(
+  audio: number[],
+  language: string,
+  prompt: string,
+  vadThreshold?: number,
+  strategy?: DecodeStrategy,
+  repetitionPenalty?: number,
+  hallucination?: HallucinationFilter,
+): Promise<Transcript> {
+  assertTauri("Native transcription");
+  return invoke<Transcript>("transcription_transcribe_prompted", {
+    audio,
+    language,
+    prompt,
+    vadThreshold,
+    repetitionPenalty,
+    strategy,
+    hallucination,
+  });
+}
+
+export async function nativeTranscriptionSetVadThreshold(threshold: number): Promise<void> {
+  assertTauri("Native transcription");
+  return invoke<void>("transcription_set_vad_threshold", { threshold });
+}
+
+/// Measure VAD over `windowMs` windows and require `minVoicedFraction` (0–1) of them to be voiced.
+export async function nativeTranscriptionSetVadWindow(
+  windowMs: number,
+  minVoicedFraction: number,
+): Promise<void> {
+  assertTauri("Native transcription");
+  return invoke<void>("transcription_set_vad_window", { windowMs, minVoicedFraction });
+}
+
+/// Zero passages quieter than `threshold` RMS before encoding; 0 (default) disables it.
+export async function nativeTranscriptionSetNoiseGate(threshold: number): Promise<void> {
+  assertTauri("Native transcription");
+  return invoke<void>("transcription_set_noise_gate", { threshold });
+}
+
+/** One file of a batch; `error` is set (and `text` empty) if it failed. */
+export interface BatchTranscription {
+  index: number;
+  total: number;
+  path: string;
+  text: string;
+  error: string | null;
+}
+
+/// Transcribe many WAV files in one call; failed files don't stop the batch.
+export async function nativeTranscriptionTranscribeBatch(
+  paths: string[],
+  language: string,
+): Promise<BatchTranscription[]> {
+  assertTauri("Native transcription");
+  return invoke<BatchTranscription[]>("transcription_transcribe_batch", { paths, language });
+}
+
+export async function listenToBatchProgress(
+  callback: (event: BatchTranscription) => void,
+): Promise<UnlistenFn> {
+  return listen<BatchTranscription>("batch-progress", (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface TranscriptionSegment {
+  start: number;
+  end: number;
+  text: string;
+}
+
+export async function nativeTranscriptionTranscribeStreaming(
+  audio: number[],
+  language: string,
+): Promise<TranscriptionSegment[]> {
+  assertTauri("Native transcription");
+  return invoke<TranscriptionSegment[]>("transcription_transcribe_streaming", { audio, language });
+}
+
+export async function listenToTranscriptionSegments(
+  callback: (segment: TranscriptionSegment) => void,
+): Promise<UnlistenFn> {
+  return listen<TranscriptionSegment>("transcription-segment", (event) => {
+    callback(event.payload);
+  });
+}
+
+/** Open a live captioning session, replacing any open one. */
+export async function startLiveTranscription(language: string): Promise<void> {
+  assertTauri("Native transcription");
+  return invoke<void>("live_transcription_start", { language });
+}
+
+/** Add 16 kHz audio; resolves to newly finalized text every ~5 s, `null` in between. */
+export async function feedLiveTranscription(
+  samples: number[],
+  channels?: number,
+): Promise<string | null> {
+  assertTauri("Native transcription");
+  return invoke<string | null>("live_transcription_feed", { samples, channels });
+}
+
+/** Close the session, resolving to the text of the audio still buffered. */
+export async function stopLiveTranscription(): Promise<string | null> {
+  assertTauri("Native transcription");
+  return invoke<string | null>("live_transcription_stop");
+}
+
+export type SubtitleFormat = "srt" | "vtt";
+
+export async function exportSubtitles(
+  segments: TranscriptionSegment[],
+  format: SubtitleFormat,
+  outputPath: string,
+): Promise<string> {
+  assertTauri("Subtitle export");
+  return invoke<string>("export_subtitles", { segments, format, outputPath });
 }
 
 export async function nativeTranscriptionUnload(): Promise<void> {
@@ -74,14 +802,49 @@ export async function nativeTranscriptionUnload(): Promise<void> {
 }
 
 export async function nativeTranscriptionModelStatus(): Promise<TranscriptionModelInfo> {
-  if (!isTauriRuntime()) return { loaded: false, cached: false };
-  try { return await invoke<TranscriptionModelInfo>("transcription_model_status"); } catch { return { loaded: false, cached: false }; }
+  if (!isTauriRuntime()) return { loaded: false, cached: false, variant: null, execution_provider: null, model_dir: "" };
+  try { return await invoke<TranscriptionModelInfo>("transcription_model_status"); } catch { return { loaded: false, cached: false, variant: null, execution_provider: null, model_dir: "" }; }
+}
+
+export async function nativeTranscriptionVerifyCache(variant?: ModelVariant): Promise<void> {
+  assertTauri("Native transcription");
+  return invoke<void>("transcription_verify_cache", { variant });
+}
+
+export async function nativeTranscriptionCacheDir(variant?: ModelVariant): Promise<string> {
+  assertTauri("Native transcription");
+  return invoke<string>("transcription_cache_dir", { variant });
+}
+
+/// Bytes that loading `variant` would download (0 when fully cached).
+export async function nativeTranscriptionDownloadSize(variant?: ModelVariant): Promise<number> {
+  assertTauri("Native transcription");
+  return invoke<number>("transcription_download_size", { variant });
+}
+
+export interface DiskSpace {
+  required_bytes: number;
+  /** `null` when free space couldn't be queried. */
+  available_bytes: number | null;
+  sufficient: boolean;
+}
+
+/// Pre-flight check that the model download fits on the models drive.
+export async function nativeTranscriptionCheckDiskSpace(variant?: ModelVariant): Promise<DiskSpace> {
+  assertTauri("Native transcription");
+  return invoke<DiskSpace>("transcription_check_disk_space", { variant });
+}
+
+/// Returns the number of bytes freed. Fails with MODEL_IN_USE while a model is loaded.
+export async function nativeTranscriptionClearCache(variant?: ModelVariant): Promise<number> {
+  assertTauri("Native transcription");
+  return invoke<number>("transcription_clear_cache", { variant });
 }
 
 export async function listenToModelDownloadProgress(
   callback: (progress: ModelDownloadProgress) => void,
 ): Promise<UnlistenFn> {
-  return listen<ModelDownloadProgress>("model-download-progress", (event) => {
-    callback(event.payload);
+  return listenToOperationProgress((progress) => {
+    if (progress.operation === "model-download") callback(progress);
   });
 }